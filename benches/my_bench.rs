@@ -28,7 +28,7 @@ impl CompressType {
     fn compress(&self, data: &[u8]) -> Vec<u8> {
         match self {
             CompressType::None => data.to_vec(),
-            CompressType::Snap => snap::raw::Encoder::new().compress_vec(&data).unwrap(),
+            CompressType::Snap => snap::raw::Encoder::new().compress_vec(data).unwrap(),
             CompressType::Zstd(level) => zstd::encode_all(data, *level).unwrap(),
         }
     }
@@ -109,7 +109,7 @@ async fn collect_compress(mut recv: tokio::sync::mpsc::Receiver<Vec<u8>>) -> Vec
     while let Some(s) = recv.recv().await {
         res.push(s);
     }
-    return res;
+    res
 }
 async fn async_with_rayon_pool(
     pool: &ThreadPool,
@@ -2,13 +2,16 @@ use std::fs::{create_dir_all, set_permissions, Permissions};
 use std::os::unix::prelude::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use crate::key_registry::KeyRegistryConfig;
+use crate::kv::PhyTs;
 use crate::level::levels::LevelsControllerConfig;
 use crate::manifest::ManifestConfig;
 use crate::memtable::MemTableConfig;
 use crate::table::TableConfig;
-use crate::txn::TxnConfig;
+use crate::txn::{MergeOperator, TxnConfig};
 use crate::util::cache::{BlockCacheConfig, IndexCacheConfig};
 use crate::util::lock::DBLockGuardConfig;
 use crate::util::skip_list::SKL_MAX_NODE_SIZE;
@@ -17,18 +20,16 @@ use crate::vlog::ValueLogConfig;
 use anyhow::anyhow;
 use anyhow::bail;
 use log::LevelFilter;
+use parking_lot::Mutex;
 use snap::raw::Decoder;
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq)]
+#[derive(Default)]
 pub enum CompressionType {
+    #[default]
     None,
     Snappy,
     ZSTD(i32),
 }
-impl Default for CompressionType {
-    fn default() -> Self {
-        CompressionType::None
-    }
-}
 impl From<u32> for CompressionType {
     fn from(value: u32) -> Self {
         match value {
@@ -38,9 +39,9 @@ impl From<u32> for CompressionType {
         }
     }
 }
-impl Into<u32> for CompressionType {
-    fn into(self) -> u32 {
-        match self {
+impl From<CompressionType> for u32 {
+    fn from(val: CompressionType) -> Self {
+        match val {
             CompressionType::None => 0,
             CompressionType::Snappy => 1,
             CompressionType::ZSTD(_) => 2,
@@ -67,13 +68,51 @@ impl CompressionType {
 }
 impl CompressionType {
     pub(crate) fn is_none(&self) -> bool {
-        match self {
-            CompressionType::None => true,
-            _ => false,
-        }
+        matches!(self, CompressionType::None)
+    }
+}
+
+/// Source of the current time, which decides whether an entry with ttl is expired.
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+/// A clock which only moves when it is advanced, so tests can expire entries without sleeping.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(SystemTime::now())
     }
 }
+impl ManualClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
 
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock() += duration;
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock() = now;
+    }
+}
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock()
+    }
+}
 #[derive(Debug, Clone)]
 pub struct Config {
     // Usually modified Config.
@@ -86,6 +125,8 @@ pub struct Config {
     name_space_offset: Option<usize>,
     // When set, checksum will be validated for each entry read from the value log file.
     verify_value_checksum: bool,
+    clock: Arc<dyn Clock>,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
 
     pub memtable: MemTableConfig,
     pub block_cache: BlockCacheConfig,
@@ -118,6 +159,8 @@ impl Default for Config {
             num_memtables: 5,
             verify_value_checksum: false,
             name_space_offset: None,
+            clock: Arc::new(SystemClock),
+            merge_operator: None,
             max_batch_count: Default::default(),
             max_batch_size: Default::default(),
             manifest: Default::default(),
@@ -173,6 +216,18 @@ impl Config {
     pub fn name_space_offset(&self) -> Option<usize> {
         self.name_space_offset
     }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub fn merge_operator(&self) -> Option<&Arc<dyn MergeOperator>> {
+        self.merge_operator.as_ref()
+    }
+
+    pub(crate) fn now(&self) -> PhyTs {
+        self.clock.now().into()
+    }
 }
 impl Config {
     pub fn set_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
@@ -222,6 +277,16 @@ impl Config {
         self.name_space_offset = offset.into();
         self
     }
+
+    pub fn set_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn set_merge_operator(mut self, merge_operator: Arc<dyn MergeOperator>) -> Self {
+        self.merge_operator = merge_operator.into();
+        self
+    }
 }
 impl Config {
     pub(crate) fn check_set_config(&mut self) -> anyhow::Result<()> {
@@ -243,7 +308,7 @@ impl Config {
         self.vlog.check_vlog_config()?;
 
         let need_cache =
-            !self.table.compression().is_none() || self.key_registry.encrypt_key().len() > 0;
+            !self.table.compression().is_none() || !self.key_registry.encrypt_key().is_empty();
 
         if need_cache && self.block_cache.block_cache_size() == 0 {
             panic!("Block_Cache_Size should be set since compression are enabled")
@@ -1,7 +1,12 @@
 use std::{
     collections::{HashSet, VecDeque},
+    fs::remove_file,
+    io::Write,
     ops::Deref,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
 };
 
 use crate::{
@@ -23,12 +28,11 @@ use crate::{
     vlog::{discard::DiscardStats, threshold::VlogThreshold, ValueLog},
     write::WriteReq,
 };
+use anyhow::bail;
 use bytes::Buf;
+use log::{info, warn};
+use tokio::sync::{mpsc, Mutex, Notify};
 use tokio::sync::{mpsc::Sender, RwLock};
-use tokio::sync::{
-    mpsc::{self, Receiver},
-    Mutex,
-};
 
 #[derive(Debug, Clone)]
 pub struct DB(Arc<DBInner>);
@@ -47,17 +51,35 @@ pub struct DBInner {
     pub(crate) block_cache: Option<BlockCache>,
     pub(crate) index_cache: IndexCache,
     pub(crate) level_controller: LevelsController,
+    pub(crate) compact_context: CompactContext,
     pub(crate) oracle: Oracle,
     pub(crate) send_write_req: Sender<WriteReq>,
     pub(crate) flush_memtable: Sender<Arc<MemTable>>,
-    pub(crate) recv_memtable: Mutex<Receiver<Arc<MemTable>>>,
+    // notified once an immutable memtable is flushed.
+    pub(crate) flushed_memtable: Notify,
     pub(crate) vlog: ValueLog,
-    banned_namespaces: RwLock<HashSet<u64>>,
+    pub(crate) banned_namespaces: RwLock<HashSet<u64>>,
     pub(crate) publisher: Publisher,
     is_closed: AtomicBool,
     pub(crate) block_writes: AtomicBool,
+    closers: Closers,
     pub(crate) opt: Config,
-    pub(crate) lock_guard: Option<DBLockGuard>,
+    pub(crate) lock_guard: Mutex<Option<DBLockGuard>>,
+}
+// the background tasks hold WeakDB, so the db can be dropped without close.
+#[derive(Debug, Clone)]
+pub(crate) struct WeakDB(Weak<DBInner>);
+impl WeakDB {
+    pub(crate) fn upgrade(&self) -> Option<DB> {
+        self.0.upgrade().map(DB)
+    }
+}
+#[derive(Debug, Clone)]
+struct Closers {
+    writes: Closer,
+    memtable: Closer,
+    compactors: Closer,
+    publisher: Closer,
 }
 impl DB {
     pub async fn open(mut opt: Config) -> anyhow::Result<DB> {
@@ -72,7 +94,7 @@ impl DB {
 
         let key_registry = opt.key_registry.open().await?;
 
-        calculate_size(opt.level_controller.dir(), &opt.vlog.value_dir()).await;
+        calculate_size(opt.level_controller.dir(), opt.vlog.value_dir()).await;
         // let mut update_size_closer = Closer::new();
         // let update_size_handle = tokio::spawn(update_size(update_size_closer.sem_clone()));
 
@@ -97,22 +119,30 @@ impl DB {
         let oracle = Oracle::new(opt.txn, max_version);
 
         let threshold = VlogThreshold::new(opt.vlog_threshold);
-        let discard_stats = DiscardStats::new(&opt.vlog.value_dir())?;
-        let mut closer = Closer::new(1);
+        let discard_stats = DiscardStats::new(opt.vlog.value_dir())?;
+        let closers = Closers {
+            writes: Closer::new(1),
+            memtable: Closer::new(1),
+            compactors: Closer::new(opt.level_controller.num_compactors() as u32),
+            publisher: Closer::new(1),
+        };
 
+        let compact_context = CompactContext::new(
+            key_registry.clone(),
+            index_cache.clone(),
+            block_cache.clone(),
+            discard_stats.clone(),
+            oracle.clone(),
+            manifest.clone(),
+            opt.clock().clone(),
+            opt.merge_operator().cloned(),
+        );
         level_controller
             .clone()
             .spawn_compact(
-                &mut closer,
+                &closers.compactors,
                 opt.table.clone(),
-                CompactContext::new(
-                    key_registry.clone(),
-                    index_cache.clone(),
-                    block_cache.clone(),
-                    discard_stats.clone(),
-                    oracle.clone(),
-                    manifest.clone(),
-                ),
+                compact_context.clone(),
             )
             .await;
         let mut vlog = ValueLog::new(
@@ -122,8 +152,7 @@ impl DB {
             opt.vlog.clone(),
         )?;
         vlog.open().await?;
-        let closer = Closer::new(1);
-        let publisher = Publisher::new(closer.clone());
+        let publisher = Publisher::new(closers.publisher.clone());
         let (send_write_req, recv_write_req) = mpsc::channel(KV_WRITES_ENTRIES_CHANNEL_CAPACITY);
         let (flush_memtable, recv_memtable) = mpsc::channel(opt.num_memtables());
         let db: DB = DB(Arc::new(DBInner {
@@ -133,33 +162,126 @@ impl DB {
             block_cache,
             index_cache,
             level_controller,
+            compact_context,
             oracle,
             send_write_req,
             flush_memtable,
+            flushed_memtable: Notify::new(),
             vlog,
             banned_namespaces: Default::default(),
             publisher,
             is_closed: AtomicBool::new(false),
             block_writes: AtomicBool::new(false),
-            recv_memtable: recv_memtable.into(),
+            closers,
             opt,
-            lock_guard,
+            lock_guard: lock_guard.into(),
         }));
-        let closer = Closer::new(1);
-        tokio::spawn(db.clone().do_writes(recv_write_req, closer));
-        let flush_memtable = Closer::new(1);
-        let _p = tokio::spawn(db.clone().flush_memtable(flush_memtable.clone()));
+        tokio::spawn(DB::do_writes(
+            db.downgrade(),
+            recv_write_req,
+            db.closers.writes.clone(),
+        ));
+        tokio::spawn(DB::flush_memtable(
+            db.downgrade(),
+            recv_memtable,
+            db.closers.memtable.clone(),
+        ));
+        // the memtables replayed from the wal are flushed in the background.
+        let replayed = db.immut_memtable.read().await.clone();
+        for memtable in replayed {
+            db.flush_memtable.send(memtable).await?;
+        }
+        db.init_banned_namespaces().await?;
 
         // drop(value_dir_lock_guard);
         // drop(dir_lock_guard);
 
         Ok(db)
     }
+
+    /// Stops the writes and the background tasks, flushes the memtable to level0, then releases the dir lock.
+    /// Calling it more than once is a no-op.
+    pub async fn close(&self) -> anyhow::Result<()> {
+        if self.is_closed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        info!("Closing database");
+        // the txns send the write requests under this lock.
+        let send_write_req = self.oracle.send_write_req.lock();
+        self.block_writes.store(true, Ordering::SeqCst);
+        drop(send_write_req);
+        self.closers.writes.signal();
+        let _ = self.closers.writes.wait().await?;
+
+        // flush the immutable memtables first, to keep the order of level0.
+        self.closers.memtable.signal();
+        let _ = self.closers.memtable.wait().await?;
+        if let Some(memtable) = self.memtable.as_ref() {
+            let memtable_r = memtable.read().await;
+            self.handle_memtable_flush(&memtable_r, Vec::new()).await?;
+            remove_file(memtable_r.wal().path())?;
+            drop(memtable_r);
+        }
+
+        self.closers.compactors.signal();
+        let _ = self.closers.compactors.wait().await?;
+        if self.opt.level_controller.compactl0_on_close() {
+            self.level_controller
+                .compact_l0_on_close(self.opt.table.clone(), self.compact_context.clone())
+                .await;
+        }
+        // the tables added by the flushes and the compactions above are durable before the unlock.
+        self.level_controller.manifest().sync()?;
+        self.closers.publisher.signal();
+        let _ = self.closers.publisher.wait().await?;
+        self.oracle.stop().await;
+
+        self.vlog.close().await?;
+        drop(self.lock_guard.lock().await.take());
+        info!("Database closed");
+        Ok(())
+    }
+
+    /// Syncs the wal of the memtable, the latest vlog file and the manifest,
+    /// the writes acknowledged before the call are durable once it returns.
+    pub async fn sync(&self) -> anyhow::Result<()> {
+        if self.is_closed() {
+            bail!(DBError::DBClosed);
+        }
+        // the rotated vlog files and the immutable memtables are synced on rotation.
+        self.vlog.sync().await?;
+        if let Some(memtable) = self.memtable.as_ref() {
+            memtable.write().await.wal_mut().flush()?;
+        }
+        self.level_controller.manifest().sync()?;
+        Ok(())
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakDB {
+        WeakDB(Arc::downgrade(&self.0))
+    }
+}
+impl Drop for DBInner {
+    fn drop(&mut self) {
+        if self.is_closed.load(Ordering::SeqCst) {
+            return;
+        }
+        // best effort, the memtable is not flushed but can be replayed from the wal on open.
+        warn!("Database is dropped without close");
+        // the background tasks can't upgrade to the db anymore, so the dir can be reopened
+        // once they exit.
+        drop(self.lock_guard.get_mut().take());
+        self.closers.writes.signal();
+        self.closers.memtable.signal();
+        self.closers.compactors.signal();
+        self.closers.publisher.signal();
+        self.oracle.signal_stop();
+    }
 }
 impl DBInner {
     pub(crate) fn update_size() {}
     pub(crate) fn is_closed(&self) -> bool {
-        self.is_closed.load(std::sync::atomic::Ordering::SeqCst)
+        self.is_closed.load(Ordering::SeqCst)
     }
     pub(crate) async fn is_banned(&self, key: &[u8]) -> Result<(), DBError> {
         match self.opt.name_space_offset() {
@@ -183,13 +305,59 @@ impl DBInner {
     }
 }
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use std::path::Path;
+
+    use bytes::Bytes;
+
     use crate::{
         config::Config,
         db::DB,
-        txn::{Txn, TxnUpdate},
+        errors::DBError,
+        txn::{Txn, TxnUpdate, WriteOptions},
     };
 
+    pub(crate) fn test_config(dir: &Path) -> Config {
+        let mut config = Config::default().set_dir(dir);
+        config.memtable.set_dir(dir.to_path_buf());
+        config.vlog.set_value_dir(dir.to_path_buf());
+        config.vlog_threshold.set_value_threshold(1 << 10);
+        config
+    }
+
+    // drop the db without close, then wait for the background tasks to exit.
+    pub(crate) async fn drop_and_wait(db: DB) -> anyhow::Result<()> {
+        let closers = db.closers.clone();
+        drop(db);
+        for closer in [
+            closers.writes,
+            closers.memtable,
+            closers.compactors,
+            closers.publisher,
+        ] {
+            let _ = closer.wait().await?;
+        }
+        Ok(())
+    }
+
+    // commit a single write in its own txn.
+    pub(crate) async fn set<K: Into<Bytes>, V: Into<Bytes>>(
+        db: &DB,
+        key: K,
+        value: V,
+    ) -> anyhow::Result<()> {
+        let mut txn = db.get_update_txn().await?;
+        txn.set(key.into(), value.into()).await?;
+        txn.commit().await?;
+        txn.discard().await
+    }
+    pub(crate) async fn delete<K: Into<Bytes>>(db: &DB, key: K) -> anyhow::Result<()> {
+        let mut txn = db.get_update_txn().await?;
+        txn.delete(key).await?;
+        txn.commit().await?;
+        txn.discard().await
+    }
+
     struct TxnTestUp;
     impl TxnUpdate for TxnTestUp {
         async fn update(self, txn: &mut Txn) -> anyhow::Result<()> {
@@ -205,4 +373,68 @@ mod tests {
         db.update(TxnTestUp).await?;
         Ok(())
     }
+    #[tokio::test]
+    async fn test_close() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let open = || {
+            let mut config = test_config(dir.path());
+            config.level_controller.set_compactl0_on_close(true);
+            DB::open(config)
+        };
+        let db = open().await?;
+        let big = Bytes::from(vec![7u8; 1 << 12]);
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("big"), big.clone()).await?;
+        txn.set(Bytes::from("small"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        db.close().await?;
+        db.close().await?;
+        let err = db.update(TxnTestUp).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::DBClosed)));
+        drop(db);
+
+        // the dir lock is released and the memtable is flushed.
+        let db = open().await?;
+        assert!(db.immut_memtable.read().await.is_empty());
+        let txn = db.new_read_txn().await?;
+        assert_eq!(txn.get("big").await?.value().await?, &big);
+        assert_eq!(txn.get("small").await?.value().await?, &Bytes::from("v"));
+        txn.discard().await?;
+
+        // dropped without close, the memtable is replayed from the wal.
+        db.update(TxnTestUp).await?;
+        drop_and_wait(db).await?;
+        let db = open().await?;
+        let txn = db.new_read_txn().await?;
+        assert_eq!(txn.get("a").await?.value().await?, &Bytes::from("1"));
+        txn.discard().await?;
+        db.close().await
+    }
+    #[tokio::test]
+    async fn test_sync() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let big = Bytes::from(vec![7u8; 1 << 12]);
+        let mut txn = db.get_update_txn().await?;
+        txn.set_write_options(WriteOptions::default().set_sync(true));
+        txn.set(Bytes::from("big"), big.clone()).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        db.update(TxnTestUp).await?;
+        db.sync().await?;
+
+        // dropped without close, the synced writes are replayed from the wal and vlog.
+        drop_and_wait(db).await?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let txn = db.new_read_txn().await?;
+        assert_eq!(txn.get("big").await?.value().await?, &big);
+        assert_eq!(txn.get("a").await?.value().await?, &Bytes::from("1"));
+        txn.discard().await?;
+        db.close().await?;
+        let err = db.sync().await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::DBClosed)));
+        Ok(())
+    }
 }
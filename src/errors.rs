@@ -1,6 +1,7 @@
 use std::{io::Error, path::PathBuf};
 
 use anyhow::anyhow;
+use bytes::Bytes;
 use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum DBError {
@@ -10,8 +11,12 @@ pub enum DBError {
     KeyNotFound,
     #[error("Txn is too big to fit into one request")]
     TxnTooBig,
-    #[error("Transaction Conflict. Please retry")]
-    Conflict,
+    #[error("Transaction Conflict with the txn committed at {commit_ts} on key {key:?} (hash {key_hash}). Please retry")]
+    Conflict {
+        key_hash: u64,
+        key: Bytes,
+        commit_ts: u64,
+    },
     #[error("No sets or deletes are allowed in a read-only transaction")]
     ReadOnlyTxn,
     #[error("This transaction has been discarded. Create a new one")]
@@ -22,6 +27,14 @@ pub enum DBError {
     InvalidKey,
     #[error("Key is using the banned prefix")]
     BannedKey,
+    #[error("Namespace mode is not set, use Config::set_name_space_offset")]
+    NamespaceMode,
+    #[error("Bandwidth must be greater than zero")]
+    ZeroBandwidth,
+    #[error("Sequence can only be used with managed_txns=false")]
+    SequenceManagedTxns,
+    #[error("Namespaces can only be banned with managed_txns=false")]
+    BanNamespaceManagedTxns,
     #[error("Value log GC can't run because threshold is set to zero")]
     ThresholdZero,
     #[error("Encryption key's length should be either 16 or 32 bytes")]
@@ -32,8 +45,12 @@ pub enum DBError {
     InvalidDataKeyID,
     #[error("DB Closed")]
     DBClosed,
+    #[error("No writes are allowed in read-only mode")]
+    ReadOnlyDB,
     #[error("Log truncate required to run DB. This might result in data loss ; end offset: {0} < size: {1} ")]
     TruncateNeeded(usize, usize),
+    #[error("Commit ts {commit_ts} is below the discard ts {discard_ts}")]
+    CommitTsBelowDiscardTs { commit_ts: u64, discard_ts: u64 },
     #[error("Writes are blocked, possibly due to DropAll or Close")]
     BlockedWrites, // ErrInvalidEncryptionKey is returned if length of encryption keys is invalid.
 }
@@ -56,7 +56,7 @@ where
         self.iter.next().await
     }
 }
-impl<'a, T, V> KvSinkIter<V> for SinkIterRev<T>
+impl<T, V> KvSinkIter<V> for SinkIterRev<T>
 where
     T: KvDoubleEndedSinkIter<V>,
     V: Into<ValueMeta>,
@@ -134,6 +134,10 @@ pub(crate) trait KvSeekIter: SinkIterator {
     fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool>;
     
 }
+// if true then KvDoubleEndedSinkIter.key_back() <= k
+pub(crate) trait KvSeekBackIter: DoubleEndedSinkIterator {
+    fn seek_back(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool>;
+}
 
 pub(crate) struct TestIter {
     data: Option<[u8; 8]>,
@@ -163,52 +167,46 @@ impl DoubleEndedSinkIter for TestIter {
 }
 impl SinkIterator for TestIter {
     fn next(&mut self) -> Result<bool, anyhow::Error> {
-        match self.data.as_mut() {
-            Some(d) => {
-                let now = (*d).as_ref().get_u64();
-                if now + 1 == self.len {
+        if let Some(d) = self.data.as_mut() {
+            let now = (*d).as_ref().get_u64();
+            if now + 1 == self.len {
+                return Ok(false);
+            }
+            if let Some(back_data) = self.back_data.as_ref() {
+                let mut b = back_data.as_ref();
+                let b = b.get_u64();
+                if now + 1 == b {
                     return Ok(false);
-                }
-                if let Some(back_data) = self.back_data.as_ref() {
-                    let mut b = back_data.as_ref();
-                    let b = b.get_u64();
-                    if now + 1 == b {
-                        return Ok(false);
-                    };
                 };
-                *d = (now + 1).to_be_bytes();
-                return Ok(true);
-            }
-            _ => {}
+            };
+            *d = (now + 1).to_be_bytes();
+            return Ok(true);
         }
         self.data = 0u64.to_be_bytes().into();
-        return Ok(true);
+        Ok(true)
     }
 }
 
 impl DoubleEndedSinkIterator for TestIter {
     fn next_back(&mut self) -> Result<bool, anyhow::Error> {
-        match self.back_data.as_mut() {
-            Some(d) => {
-                let now = (*d).as_ref().get_u64();
-                if now == 0 {
+        if let Some(d) = self.back_data.as_mut() {
+            let now = (*d).as_ref().get_u64();
+            if now == 0 {
+                return Ok(false);
+            }
+            if let Some(data) = self.data.as_ref() {
+                let mut b = data.as_ref();
+                let s = b.get_u64();
+                if now - 1 == s {
                     return Ok(false);
                 }
-                if let Some(data) = self.data.as_ref() {
-                    let mut b = data.as_ref();
-                    let s = b.get_u64();
-                    if now - 1 == s {
-                        return Ok(false);
-                    }
-                }
-
-                *d = (now - 1).to_be_bytes();
-                return Ok(true);
             }
-            _ => {}
+
+            *d = (now - 1).to_be_bytes();
+            return Ok(true);
         }
         self.back_data = (self.len - 1).to_be_bytes().into();
-        return Ok(true);
+        Ok(true)
     }
 }
 impl KvSinkIter<ValueMeta> for TestIter {
@@ -216,7 +214,7 @@ impl KvSinkIter<ValueMeta> for TestIter {
         if let Some(s) = self.item() {
             return Some(s.as_ref().into());
         }
-        return None;
+        None
     }
 
     fn value(&self) -> Option<ValueMeta> {
@@ -225,7 +223,7 @@ impl KvSinkIter<ValueMeta> for TestIter {
             value.set_value(s.to_vec().into());
             return Some(value);
         }
-        return None;
+        None
     }
 }
 impl KvDoubleEndedSinkIter<ValueMeta> for TestIter {
@@ -233,7 +231,7 @@ impl KvDoubleEndedSinkIter<ValueMeta> for TestIter {
         if let Some(s) = self.item_back() {
             return Some(s.as_ref().into());
         }
-        return None;
+        None
     }
 
     fn value_back(&self) -> Option<ValueMeta> {
@@ -242,7 +240,7 @@ impl KvDoubleEndedSinkIter<ValueMeta> for TestIter {
             value.set_value(s.to_vec().into());
             return Some(value);
         }
-        return None;
+        None
     }
 }
 impl KvSeekIter for TestIter {
@@ -252,7 +250,7 @@ impl KvSeekIter for TestIter {
             return Ok(false);
         }
         self.data = Some(key.to_be_bytes());
-        return Ok(true);
+        Ok(true)
     }
 }
 
@@ -50,9 +50,9 @@ impl From<u64> for CipherKeyId {
         Self(value)
     }
 }
-impl Into<u64> for CipherKeyId {
-    fn into(self) -> u64 {
-        self.0
+impl From<CipherKeyId> for u64 {
+    fn from(val: CipherKeyId) -> Self {
+        val.0
     }
 }
 
@@ -125,7 +125,7 @@ impl KeyRegistryConfig {
             key_registry.fp = Some(key_registry_fp);
         }
 
-        return Ok(KeyRegistry(Arc::new(RwLock::new(key_registry))));
+        Ok(KeyRegistry(Arc::new(RwLock::new(key_registry))))
     }
 
     pub fn set_dir(&mut self, dir: PathBuf) {
@@ -153,7 +153,7 @@ impl KeyRegistryConfig {
 impl KeyRegistryInner {
     fn new(encrypt_key: &[u8], data_key_rotation_duration: Duration) -> anyhow::Result<Self> {
         let keys_len = encrypt_key.len();
-        if keys_len > 0 && !vec![16, 32].contains(&keys_len) {
+        if keys_len > 0 && ![16, 32].contains(&keys_len) {
             bail!("{:?} During OpenKeyRegistry", DBError::InvalidEncryptionKey);
         }
         let cipher: Option<AesCipher> = if keys_len > 0 {
@@ -188,7 +188,7 @@ impl KeyRegistryInner {
         buf.put_u32(e_sanity.len() as u32);
         buf.put_slice(&e_sanity);
 
-        for (_, data_key) in self.data_keys.iter_mut() {
+        for data_key in self.data_keys.values_mut() {
             Self::store_data_key(&mut buf, &self.cipher, data_key)
                 .map_err(|e| anyhow!("Error while storing datakey in WriteKeyRegistry {}", e))?;
         }
@@ -262,6 +262,11 @@ impl KeyRegistryInner {
     }
 }
 impl KeyRegistry {
+    // write all the data keys to the key registry file in dir.
+    pub(crate) async fn write_to(&self, dir: &PathBuf) -> anyhow::Result<()> {
+        self.write().await.write_to_file(dir).await
+    }
+
     pub(crate) async fn latest_cipher(&self) -> anyhow::Result<Option<AesCipher>> {
         if let Some(data_key) = self.latest_datakey().await? {
             return Ok(AesCipher::new(&data_key.data, data_key.key_id.into())?.into());
@@ -288,7 +293,7 @@ impl KeyRegistry {
                     );
                 }
             };
-            return (None, false);
+            (None, false)
         };
         let (key, valid) = valid_key(&inner_r);
         if valid {
@@ -363,7 +368,7 @@ impl<'a> KeyRegistryIter<'a> {
             .read_exact(nonce.as_mut())
             .map_err(|e| anyhow!("Error while reading IV for key registry. {}", e))?;
 
-        let mut len_e_saintytext_buf = vec![0 as u8; 4];
+        let mut len_e_saintytext_buf = vec![0_u8; 4];
         self.reader
             .read_exact(len_e_saintytext_buf.as_mut())
             .map_err(|e| anyhow!("Error while reading saintytext.len for key registry. {}", e))?;
@@ -422,7 +427,7 @@ impl<'a> Iterator for KeyRegistryIter<'a> {
         let e_data_key_len = len_crc_buf_ref.get_u32();
         let e_data_key_crc: u32 = len_crc_buf_ref.get_u32();
 
-        let mut e_data_key = vec![0 as u8; e_data_key_len as usize];
+        let mut e_data_key = vec![0_u8; e_data_key_len as usize];
         match self.reader.read_exact(e_data_key.as_mut()) {
             Ok(_) => {}
             Err(e) => {
@@ -472,6 +477,7 @@ impl<'a> Iterator for KeyRegistryIter<'a> {
 }
 
 #[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum AesCipher {
     Aes128(Aes128Gcm, CipherKeyId),
     Aes256(Aes256Gcm, CipherKeyId),
@@ -17,6 +17,8 @@ pub struct Entry {
     offset: usize,
     header_len: usize,
     value_threshold: usize,
+    // expires_at will be set by the clock of db when this entry is set in txn.
+    ttl: Option<Duration>,
 }
 
 impl Entry {
@@ -34,11 +36,23 @@ impl Entry {
             header_len: 0,
             value_meta,
             value_threshold: 0,
+            ttl: None,
         }
     }
 
+    /// The entry will expire after ttl since it is set in txn.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl.into();
+        self
+    }
+
+    pub fn with_meta(mut self, user_meta: u8) -> Self {
+        self.value_meta.user_meta = user_meta;
+        self
+    }
+
     pub fn key(&self) -> &Bytes {
-        &self.key_ts.key()
+        self.key_ts.key()
     }
 
     pub fn set_key<B: Into<Bytes>>(&mut self, key: B) {
@@ -126,9 +140,9 @@ impl Entry {
         let k: KeyTs = key_ts.into();
         let value_meta = ValueMeta {
             value: value.to_vec().into(),
-            expires_at: PhyTs::default(),
-            user_meta: 0,
-            meta: Meta::default(),
+            expires_at: header.expires_at(),
+            user_meta: header.user_meta(),
+            meta: header.meta(),
         };
         Self {
             key_ts: k,
@@ -136,6 +150,7 @@ impl Entry {
             header_len,
             value_meta,
             value_threshold: 0,
+            ttl: None,
         }
     }
     #[inline]
@@ -143,11 +158,18 @@ impl Entry {
         self.meta().contains(Meta::DELETE)
     }
 
-    pub(crate) fn is_expired(&self) -> bool {
+    pub(crate) fn is_expired(&self, now: PhyTs) -> bool {
         if self.expires_at() == PhyTs::default() {
             return false;
         }
-        self.expires_at() <= PhyTs::now().unwrap()
+        self.expires_at() <= now
+    }
+
+    // resolve ttl to expires_at with the time of db.
+    pub(crate) fn apply_ttl(&mut self, now: PhyTs) {
+        if let Some(ttl) = self.ttl.take() {
+            self.value_meta.expires_at = now + ttl;
+        }
     }
     pub(crate) fn try_set_value_threshold(&mut self, threshold: usize) {
         if self.value_threshold == 0 {
@@ -164,7 +186,7 @@ impl Entry {
 pub struct TxnTs(u64);
 impl TxnTs {
     #[inline(always)]
-    pub(crate) fn to_u64(&self) -> u64 {
+    pub(crate) fn to_u64(self) -> u64 {
         self.0
     }
 }
@@ -197,6 +219,11 @@ impl From<u64> for TxnTs {
         Self(value)
     }
 }
+impl From<TxnTs> for u64 {
+    fn from(value: TxnTs) -> Self {
+        value.0
+    }
+}
 
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct PhyTs(u64);
@@ -205,20 +232,38 @@ impl From<u64> for PhyTs {
         Self(value)
     }
 }
-impl Into<u64> for PhyTs {
-    fn into(self) -> u64 {
-        self.0
+impl From<PhyTs> for u64 {
+    fn from(val: PhyTs) -> Self {
+        val.0
     }
 }
-impl Into<SystemTime> for PhyTs {
-    fn into(self) -> SystemTime {
+impl From<PhyTs> for SystemTime {
+    fn from(val: PhyTs) -> Self {
         SystemTime::UNIX_EPOCH
-            .checked_add(Duration::from_secs(self.0))
+            .checked_add(Duration::from_secs(val.0))
             .unwrap()
     }
 }
+impl From<SystemTime> for PhyTs {
+    fn from(value: SystemTime) -> Self {
+        value
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            .into()
+    }
+}
+impl Add<Duration> for PhyTs {
+    type Output = PhyTs;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        // round up, so a ttl less than 1s won't expire immediately.
+        let secs = rhs.as_secs() + (rhs.subsec_nanos() > 0) as u64;
+        Self(self.0.saturating_add(secs))
+    }
+}
 impl PhyTs {
-    pub(crate) fn to_u64(&self) -> u64 {
+    pub(crate) fn to_u64(self) -> u64 {
         self.0
     }
     pub(crate) fn now() -> Result<Self, SystemTimeError> {
@@ -235,11 +280,7 @@ pub(crate) struct KeyTs {
 }
 impl PartialOrd for KeyTs {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match self.key.partial_cmp(&other.key) {
-            Some(core::cmp::Ordering::Equal) => {}
-            ord => return ord,
-        }
-        other.txn_ts.partial_cmp(&self.txn_ts)
+        Some(self.cmp(other))
     }
 }
 impl PartialEq<KeyTsBorrow<'_>> for KeyTs {
@@ -363,20 +404,12 @@ impl Deref for KeyTsBorrow<'_> {
 
 impl PartialOrd for KeyTsBorrow<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let self_split = self.len() - 8;
-        let other_split = other.len() - 8;
-        match self[..self_split].partial_cmp(&other[..other_split]) {
-            Some(std::cmp::Ordering::Equal) => {}
-            ord => {
-                return ord;
-            }
-        }
-        other[other_split..].partial_cmp(&self[self_split..])
+        Some(Ord::cmp(self, other))
     }
 }
 impl Ord for KeyTsBorrow<'_> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        KeyTsBorrow::cmp(&self, &other)
+        KeyTsBorrow::cmp(self, other)
     }
 }
 impl PartialEq<KeyTs> for KeyTsBorrow<'_> {
@@ -428,12 +461,12 @@ impl<'a> From<&'a [u8]> for KeyTsBorrow<'a> {
 }
 impl<'a> AsRef<[u8]> for KeyTsBorrow<'a> {
     fn as_ref(&self) -> &[u8] {
-        &self.0
+        self.0
     }
 }
-impl<'a> Into<&'a [u8]> for KeyTsBorrow<'a> {
-    fn into(self) -> &'a [u8] {
-        &self.0
+impl<'a> From<KeyTsBorrow<'a>> for &'a [u8] {
+    fn from(val: KeyTsBorrow<'a>) -> Self {
+        val.0
     }
 }
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -510,14 +543,26 @@ impl ValueMeta {
     pub(crate) fn set_value(&mut self, value: Bytes) {
         self.value = value;
     }
-    pub(crate) fn is_deleted_or_expired(&self) -> bool {
+
+    pub(crate) fn meta_mut(&mut self) -> &mut Meta {
+        &mut self.meta
+    }
+
+    pub(crate) fn expires_at(&self) -> PhyTs {
+        self.expires_at
+    }
+
+    pub(crate) fn user_meta(&self) -> u8 {
+        self.user_meta
+    }
+    pub(crate) fn is_deleted_or_expired(&self, now: PhyTs) -> bool {
         if self.meta.contains(Meta::DELETE) {
             return true;
         };
         if self.expires_at == PhyTs::default() {
             return false;
         }
-        self.expires_at <= PhyTs::now().unwrap()
+        self.expires_at <= now
     }
 }
 
@@ -531,7 +576,7 @@ impl ValuePointer {
     const SIZE: usize = mem::size_of::<ValuePointer>();
     pub(crate) fn new(fid: u32, len: usize, offset: usize) -> Self {
         Self {
-            fid: fid,
+            fid,
             len: len as u32,
             offset: offset as u32,
         }
@@ -550,7 +595,7 @@ impl ValuePointer {
     }
 
     pub(crate) fn deserialize(bytes: &[u8]) -> Self {
-        let mut p: &[u8] = bytes.as_ref();
+        let mut p: &[u8] = bytes;
 
         Self {
             fid: p.get_u32(),
@@ -566,6 +611,10 @@ impl ValuePointer {
     pub(crate) fn fid(&self) -> u32 {
         self.fid
     }
+
+    pub(crate) fn offset(&self) -> u32 {
+        self.offset
+    }
 }
 
 #[cfg(test)]
@@ -574,7 +623,10 @@ mod tests {
 
     use bytes::Bytes;
 
-    use crate::kv::{KeyTsBorrow, Meta, ValueMeta};
+    use crate::{
+        kv::{Entry, KeyTsBorrow, Meta, ValueMeta},
+        vlog::header::VlogEntryHeader,
+    };
 
     use super::KeyTs;
 
@@ -620,17 +672,33 @@ mod tests {
     }
     #[test]
     fn test_serialize() {
-        let mut v = ValueMeta::default();
-        v.value = String::from("abc").as_bytes().to_vec().into();
-        v.expires_at = 123456789.into();
-        v.meta = Meta(1);
+        let v = ValueMeta {
+            value: String::from("abc").as_bytes().to_vec().into(),
+            expires_at: 123456789.into(),
+            meta: Meta(1),
+            ..Default::default()
+        };
         assert_eq!(v.serialized_size(), 9);
         assert_eq!(v, ValueMeta::deserialize(&v.serialize()).unwrap());
     }
     #[test]
+    fn test_new_ts() {
+        let mut e = Entry::new("a".into(), "v".into());
+        e.set_version(1.into());
+        e.set_expires_at(123456789);
+        e.set_user_meta(7);
+        e.set_meta(Meta::TXN);
+        let header = VlogEntryHeader::new(&e);
+        let key_ts = e.key_ts().serialize();
+        let decoded = Entry::new_ts(&key_ts, e.value(), &header, 0, 0);
+        assert_eq!(decoded.value_meta(), e.value_meta());
+    }
+    #[test]
     fn test_empty() {
-        let mut v = ValueMeta::default();
-        v.value = Bytes::from("");
+        let v = ValueMeta {
+            value: Bytes::from(""),
+            ..Default::default()
+        };
         dbg!(v.serialized_size());
 
         let k = KeyTs::new(Bytes::default(), 0.into());
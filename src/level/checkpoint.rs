@@ -0,0 +1,144 @@
+use std::{
+    fs::{create_dir, hard_link, remove_dir_all},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    db::DB,
+    util::{
+        sys::{sync_dir, PinnedPrefix},
+        DBFileId,
+    },
+};
+
+impl DB {
+    /// Creates an openable copy of db in dir, which must not exist. The tables and the vlog files are
+    /// hard linked, the latest vlog file and the wal of the memtable are copied up to their write offsets,
+    /// so dir should be on the same filesystem, and it's opened with all the dirs of config set to it.
+    /// The writes are blocked and the compactions are paused only while the files are linked,
+    /// the copies are made after that.
+    pub async fn checkpoint<P: AsRef<Path>>(&self, dir: P) -> anyhow::Result<()> {
+        let dir = dir.as_ref().to_path_buf();
+        let blocked = self.block_pending_writes().await?;
+        let guard = self.level_controller.compact_lock().write().await;
+
+        create_dir(&dir).map_err(|e| anyhow!("Cannot create checkpoint dir {:?}: {}", dir, e))?;
+        let pinned = self.link_checkpoint(&dir).await;
+        drop(guard);
+        drop(blocked);
+
+        let mut result =
+            pinned.and_then(|pinned| pinned.into_iter().try_for_each(|pinned| pinned.copy()));
+        if result.is_ok() {
+            result = sync_dir(&dir);
+        }
+        if result.is_err() {
+            let _ = remove_dir_all(&dir);
+        }
+        result
+    }
+
+    // link the files into dir, the latest vlog file and the wal are pinned to be copied
+    // up to their write offsets.
+    // pre condition: the writes are blocked and hold the write lock of compact_lock.
+    async fn link_checkpoint(&self, dir: &PathBuf) -> anyhow::Result<Vec<PinnedPrefix>> {
+        for handler in self.level_controller.levels() {
+            let handler_r = handler.read().await;
+            for table in handler_r.tables.iter() {
+                let table_id = table.table_id();
+                hard_link(
+                    table_id.join_dir(self.opt.level_controller.dir()),
+                    table_id.join_dir(dir),
+                )?;
+            }
+            drop(handler_r);
+        }
+        // the tables above are exactly the ones in the manifest.
+        self.level_controller.manifest().write_to(dir)?;
+        self.key_registry.write_to(dir).await?;
+        let mut pinned = Vec::with_capacity(2);
+        pinned.extend(self.vlog.checkpoint(dir).await?);
+
+        // the immutable memtables are flushed, only the wal of the memtable is left.
+        let mut memtable_w = self.memtable.as_ref().unwrap().write().await;
+        memtable_w.wal_mut().flush()?;
+        let wal = memtable_w.wal();
+        pinned.push(PinnedPrefix::new(
+            wal.path(),
+            wal.fid().join_dir(dir),
+            wal.write_offset(),
+        )?);
+        drop(memtable_w);
+        Ok(pinned)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::fs::read_dir;
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+    };
+
+    #[tokio::test]
+    async fn test_checkpoint() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.memtable.set_memtable_size(1 << 20);
+        let db = DB::open(config).await?;
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+        let value = |i: usize| match i % 100 {
+            // the large values are in the vlog.
+            0 => Bytes::from(vec![i as u8; 1 << 12]),
+            _ => Bytes::from(format!("{:0100}", i)),
+        };
+        let mut batch = db.new_write_batch();
+        for i in 0..5000 {
+            batch.set(key(i), value(i)).await?;
+        }
+        batch.flush().await?;
+        // the last keys are only in the memtable.
+        let mut txn = db.get_update_txn().await?;
+        txn.set("a", "1").await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let checkpoints = tempfile::tempdir()?;
+        let checkpoint_dir = checkpoints.path().join("checkpoint");
+        db.checkpoint(&checkpoint_dir).await?;
+        // the dir must not exist.
+        assert!(db.checkpoint(&checkpoint_dir).await.is_err());
+        // the pinned files are removed once they are copied.
+        for entry in read_dir(&checkpoint_dir)? {
+            assert_ne!(entry?.path().extension(), Some("pinned".as_ref()));
+        }
+        let mut txn = db.get_update_txn().await?;
+        txn.set("b", "1").await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let copy = DB::open(test_config(&checkpoint_dir)).await?;
+        let txn = copy.new_read_txn().await?;
+        for i in [0, 1, 100, 2500, 4999] {
+            assert_eq!(txn.get(key(i)).await?.value().await?, &value(i));
+        }
+        assert_eq!(txn.get("a").await?.value().await?, &Bytes::from("1"));
+        let err = txn.get("b").await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        txn.discard().await?;
+        copy.close().await?;
+
+        // db is not changed by the checkpoint.
+        let txn = db.new_read_txn().await?;
+        assert_eq!(txn.get("b").await?.value().await?, &Bytes::from("1"));
+        assert_eq!(txn.get(key(0)).await?.value().await?, &value(0));
+        txn.discard().await?;
+        db.close().await
+    }
+}
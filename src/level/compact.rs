@@ -9,7 +9,7 @@ use super::{
 use crate::util::metrics::{add_num_compaction_tables, sub_num_compaction_tables};
 use crate::{
     iter::{KvSeekIter, KvSinkIter, SinkIterator},
-    kv::{KeyTs, KeyTsBorrow, Meta, TxnTs, ValueMeta, ValuePointer},
+    kv::{KeyTs, KeyTsBorrow, Meta, PhyTs, TxnTs, ValueMeta, ValuePointer},
     level::compaction::KeyTsRange,
     pb::badgerpb4::ManifestChange,
     table::{
@@ -17,6 +17,7 @@ use crate::{
         write::TableBuilder,
         Table, TableConfig,
     },
+    txn::MergeOperator,
     util::{metrics::add_num_bytes_compaction_written, sys::sync_dir, DBFileId},
 };
 use anyhow::bail;
@@ -33,7 +34,7 @@ impl LevelsController {
         config: TableConfig,
         context: CompactContext,
     ) -> anyhow::Result<()> {
-        if plan.priority().targets().file_size().len() == 0 {
+        if plan.priority().targets().file_size().is_empty() {
             bail!("Filesizes cannot be zero. Targets are not set");
         };
 
@@ -42,13 +43,13 @@ impl LevelsController {
         let this_level = plan.this_level_handler();
         let next_level = plan.next_level_handler();
 
-        debug_assert!(plan.splits().len() == 0);
+        debug_assert!(plan.splits().is_empty());
 
         if this_level.level() != next_level.level() {
             plan.add_splits(self);
         }
 
-        if plan.splits().len() == 0 {
+        if plan.splits().is_empty() {
             plan.splits_mut().push(KeyTsRange::default());
         }
 
@@ -157,8 +158,8 @@ impl LevelsController {
         let mut valid = Vec::new();
         't: for table in plan.bottom().iter() {
             for prefix in plan.priority().drop_prefixes().iter() {
-                if table.smallest().key().starts_with(&prefix)
-                    && table.biggest().key().starts_with(&prefix)
+                if table.smallest().key().starts_with(prefix)
+                    && table.biggest().key().starts_with(prefix)
                 {
                     continue 't;
                 };
@@ -175,7 +176,7 @@ impl LevelsController {
                     .rev()
                     .map(|t| t.iter(false).into())
                     .collect::<Vec<_>>();
-            } else if plan.top().len() > 0 {
+            } else if !plan.top().is_empty() {
                 assert_eq!(plan.top().len(), 1);
                 out = vec![plan.top()[0].iter(false).into()];
             };
@@ -225,7 +226,11 @@ impl LevelsController {
 
         let discard_ts = compact_context.oracle.discard_at_or_below();
 
-        let mut add_context = AddKeyContext::default();
+        let mut add_context = AddKeyContext {
+            now: compact_context.clock.now().into(),
+            merge_operator: compact_context.merge_operator.clone(),
+            ..Default::default()
+        };
         let left_bytes = key_range.left().serialize();
         let left_key_borrow: KeyTsBorrow = left_bytes.as_slice().into();
 
@@ -305,6 +310,9 @@ struct AddKeyContext {
     num_versions: usize,
     discard_stats: HashMap<u32, u64>,
     first_key_has_discard_set: bool,
+    // the entries expired before now will be dropped.
+    now: PhyTs,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
 }
 impl AddKeyContext {
     fn update_discard(&mut self, value: &ValueMeta) {
@@ -322,6 +330,7 @@ impl AddKeyContext {
     }
 }
 impl TableBuilder {
+    #[allow(clippy::too_many_arguments)]
     async fn add_keys(
         &mut self,
         iter: &mut SinkMergeIter,
@@ -395,7 +404,23 @@ impl TableBuilder {
                     }
                 }
             }
-            let is_expired = value.is_deleted_or_expired();
+            // the operands below discard_ts won't be read separately, so fold them into one entry.
+            let folded_key;
+            let mut advanced = false;
+            let (key_ts, value) = match context.merge_operator.as_ref() {
+                Some(operator)
+                    if key_ts.txn_ts() <= discard_ts
+                        && value.meta().contains(Meta::MERGE_ENTRY)
+                        && !value.meta().contains(Meta::VALUE_POINTER) =>
+                {
+                    folded_key = key_ts.to_vec();
+                    let value = collapse_merge(iter, operator, !has_overlap, context.now)?;
+                    advanced = true;
+                    (KeyTsBorrow::from(folded_key.as_slice()), value)
+                }
+                _ => (key_ts, value),
+            };
+            let is_expired = value.is_deleted_or_expired(context.now);
             if key_ts.txn_ts() <= discard_ts && !value.meta().contains(Meta::MERGE_ENTRY) {
                 context.num_versions += 1;
                 let last_valid_version = value.meta().contains(Meta::DISCARD_EARLIER_VERSIONS)
@@ -407,7 +432,9 @@ impl TableBuilder {
                     if (is_expired || !last_valid_version) && !has_overlap {
                         num_skips += 1;
                         context.update_discard(&value);
-                        iter.next()?;
+                        if !advanced {
+                            iter.next()?;
+                        }
                         continue;
                     }
                 }
@@ -416,15 +443,16 @@ impl TableBuilder {
 
             let mut vptr_len = None;
             if value.meta().contains(Meta::VALUE_POINTER) {
-                vptr_len = Some(ValuePointer::deserialize(&value.value()).len());
+                vptr_len = Some(ValuePointer::deserialize(value.value()).len());
             }
-            if context.first_key_has_discard_set {
-                self.push_stale(&key_ts, &value, vptr_len);
-            } else if is_expired {
+            if context.first_key_has_discard_set || is_expired {
                 self.push_stale(&key_ts, &value, vptr_len);
             } else {
                 self.push(&key_ts, &value, vptr_len);
             }
+            if !advanced {
+                iter.next()?;
+            }
         }
         debug!(
             "[{}] LOG Compact. Added {num_keys} keys. Skipped {num_skips} keys. Iteration took: {}",
@@ -434,3 +462,118 @@ impl TableBuilder {
         Ok(())
     }
 }
+// fold the merge operands of the current key, and stop at the first entry which can't be folded.
+// the result is a complete value if the older value is found (or there is nothing older),
+// otherwise it's still an operand.
+// the values in vlog (VALUE_POINTER) can't be read in compaction, so an operand or an older value
+// in vlog isn't folded: it's kept as a separate version and merged when the key is read.
+fn collapse_merge(
+    iter: &mut SinkMergeIter,
+    operator: &Arc<dyn MergeOperator>,
+    is_bottom: bool,
+    now: PhyTs,
+) -> anyhow::Result<ValueMeta> {
+    let key_ts: KeyTs = iter.key().unwrap_or_default().into();
+    let mut newest = iter.value().unwrap_or_default();
+    let mut operands = Vec::new();
+    let mut existing = None;
+    let mut complete = is_bottom;
+    loop {
+        let value = iter.value().unwrap_or_default();
+        if !value.is_deleted_or_expired(now) {
+            operands.push(value.value().clone());
+        }
+        if !iter.next()? {
+            break;
+        }
+        let (next_key, next) = match (iter.key(), iter.value()) {
+            (Some(k), Some(v)) => (k, v),
+            _ => break,
+        };
+        if next_key.key() != key_ts.key() {
+            break;
+        }
+        let meta = next.meta();
+        if meta.contains(Meta::VALUE_POINTER) {
+            // can't read vlog in compaction.
+            complete = false;
+            break;
+        }
+        if meta.contains(Meta::MERGE_ENTRY) {
+            continue;
+        }
+        complete = true;
+        if !next.is_deleted_or_expired(now) {
+            existing = Some(next.value().clone());
+        }
+        break;
+    }
+    operands.reverse();
+    newest.set_value(operator.merge(key_ts.key(), existing.as_deref(), &operands));
+    if complete {
+        newest.meta_mut().remove(Meta::MERGE_ENTRY);
+    }
+    Ok(newest)
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use crate::{
+        iter::{KvSinkIter, SinkIterator},
+        kv::{Entry, KeyTsBorrow, Meta, PhyTs, TxnTs},
+        table::iter::SinkMergeIter,
+        txn::MergeOperator,
+        util::skip_list::SkipList,
+    };
+
+    use super::collapse_merge;
+
+    #[derive(Debug)]
+    struct Append;
+    impl MergeOperator for Append {
+        fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Bytes]) -> Bytes {
+            let mut value = existing.map(|e| e.to_vec()).unwrap_or_default();
+            for operand in operands {
+                value.extend_from_slice(operand);
+            }
+            value.into()
+        }
+    }
+    #[test]
+    fn test_collapse_merge() -> anyhow::Result<()> {
+        let skip_list = SkipList::new(1 << 20, KeyTsBorrow::cmp);
+        for (key, ts, value, meta) in [
+            ("k", 4, "d", Meta::MERGE_ENTRY),
+            ("k", 3, "c", Meta::MERGE_ENTRY),
+            ("k", 2, "b", Meta::default()),
+            ("k", 1, "a", Meta::default()),
+            ("l", 1, "x", Meta::MERGE_ENTRY),
+        ] {
+            let mut entry = Entry::new(Bytes::from(key), Bytes::from(value));
+            entry.set_version(TxnTs::from(ts));
+            entry.set_meta(meta);
+            skip_list.push(&entry.key_ts().serialize(), &entry.value_meta().serialize());
+        }
+        let operator: Arc<dyn MergeOperator> = Arc::new(Append);
+        let mut iter = SinkMergeIter::new(vec![skip_list.owned_iter().into()]).unwrap();
+        iter.next()?;
+
+        let value = collapse_merge(&mut iter, &operator, false, PhyTs::default())?;
+        assert_eq!(value.value(), &Bytes::from("bcd"));
+        assert!(!value.meta().contains(Meta::MERGE_ENTRY));
+        // the older value is kept for the version check of compaction.
+        assert_eq!(iter.key().unwrap().txn_ts(), TxnTs::from(2));
+
+        iter.next()?;
+        iter.next()?;
+        // nothing older in this compaction, but the lower levels may have.
+        let value = collapse_merge(&mut iter, &operator, false, PhyTs::default())?;
+        assert_eq!(value.value(), &Bytes::from("x"));
+        assert!(value.meta().contains(Meta::MERGE_ENTRY));
+        assert!(!iter.valid());
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use std::{
 };
 
 use crate::{
+    config::Clock,
     key_registry::KeyRegistry,
     kv::KeyTs,
     level::{
@@ -15,7 +16,7 @@ use crate::{
     },
     manifest::Manifest,
     table::{Table, TableConfig},
-    txn::oracle::Oracle,
+    txn::{oracle::Oracle, MergeOperator},
     util::{
         cache::{BlockCache, IndexCache},
         closer::Closer,
@@ -24,9 +25,10 @@ use crate::{
     vlog::discard::DiscardStats,
 };
 use bytes::Bytes;
-use log::{debug, warn};
+use log::{debug, info, warn};
 use parking_lot::RwLock;
 use rand::Rng;
+use scopeguard::defer;
 use tokio::select;
 
 use super::{
@@ -43,6 +45,16 @@ pub(crate) struct CompactPriority {
 }
 
 impl CompactPriority {
+    pub(crate) fn new(level: Level, targets: CompactTargets, drop_prefixes: Vec<Bytes>) -> Self {
+        Self {
+            level,
+            score: 1.,
+            adjusted: 1.,
+            drop_prefixes,
+            targets,
+        }
+    }
+
     pub(crate) fn level(&self) -> Level {
         self.level
     }
@@ -88,9 +100,12 @@ pub(crate) struct CompactContext {
     pub(crate) discard_stats: DiscardStats,
     pub(crate) oracle: Oracle,
     pub(crate) manifest: Manifest,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) merge_operator: Option<Arc<dyn MergeOperator>>,
 }
 
 impl CompactContext {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         key_registry: KeyRegistry,
         index_cache: IndexCache,
@@ -98,6 +113,8 @@ impl CompactContext {
         discard_stats: DiscardStats,
         oracle: Oracle,
         manifest: Manifest,
+        clock: Arc<dyn Clock>,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
     ) -> Self {
         Self {
             key_registry,
@@ -106,13 +123,15 @@ impl CompactContext {
             discard_stats,
             oracle,
             manifest,
+            clock,
+            merge_operator,
         }
     }
 }
 impl LevelsController {
     pub(crate) async fn spawn_compact(
         self,
-        closer: &mut Closer,
+        closer: &Closer,
         config: TableConfig,
         context: CompactContext,
     ) {
@@ -132,6 +151,7 @@ impl LevelsController {
         config: TableConfig,
         context: CompactContext,
     ) -> anyhow::Result<()> {
+        defer!(closer.done());
         let sleep =
             tokio::time::sleep(Duration::from_millis(rand::thread_rng().gen_range(0..1000)));
         select! {
@@ -189,6 +209,16 @@ impl LevelsController {
             }
         }
     }
+    // pre condition: the compactors are stopped.
+    pub(crate) async fn compact_l0_on_close(&self, config: TableConfig, context: CompactContext) {
+        if self.level_handler(LEVEL0).get_tables_len().await == 0 {
+            return;
+        }
+        let priority = CompactPriority::new(LEVEL0, self.level_targets().await, Vec::new());
+        if let Err(e) = self.compact(usize::MAX, priority, config, context).await {
+            info!("While forcing compaction on level 0: {}", e);
+        }
+    }
     async fn pick_compact_levels(&self) -> Vec<CompactPriority> {
         let mut prios = Vec::new();
         let targets = self.level_targets().await;
@@ -250,6 +280,7 @@ impl LevelsController {
         config: TableConfig,
         context: CompactContext,
     ) -> bool {
+        let _guard = self.compact_lock().read().await;
         match self
             .compact(compact_task_id, priority, config, context)
             .await
@@ -263,7 +294,7 @@ impl LevelsController {
             }
         }
     }
-    async fn compact(
+    pub(super) async fn compact(
         &self,
         compact_task_id: usize,
         mut priority: CompactPriority,
@@ -291,10 +322,11 @@ impl LevelsController {
         );
         plan.fix(self, &context.oracle).await?;
 
-        if let Err(e) = self
+        let result = self
             .run_compact(compact_task_id, priority_level, &mut plan, config, context)
-            .await
-        {
+            .await;
+        self.compact_status().delete(&plan);
+        if let Err(e) = result {
             warn!(
                 "[Compactor: {}] LOG Compact FAILED with error: {}: {:?}",
                 compact_task_id, e, plan
@@ -312,7 +344,7 @@ impl LevelsController {
 }
 
 impl LevelsControllerInner {
-    async fn level_targets(&self) -> CompactTargets {
+    pub(super) async fn level_targets(&self) -> CompactTargets {
         let levels_len = self.levels().len();
         assert!(levels_len < u8::MAX as usize);
         let levels_bound: Level = (levels_len as u8).into();
@@ -363,7 +395,7 @@ impl LevelsControllerInner {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub(crate) struct KeyTsRange {
     left: KeyTs,
     right: KeyTs,
@@ -371,15 +403,15 @@ pub(crate) struct KeyTsRange {
 }
 impl From<&[Table]> for KeyTsRange {
     fn from(value: &[Table]) -> Self {
-        if value.len() == 0 {
+        if value.is_empty() {
             return KeyTsRange::default();
         }
 
         let mut smallest = value[0].smallest();
         let mut biggest = value[0].biggest();
-        for i in 1..value.len() {
-            smallest = smallest.min(value[i].smallest());
-            biggest = biggest.max(value[i].biggest());
+        for table in &value[1..] {
+            smallest = smallest.min(table.smallest());
+            biggest = biggest.max(table.biggest());
         }
         Self {
             left: KeyTs::new(smallest.key().clone(), u64::MAX.into()),
@@ -430,7 +462,7 @@ impl KeyTsRange {
             return false;
         }
 
-        return true;
+        true
     }
     #[inline]
     pub(crate) fn extend(&mut self, other: Self) {
@@ -545,6 +577,13 @@ impl CompactStatusInner {
     pub(crate) fn push(&mut self, level: Level, range: KeyTsRange) {
         self.levels[level.to_usize()].ranges.push(range);
     }
+
+    pub(crate) fn remove(&mut self, level: Level, range: &KeyTsRange) {
+        let ranges = &mut self.levels[level.to_usize()].ranges;
+        if let Some(index) = ranges.iter().position(|r| r == range) {
+            ranges.remove(index);
+        }
+    }
 }
 impl CompactStatus {
     pub(crate) fn new(max_levels: usize) -> Self {
@@ -599,6 +638,6 @@ impl LevelCompactStatus {
                 return true;
             }
         }
-        return false;
+        false
     }
 }
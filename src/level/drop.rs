@@ -0,0 +1,335 @@
+use std::{
+    fs::remove_file,
+    mem::{replace, take},
+    sync::{atomic::Ordering, Arc},
+};
+
+use anyhow::{anyhow, bail};
+use bytes::Bytes;
+use log::info;
+use tokio::sync::oneshot;
+
+use crate::{
+    db::DB,
+    errors::DBError,
+    memtable::MemTable,
+    pb::badgerpb4::ManifestChange,
+    table::{Table, TableConfig},
+    util::DBFileId,
+    write::WriteReq,
+};
+
+use super::{
+    compaction::{CompactContext, CompactPriority},
+    levels::{LevelsController, LEVEL0},
+    plan::CompactPlan,
+};
+
+impl DB {
+    /// Drop all the data of db. The writes are blocked and the compactions are paused until it's done.
+    pub async fn drop_all(&self) -> anyhow::Result<()> {
+        let _blocked = self.block_pending_writes().await?;
+        let _guard = self.level_controller.compact_lock().write().await;
+
+        let memtable = self.replace_memtable().await?;
+        remove_file(memtable.wal().path())?;
+        let num_tables = self.level_controller.drop_tree().await?;
+        let num_vlogs = self.vlog.drop_all().await?;
+        info!(
+            "Drop all: deleted {} tables and {} vlog files",
+            num_tables, num_vlogs
+        );
+        Ok(())
+    }
+
+    /// Drop all the keys with any of the prefixes. The writes are blocked and the compactions are paused until it's done.
+    /// The vlog files only referred by the dropped keys are deleted, the values of them in the other
+    /// vlog files are counted into the discard stats.
+    pub async fn drop_prefix<B: AsRef<[u8]>>(&self, prefixes: &[B]) -> anyhow::Result<()> {
+        let prefixes = prefixes
+            .iter()
+            .map(|p| Bytes::copy_from_slice(p.as_ref()))
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+        if prefixes.is_empty() {
+            return Ok(());
+        }
+        let _blocked = self.block_pending_writes().await?;
+
+        // flush before the compactions are paused, otherwise it may stall on level0.
+        // the keys are dropped by the compaction of level0, which counts the discarded values.
+        let memtable = self.replace_memtable().await?;
+        self.handle_memtable_flush(&memtable, Vec::new()).await?;
+        remove_file(memtable.wal().path())?;
+
+        let _guard = self.level_controller.compact_lock().write().await;
+        self.level_controller
+            .drop_prefixes(
+                &prefixes,
+                self.opt.table.clone(),
+                self.compact_context.clone(),
+            )
+            .await?;
+        let num_vlogs = self.vlog.delete_discarded().await?;
+        info!("Drop prefix: deleted {} vlog files", num_vlogs);
+        Ok(())
+    }
+
+    // block the writes, then wait for the pending writes and the immutable memtables.
+    pub(crate) async fn block_pending_writes(&self) -> anyhow::Result<BlockedWrites> {
+        if self.opt.read_only() {
+            bail!(DBError::ReadOnlyDB);
+        }
+        // the txns send the write requests under this lock, so the requests sent before
+        // are in the channel once it's released, and the later ones see the blocked writes.
+        let send_write_req = self.oracle.send_write_req.lock();
+        let blocked = self
+            .block_writes
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+        drop(send_write_req);
+        if !blocked {
+            bail!(DBError::BlockedWrites)
+        }
+        let blocked = BlockedWrites(self.clone());
+        let (sender, receiver) = oneshot::channel();
+        self.send_write_req
+            .send(WriteReq::new(Vec::new(), sender))
+            .await
+            .map_err(|e| anyhow!("Failed to send the write request for {}", e))?;
+        receiver.await??;
+
+        // a flush between the check and the wait is kept as the permit of notify_one.
+        while !self.immut_memtable.read().await.is_empty() {
+            self.flushed_memtable.notified().await;
+        }
+        Ok(blocked)
+    }
+
+    pub(super) async fn replace_memtable(&self) -> anyhow::Result<Arc<MemTable>> {
+        let memtable = self.memtable.as_ref().unwrap();
+        let new_memtable = self.opt.memtable.new(&self.key_registry).await?;
+        let mut memtable_w = memtable.write().await;
+        let old_memtable = replace(&mut *memtable_w, new_memtable);
+        drop(memtable_w);
+        Ok(Arc::new(old_memtable))
+    }
+}
+// the writes are unblocked once it's dropped.
+pub(crate) struct BlockedWrites(DB);
+impl Drop for BlockedWrites {
+    fn drop(&mut self) {
+        self.0.block_writes.store(false, Ordering::SeqCst);
+    }
+}
+impl LevelsController {
+    // pre condition: hold the write lock of compact_lock.
+    async fn drop_tree(&self) -> anyhow::Result<usize> {
+        let mut tables = Vec::new();
+        for handler in self.levels() {
+            let mut handler_w = handler.write().await;
+            tables.extend(take(&mut handler_w.tables));
+            handler_w.init(handler.level(), Vec::new());
+            drop(handler_w);
+        }
+        if tables.is_empty() {
+            return Ok(0);
+        }
+        let changes = tables
+            .iter()
+            .map(|t| ManifestChange::new_delete(t.table_id()))
+            .collect();
+        self.manifest().push_changes(changes)?;
+        for table in tables.iter() {
+            remove_file(table.table_id().join_dir(self.level_config().dir()))?;
+        }
+        Ok(tables.len())
+    }
+
+    // pre condition: hold the write lock of compact_lock.
+    async fn drop_prefixes(
+        &self,
+        prefixes: &[Bytes],
+        config: TableConfig,
+        context: CompactContext,
+    ) -> anyhow::Result<()> {
+        let targets = self.level_targets().await;
+        for handler in self.levels() {
+            let level = handler.level();
+            let priority = CompactPriority::new(level, targets.clone(), prefixes.to_vec());
+            let handler_r = handler.read().await;
+            if level == LEVEL0 {
+                let is_empty = handler_r.tables.is_empty();
+                drop(handler_r);
+                if !is_empty {
+                    self.compact(usize::MAX, priority, config.clone(), context.clone())
+                        .await?;
+                }
+                continue;
+            }
+            let tables = handler_r
+                .tables
+                .iter()
+                .filter(|t| prefixes.iter().any(|p| contains_prefix(t, p)))
+                .cloned()
+                .collect::<Vec<_>>();
+            drop(handler_r);
+            if tables.is_empty() {
+                continue;
+            }
+
+            let mut plan =
+                CompactPlan::new_drop_prefixes(self, priority, handler.clone(), tables).await?;
+            let result = self
+                .run_compact(
+                    usize::MAX,
+                    level,
+                    &mut plan,
+                    config.clone(),
+                    context.clone(),
+                )
+                .await;
+            self.compact_status().delete(&plan);
+            result?;
+        }
+        Ok(())
+    }
+}
+// the table may contain the keys with prefix.
+fn contains_prefix(table: &Table, prefix: &[u8]) -> bool {
+    let smallest = table.smallest().key().as_ref();
+    let biggest = table.biggest().key().as_ref();
+    smallest.starts_with(prefix)
+        || biggest.starts_with(prefix)
+        || (smallest < prefix && prefix < biggest)
+}
+#[cfg(test)]
+mod tests {
+    use std::fs::read_dir;
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{
+            tests::{drop_and_wait, test_config},
+            DB,
+        },
+        errors::DBError,
+    };
+
+    async fn open(dir: &tempfile::TempDir) -> anyhow::Result<DB> {
+        let mut config = test_config(dir.path());
+        config.memtable.set_memtable_size(1 << 20);
+        DB::open(config).await
+    }
+    async fn write(db: &DB, prefix: &str, value_len: usize) -> anyhow::Result<()> {
+        let mut batch = db.new_write_batch();
+        for i in 0..2000 {
+            batch
+                .set(format!("{prefix}{i:05}"), "v".repeat(value_len))
+                .await?;
+        }
+        batch.flush().await
+    }
+    async fn assert_keys(db: &DB, prefix: &str, exists: bool) -> anyhow::Result<()> {
+        let txn = db.new_read_txn().await?;
+        for i in (0..2000).step_by(97) {
+            let result = txn.get(Bytes::from(format!("{prefix}{i:05}"))).await;
+            match exists {
+                true => assert!(result.is_ok(), "{prefix}{i:05}"),
+                false => assert!(matches!(
+                    result.unwrap_err().downcast_ref(),
+                    Some(DBError::KeyNotFound)
+                )),
+            }
+        }
+        txn.discard().await
+    }
+    #[tokio::test]
+    async fn test_drop_all() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open(&dir).await?;
+        write(&db, "a", 2 << 10).await?;
+        write(&db, "b", 100).await?;
+
+        db.drop_all().await?;
+        assert_keys(&db, "a", false).await?;
+        assert_keys(&db, "b", false).await?;
+        for level in db.level_controller.levels() {
+            assert_eq!(level.get_tables_len().await, 0);
+        }
+
+        write(&db, "c", 2 << 10).await?;
+        assert_keys(&db, "c", true).await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_drop_prefix() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open(&dir).await?;
+        for prefix in ["a", "b", "c"] {
+            write(&db, prefix, 300).await?;
+        }
+
+        db.drop_prefix(&["b", "c"]).await?;
+        // the memtables are flushed and level0 is compacted without the prefixes.
+        assert_eq!(db.level_controller.levels()[0].get_tables_len().await, 0);
+        let mut num_tables = 0;
+        for level in db.level_controller.levels() {
+            num_tables += level.get_tables_len().await;
+        }
+        assert!(num_tables > 0);
+        assert_keys(&db, "a", true).await?;
+        assert_keys(&db, "b", false).await?;
+        assert_keys(&db, "c", false).await?;
+
+        write(&db, "b", 300).await?;
+        assert_keys(&db, "b", true).await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_drop_prefix_vlog() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let num_vlogs = || -> anyhow::Result<usize> {
+            let mut count = 0;
+            for entry in read_dir(dir.path())? {
+                if entry?.path().extension() == Some("vlog".as_ref()) {
+                    count += 1;
+                }
+            }
+            Ok(count)
+        };
+        // a new vlog file is created on each open, so the values of a and b are in different files.
+        let db = open(&dir).await?;
+        write(&db, "a", 2 << 10).await?;
+        drop_and_wait(db).await?;
+        let db = open(&dir).await?;
+        write(&db, "b", 2 << 10).await?;
+        let before = num_vlogs()?;
+
+        db.drop_prefix(&["a"]).await?;
+        assert_eq!(num_vlogs()?, before - 1);
+        assert_keys(&db, "a", false).await?;
+        let txn = db.new_read_txn().await?;
+        for i in (0..2000).step_by(97) {
+            let item = txn.get(Bytes::from(format!("b{i:05}"))).await?;
+            assert_eq!(item.value().await?.len(), 2 << 10);
+        }
+        txn.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_drop_read_only() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open(&dir).await?;
+        write(&db, "a", 100).await?;
+        db.close().await?;
+
+        let db = DB::open(test_config(dir.path()).set_read_only(true)).await?;
+        let err = db.drop_prefix(&["a"]).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::ReadOnlyDB)));
+        let err = db.drop_all().await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::ReadOnlyDB)));
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
 use std::{
+    fs::remove_file,
     sync::{atomic::Ordering, Arc},
     time::{Duration, Instant},
 };
 
 use log::{error, info};
 use scopeguard::defer;
+use tokio::{select, sync::mpsc::Receiver};
 
 use crate::{
-    db::DB,
+    db::{WeakDB, DB},
     memtable::MemTable,
     pb::badgerpb4::ManifestChange,
     table::{write::TableBuilder, Table},
@@ -19,38 +21,62 @@ use super::{level_handler::LevelHandler, levels::LevelsControllerInner};
 
 impl DB {
     #[deny(unused)]
-    pub(crate) async fn flush_memtable(self, closer: Closer) {
+    pub(crate) async fn flush_memtable(
+        db: WeakDB,
+        mut recv_memtable: Receiver<Arc<MemTable>>,
+        closer: Closer,
+    ) {
         defer!(closer.done());
-        let mut recv_memtable = self.recv_memtable.lock().await;
-        while let Some(memtable) = recv_memtable.recv().await {
-            loop {
-                if let Err(e) = self
-                    .handle_memtable_flush(memtable.clone(), Vec::new())
-                    .await
-                {
-                    error!("flushing memtable to disk:{}, retrying", e);
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-                    continue;
-                };
-                let mut immut_w = self.immut_memtable.write().await;
-                if let Some(s) = immut_w.pop_front() {
-                    assert_eq!(s.wal().fid(), memtable.wal().fid())
-                };
-                drop(immut_w);
-                break;
+        loop {
+            select! {
+                memtable = recv_memtable.recv() => {
+                    let (Some(memtable), Some(db)) = (memtable, db.upgrade()) else {
+                        return;
+                    };
+                    db.flush_immut_memtable(memtable).await;
+                }
+                _ = closer.captured() => {
+                    while let Ok(memtable) = recv_memtable.try_recv() {
+                        let Some(db) = db.upgrade() else {
+                            return;
+                        };
+                        db.flush_immut_memtable(memtable).await;
+                    }
+                    return;
+                }
             }
         }
     }
-    async fn handle_memtable_flush(
+    async fn flush_immut_memtable(&self, memtable: Arc<MemTable>) {
+        loop {
+            if let Err(e) = self.handle_memtable_flush(&memtable, Vec::new()).await {
+                error!("flushing memtable to disk:{}, retrying", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            };
+            let mut immut_w = self.immut_memtable.write().await;
+            if let Some(s) = immut_w.pop_front() {
+                assert_eq!(s.wal().fid(), memtable.wal().fid())
+            };
+            drop(immut_w);
+            self.flushed_memtable.notify_one();
+            // the memtable is persisted in level0, no need to replay the wal.
+            if let Err(e) = remove_file(memtable.wal().path()) {
+                error!("removing wal {:?}: {}", memtable.wal().path(), e);
+            }
+            break;
+        }
+    }
+    pub(crate) async fn handle_memtable_flush(
         &self,
-        memtable: Arc<MemTable>,
-        _drop_prefixed: Vec<&[u8]>,
+        memtable: &MemTable,
+        drop_prefixes: Vec<Vec<u8>>,
     ) -> anyhow::Result<()> {
         let cipher = self.key_registry.latest_cipher().await?;
         let table_opt = self.opt.table.clone();
         let skip_list_iter = memtable.skip_list.iter();
         let mut table_builder =
-            TableBuilder::build_l0_table(skip_list_iter, Vec::new(), table_opt, cipher)?;
+            TableBuilder::build_l0_table(skip_list_iter, drop_prefixes, table_opt, cipher)?;
         if table_builder.is_empty() {
             let _ = table_builder.finish().await;
             return Ok(());
@@ -0,0 +1,439 @@
+use std::{
+    cmp::Ordering,
+    fs::{copy, hard_link, remove_file, File, OpenOptions},
+    path::Path,
+};
+
+use anyhow::bail;
+
+use crate::{
+    db::DB,
+    iter::{KvSinkIter, SinkIterator},
+    kv::{KeyTsBorrow, Meta, TxnTs, ValueMeta},
+    pb::badgerpb4::ManifestChange,
+    table::{ChecksumVerificationMode, Table},
+    util::{mmap::MmapFile, sys::sync_dir, DBFileId},
+};
+
+use super::{compaction::KeyTsRange, level_handler::LevelHandler};
+
+impl DB {
+    /// Adds the table files written by SstWriter, the original files are kept. The files must not
+    /// overlap with each other, and they are added to the lowest level which neither it nor the
+    /// levels above overlap with them.
+    /// The files are linked into db without rewriting them, so they must be written with the table
+    /// config of db, and db must not encrypt its tables. The keys of them are at a new commit ts,
+    /// which is kept in the manifest, so they are newer than the existing versions and each key
+    /// must have one version in the files.
+    /// With managed_txns the keys keep the versions written by SstWriter.
+    pub async fn ingest_external_files<P: AsRef<Path>>(&self, paths: &[P]) -> anyhow::Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let _blocked = self.block_pending_writes().await?;
+        // flush the memtable, so the tables hold all the keys to check the overlaps with.
+        let memtable = self.replace_memtable().await?;
+        self.handle_memtable_flush(&memtable, Vec::new()).await?;
+        remove_file(memtable.wal().path())?;
+        let _guard = self.level_controller.compact_lock().write().await;
+
+        let commit_ts = if self.oracle.config().managed_txns() {
+            None
+        } else {
+            Some(self.oracle.new_commit_ts().await?)
+        };
+        let result = self.ingest(paths, commit_ts).await;
+        if let Some(commit_ts) = commit_ts {
+            self.oracle.done_commit(commit_ts).await?;
+        }
+        result
+    }
+
+    // pre condition: hold the write lock of compact_lock.
+    async fn ingest<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        commit_ts: Option<TxnTs>,
+    ) -> anyhow::Result<()> {
+        // the files are not encrypted.
+        if self.key_registry.latest_cipher().await?.is_some() {
+            bail!("Cannot ingest the external files into an encrypted db");
+        }
+        let mut tables = Vec::with_capacity(paths.len());
+        for path in paths {
+            match self.link_external_file(path.as_ref(), commit_ts).await {
+                Ok(table) => tables.push(table),
+                Err(e) => {
+                    self.remove_ingested(&tables);
+                    return Err(e);
+                }
+            }
+        }
+        let result = match self.ingest_target(&mut tables).await {
+            Ok(target) => self.push_ingested(target, &tables).await,
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.remove_ingested(&tables);
+        }
+        result
+    }
+
+    // link the file into a table of db, the keys are read at global_version if it's some.
+    async fn link_external_file(
+        &self,
+        path: &Path,
+        global_version: Option<TxnTs>,
+    ) -> anyhow::Result<Table> {
+        let table = self.open_external_file(path, global_version).await?;
+        if let Err(e) = validate_external_table(&table, path) {
+            let table_path = table.table_id().join_dir(self.opt.level_controller.dir());
+            drop(table);
+            let _ = remove_file(table_path);
+            return Err(e);
+        }
+        Ok(table)
+    }
+
+    async fn open_external_file(
+        &self,
+        path: &Path,
+        global_version: Option<TxnTs>,
+    ) -> anyhow::Result<Table> {
+        let file_id = self.level_controller.get_reserve_file_id();
+        let file_path = file_id.join_dir(self.opt.level_controller.dir());
+        // the file may be on another device.
+        if hard_link(path, &file_path).is_err() {
+            copy(path, &file_path)?;
+            File::open(&file_path)?.sync_all()?;
+        }
+        // the compression is not kept in the file, so a file written with another
+        // table config fails the checksums.
+        let mut config = self.opt.table.clone();
+        config.set_checksum_verify_mode(ChecksumVerificationMode::OnBlockRead);
+        config.set_global_version(global_version);
+        let mut fp_open_opt = OpenOptions::new();
+        fp_open_opt.read(true).write(true);
+        let table = match MmapFile::open(&file_path, fp_open_opt, 0) {
+            Ok((mmap_f, _)) => {
+                config
+                    .open(
+                        mmap_f,
+                        None,
+                        self.index_cache.clone(),
+                        self.block_cache.clone(),
+                    )
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        if table.is_err() {
+            let _ = remove_file(&file_path);
+        }
+        table
+    }
+
+    // the lowest level which neither it nor the levels above overlap with tables.
+    // pre condition: hold the write lock of compact_lock.
+    async fn ingest_target(&self, tables: &mut [Table]) -> anyhow::Result<&LevelHandler> {
+        tables.sort_by(|a, b| a.smallest().cmp(b.smallest()));
+        let overlap = tables
+            .windows(2)
+            .find(|w| w[0].biggest().key() >= w[1].smallest().key());
+        if let Some(w) = overlap {
+            bail!(
+                "The key ranges of the files overlap: {:?} and {:?}",
+                w[0].biggest(),
+                w[1].smallest()
+            );
+        }
+
+        // the tables are added as the newest ones of level0 if it overlaps with them.
+        let key_range = KeyTsRange::from(&*tables);
+        let levels = self.level_controller.levels();
+        let mut target = &levels[0];
+        for handler in levels {
+            let handler_r = handler.read().await;
+            let is_overlap = handler_r
+                .tables
+                .iter()
+                .any(|t| KeyTsRange::from(t).is_overlaps_with(&key_range));
+            drop(handler_r);
+            if is_overlap {
+                break;
+            }
+            target = handler;
+        }
+        Ok(target)
+    }
+
+    async fn push_ingested(&self, handler: &LevelHandler, tables: &[Table]) -> anyhow::Result<()> {
+        let changes = tables
+            .iter()
+            .map(|table| {
+                ManifestChange::new_create(
+                    table.table_id(),
+                    handler.level(),
+                    table
+                        .cipher()
+                        .and_then(|x| x.cipher_key_id().into())
+                        .unwrap_or_default(),
+                    table.config().compression(),
+                )
+                .with_global_version(table.config().global_version())
+            })
+            .collect();
+        sync_dir(self.opt.level_controller.dir())?;
+        self.level_controller.manifest().push_changes(changes)?;
+        handler.replace(&[], tables).await;
+        Ok(())
+    }
+
+    fn remove_ingested(&self, tables: &[Table]) {
+        for table in tables {
+            let _ = remove_file(table.table_id().join_dir(self.opt.level_controller.dir()));
+        }
+    }
+}
+// the keys are sorted, and there is no vlog out of db for the value pointers.
+fn validate_external_table(table: &Table, path: &Path) -> anyhow::Result<()> {
+    let mut iter = table.iter(false);
+    let mut last: Option<Vec<u8>> = None;
+    while iter.next()? {
+        let key_ts = iter.key().unwrap();
+        let value: ValueMeta = iter.value().unwrap();
+        if value.meta().contains(Meta::VALUE_POINTER) {
+            bail!("Invalid value pointer of key {:?} in {:?}", key_ts, path);
+        }
+        if let Some(last) = last.as_ref() {
+            match KeyTsBorrow::cmp(last, &key_ts) {
+                Ordering::Less => {}
+                Ordering::Equal if table.config().global_version().is_some() => {
+                    bail!(
+                        "Key {:?} has more than one version in {:?}",
+                        key_ts.key(),
+                        path
+                    );
+                }
+                _ => bail!(
+                    "Keys are not in sorted order in {:?}: {:?} after {:?}",
+                    path,
+                    key_ts,
+                    KeyTsBorrow::from(last.as_slice())
+                ),
+            }
+        }
+        last = Some(key_ts.to_vec());
+    }
+    if last.is_none() {
+        bail!("Cannot ingest an empty table {:?}", path);
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        db::{
+            tests::{set, test_config},
+            DB,
+        },
+        errors::DBError,
+        txn::IteratorOptions,
+        Entry, SstWriter,
+    };
+
+    #[tokio::test]
+    async fn test_ingest_external_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let table_config = config.table.clone();
+        let db = DB::open(config).await?;
+        let mut txn = db.get_update_txn().await?;
+        txn.set("a", "1").await?;
+        txn.set("b", "1").await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let files = tempfile::tempdir()?;
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+        let mut paths = Vec::new();
+        for f in 0..2 {
+            let path = files.path().join(format!("{}.sst", f));
+            let mut writer = SstWriter::new(&path, table_config.clone());
+            for i in f * 1000..(f + 1) * 1000 {
+                writer.set(key(i), Bytes::from(i.to_string()))?;
+            }
+            writer.finish().await?;
+            paths.push(path);
+        }
+        db.ingest_external_files(&paths).await?;
+        // no overlap with the keys flushed from the memtable.
+        let handler = db.level_controller.levels().last().unwrap();
+        assert_eq!(handler.read().await.tables.len(), 2);
+
+        let txn = db.new_read_txn().await?;
+        for i in [0, 999, 1000, 1999] {
+            let item = txn.get(key(i)).await?;
+            assert_eq!(item.value().await?, &Bytes::from(i.to_string()));
+        }
+        assert_eq!(txn.get("a").await?.value().await?, &Bytes::from("1"));
+        txn.discard().await?;
+
+        // overlapping with each other.
+        let path = files.path().join("2.sst");
+        let mut writer = SstWriter::new(&path, table_config.clone());
+        writer.set(key(0), Bytes::from("2"))?;
+        writer.finish().await?;
+        assert!(db
+            .ingest_external_files(&[path.clone(), path])
+            .await
+            .is_err());
+
+        // the commits after it are newer than the ingested keys.
+        let mut txn = db.get_update_txn().await?;
+        txn.set(key(0), Bytes::from("3")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        let txn = db.new_read_txn().await?;
+        assert_eq!(txn.get(key(0)).await?.value().await?, &Bytes::from("3"));
+        txn.discard().await?;
+        db.close().await?;
+
+        let db = DB::open(test_config(dir.path())).await?;
+        let txn = db.new_read_txn().await?;
+        assert_eq!(
+            txn.get(key(1999)).await?.value().await?,
+            &Bytes::from("1999")
+        );
+        txn.discard().await?;
+        db.close().await
+    }
+    #[tokio::test]
+    async fn test_ingest_over_existing_keys() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let table_config = config.table.clone();
+        let db = DB::open(config).await?;
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+        let mut txn = db.get_update_txn().await?;
+        for i in 0..100 {
+            txn.set(key(i), Bytes::from("old")).await?;
+        }
+        txn.commit().await?;
+        txn.discard().await?;
+        let txn = db.new_read_txn().await?;
+        let old_version = txn.get(key(0)).await?.version();
+        txn.discard().await?;
+
+        let files = tempfile::tempdir()?;
+        let path = files.path().join("0.sst");
+        let mut writer = SstWriter::new(&path, table_config.clone());
+        for i in 50..150 {
+            writer.set(key(i), Bytes::from("new"))?;
+        }
+        writer.finish().await?;
+        db.ingest_external_files(&[path]).await?;
+        // overlaps with the keys flushed from the memtable.
+        assert_eq!(db.level_controller.levels()[0].get_tables_len().await, 2);
+
+        let assert_values = |db: DB| async move {
+            let txn = db.new_read_txn().await?;
+            for i in [0, 49, 50, 99, 100, 149] {
+                let item = txn.get(key(i)).await?;
+                let value = if i < 50 { "old" } else { "new" };
+                assert_eq!(item.value().await?, &Bytes::from(value));
+                if i >= 50 {
+                    assert!(item.version() > old_version);
+                }
+            }
+            txn.discard().await?;
+            anyhow::Ok(db)
+        };
+        let db = assert_values(db).await?;
+        db.close().await?;
+        let db = assert_values(DB::open(test_config(dir.path())).await?).await?;
+        db.close().await
+    }
+    #[tokio::test]
+    async fn test_ingest_at_global_version() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let table_config = config.table.clone();
+        let db = DB::open(config).await?;
+        set(&db, "a", "1").await?;
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+
+        let files = tempfile::tempdir()?;
+        let path = files.path().join("0.sst");
+        let mut writer = SstWriter::new(&path, table_config.clone());
+        for i in 0..100 {
+            writer.set(key(i), Bytes::from(i.to_string()))?;
+        }
+        writer.finish().await?;
+        let before = db.new_read_txn().await?;
+        db.ingest_external_files(&[path]).await?;
+
+        // the keys keep the versions in the file, and are read at the global version.
+        let handler = db.level_controller.levels().last().unwrap();
+        let table = handler.read().await.tables[0].clone();
+        let global_version = table.config().global_version().unwrap();
+        assert_eq!(table.max_version(), global_version);
+        assert_eq!(table.smallest().txn_ts(), global_version);
+        drop(table);
+
+        // the keys are newer than the read ts before ingestion.
+        let err = before.get(key(0)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        before.discard().await?;
+
+        let txn = db.new_read_txn().await?;
+        let mut iter = txn.iter(IteratorOptions::default()).await?;
+        iter.seek(key(50)).await?;
+        let mut keys = Vec::new();
+        while let Some(item) = iter.item() {
+            assert_eq!(item.version(), global_version.to_u64());
+            keys.push(Bytes::copy_from_slice(item.key()));
+            iter.next().await?;
+        }
+        assert_eq!(keys, (50..100).map(key).collect::<Vec<_>>());
+        drop(iter);
+        let mut iter = txn
+            .iter(IteratorOptions::default().set_reverse(true))
+            .await?;
+        iter.seek(key(50)).await?;
+        let mut keys = Vec::new();
+        while let Some(item) = iter.item() {
+            keys.push(Bytes::copy_from_slice(item.key()));
+            iter.next().await?;
+        }
+        let mut expected = (0..=50).rev().map(key).collect::<Vec<_>>();
+        expected.push(Bytes::from("a"));
+        assert_eq!(keys, expected);
+        drop(iter);
+        txn.discard().await?;
+
+        // every key is at the global version, so it has one version in the file.
+        let path = files.path().join("1.sst");
+        let mut writer = SstWriter::new(&path, table_config.clone());
+        for version in [2, 1] {
+            let mut entry = Entry::new(key(200), Bytes::from(version.to_string()));
+            entry.set_version(version.into());
+            writer.push(entry)?;
+        }
+        writer.finish().await?;
+        assert!(db.ingest_external_files(&[path]).await.is_err());
+        db.close().await?;
+
+        // the global version is kept in the manifest.
+        let db = DB::open(test_config(dir.path())).await?;
+        let txn = db.new_read_txn().await?;
+        let item = txn.get(key(99)).await?;
+        assert_eq!(item.version(), global_version.to_u64());
+        assert_eq!(item.value().await?, &Bytes::from("99"));
+        assert!(txn.get(key(200)).await.is_err());
+        txn.discard().await?;
+        db.close().await
+    }
+}
@@ -6,9 +6,9 @@ use std::{cmp::Ordering, io};
 use anyhow::anyhow;
 use tokio::sync::RwLock;
 
-use crate::kv::{KeyTs, KeyTsBorrow};
+use crate::kv::KeyTs;
 use crate::util::SSTableId;
-use crate::{table::Table, util::compare_key};
+use crate::table::Table;
 
 use super::levels::{Level, LEVEL0};
 #[derive(Debug, Clone)]
@@ -69,7 +69,7 @@ impl LevelHandlerTables {
         self.total_stale_size = total_stale_size;
 
         if level == LEVEL0 {
-            self.tables.sort_by(|a, b| a.table_id().cmp(&b.table_id()));
+            self.tables.sort_by_key(|a| a.table_id());
         } else {
             self.tables.sort_by(|a, b| a.smallest().cmp(b.smallest()));
         }
@@ -234,7 +234,7 @@ vs
 
 //fix from std binary_search_by
 #[inline]
-async fn binary_search_biggest(tables: &Vec<Table>, value: &[u8]) -> Result<usize, usize> {
+async fn binary_search_biggest(tables: &[Table], value: &[u8]) -> Result<usize, usize> {
     // INVARIANTS:
     // - 0 <= left <= left + size = right <= self.len()
     // - f returns Less for everything in self[..left]
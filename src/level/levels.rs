@@ -12,11 +12,10 @@ use std::{
     time::Duration,
 };
 
-use aead::generic_array::typenum::Le;
 use anyhow::anyhow;
 use anyhow::bail;
 use log::{debug, error, info};
-use tokio::select;
+use tokio::{select, sync::RwLock};
 use tokio_util::sync::CancellationToken;
 
 use super::{compaction::CompactStatus, level_handler::LevelHandler};
@@ -53,6 +52,8 @@ pub(crate) struct LevelsControllerInner {
     level_0_stalls_ms: AtomicU64,
     levels: Vec<LevelHandler>,
     compact_status: CompactStatus,
+    // compactions hold the read lock, drop_all and drop_prefix hold the write lock to pause them.
+    compact_lock: RwLock<()>,
     memtable_size: usize,
     max_levels: u8,
     level_config: LevelsControllerConfig,
@@ -76,18 +77,18 @@ impl From<usize> for Level {
         Self(value as u8)
     }
 }
-impl Into<usize> for Level {
-    fn into(self) -> usize {
-        self.0 as usize
+impl From<Level> for usize {
+    fn from(val: Level) -> Self {
+        val.0 as usize
     }
 }
-impl Into<u32> for Level {
-    fn into(self) -> u32 {
-        self.0 as u32
+impl From<Level> for u32 {
+    fn from(val: Level) -> Self {
+        val.0 as u32
     }
 }
 impl Level {
-    pub(crate) fn to_usize(&self) -> usize {
+    pub(crate) fn to_usize(self) -> usize {
         self.0 as usize
     }
 }
@@ -111,16 +112,18 @@ impl AddAssign<u8> for Level {
     }
 }
 impl Step for Level {
-    fn steps_between(start: &Self, end: &Self) -> Option<usize> {
-        end.0.checked_sub(start.0).and_then(|x| Some(x as usize))
+    fn steps_between(start: &Self, end: &Self) -> (usize, Option<usize>) {
+        match end.0.checked_sub(start.0) {
+            Some(x) => (x as usize, Some(x as usize)),
+            None => (0, None),
+        }
     }
 
     fn forward_checked(start: Self, count: usize) -> Option<Self> {
         if count < u8::MAX as usize {
             start
                 .0
-                .checked_add(count as u8)
-                .and_then(|x| Some(Level(x)))
+                .checked_add(count as u8).map(Level)
         } else {
             None
         }
@@ -130,8 +133,7 @@ impl Step for Level {
         if count < u8::MAX as usize {
             start
                 .0
-                .checked_sub(count as u8)
-                .and_then(|x| Some(Level(x)))
+                .checked_sub(count as u8).map(Level)
         } else {
             None
         }
@@ -227,6 +229,14 @@ impl LevelsControllerConfig {
         self.lmax_compaction
     }
 
+    pub fn compactl0_on_close(&self) -> bool {
+        self.compactl0_on_close
+    }
+
+    pub fn set_compactl0_on_close(&mut self, compactl0_on_close: bool) {
+        self.compactl0_on_close = compactl0_on_close;
+    }
+
     pub fn base_level_size(&self) -> usize {
         self.base_level_size
     }
@@ -259,11 +269,10 @@ impl LevelsControllerConfig {
         assert!(self.num_level_zero_tables_stall > self.num_level_zero_tables);
 
         let compact_status = CompactStatus::default();
-        let mut compact_status_w = compact_status.write();
-        compact_status_w
+        compact_status
+            .write()
             .levels_mut()
             .resize_with(self.max_level.0 as usize, LevelCompactStatus::default);
-        drop(compact_status_w);
 
         let (max_file_id, level_tables) = self
             .open_tables_by_manifest(
@@ -290,6 +299,7 @@ impl LevelsControllerConfig {
             level_0_stalls_ms: Default::default(),
             levels,
             compact_status,
+            compact_lock: Default::default(),
             memtable_size: self.memtable_size,
             max_levels: self.max_level.0,
             level_config: self.clone(),
@@ -331,6 +341,8 @@ impl LevelsControllerConfig {
         });
         cancell
     }
+    // the manifest is locked until every table of it is opened.
+    #[allow(clippy::await_holding_lock)]
     async fn open_tables_by_manifest(
         &self,
         default_table_config: TableConfig,
@@ -364,6 +376,7 @@ impl LevelsControllerConfig {
             max_file_id = max_file_id.max((*file_id).into());
 
             let compression = table_manifest.compression;
+            let global_version = table_manifest.global_version;
             let key_id = table_manifest.keyid;
             let key_registry_clone = key_registry.clone();
             let block_cache_clone = block_cache.clone();
@@ -373,6 +386,7 @@ impl LevelsControllerConfig {
             let future = async move {
                 let cipher = key_registry_clone.get_cipher(key_id).await?;
                 table_config.set_compression(compression);
+                table_config.set_global_version(global_version);
                 let mut fp_open_opt = OpenOptions::new();
                 fp_open_opt.read(true).write(!read_only);
 
@@ -440,7 +454,7 @@ impl LevelsControllerInner {
 
     #[inline]
     pub(crate) fn last_level_handler(&self) -> &LevelHandler {
-        debug_assert!(self.levels.len() > 0);
+        debug_assert!(!self.levels.is_empty());
         self.levels.last().unwrap()
     }
 
@@ -721,6 +735,10 @@ impl LevelsControllerInner {
     pub(crate) fn compact_status(&self) -> &CompactStatus {
         &self.compact_status
     }
+
+    pub(crate) fn compact_lock(&self) -> &RwLock<()> {
+        &self.compact_lock
+    }
 }
 
 pub(crate) fn revert_to_manifest(
@@ -729,7 +747,7 @@ pub(crate) fn revert_to_manifest(
     sst_id_set: HashSet<SSTableId>,
 ) -> anyhow::Result<()> {
     //check all files in manifest exist;
-    for (id, _) in manifest.tables.iter() {
+    for id in manifest.tables.keys() {
         if !sst_id_set.contains(id) {
             bail!("file does not exist for table {:?}", id);
         };
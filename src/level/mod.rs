@@ -1,6 +1,9 @@
+mod checkpoint;
 pub(crate) mod compact;
 pub(crate) mod compaction;
+pub(crate) mod drop;
 pub(crate) mod flush;
+mod ingest;
 pub(crate) mod level_handler;
 pub(crate) mod levels;
 mod plan;
@@ -48,7 +48,6 @@ impl CompactPlan {
     ) -> Self {
         Self {
             compact_task_id,
-            priority,
             this_level_handler,
             next_level_handler,
             top: vec![],
@@ -57,9 +56,31 @@ impl CompactPlan {
             this_range: KeyTsRange::default(),
             next_range: KeyTsRange::default(),
             splits: vec![],
-            drop_prefixes: vec![],
+            drop_prefixes: priority.drop_prefixes().to_vec(),
+            priority,
         }
     }
+    /// Rewrite the tables of one level in place, to drop the keys with drop_prefixes.
+    pub(super) async fn new_drop_prefixes(
+        controller: &LevelsControllerInner,
+        priority: CompactPriority,
+        handler: LevelHandler,
+        tables: Vec<Table>,
+    ) -> anyhow::Result<Self> {
+        let mut plan = Self::new(usize::MAX, priority, handler.clone(), handler);
+        plan.this_range = tables.as_slice().into();
+        plan.next_range = plan.this_range.clone();
+        plan.bottom = tables;
+        let lock = CompactPlanLockLevel {
+            this_level: plan.this_level_handler.read().await,
+            next_level: plan.next_level_handler.read().await,
+        };
+        if !controller.compact_status().try_update(&lock, &plan) {
+            bail!("Unable to add the compaction to drop prefixes")
+        }
+        drop(lock);
+        Ok(plan)
+    }
     pub(super) async fn fix(
         &mut self,
         controller: &LevelsControllerInner,
@@ -95,7 +116,7 @@ impl CompactPlan {
             next_level: self.next_level_handler.read().await,
         };
         let mut this_tables = lock.this_level.tables.clone();
-        this_tables.sort_unstable_by(|a, b| a.max_version().cmp(&b.max_version()));
+        this_tables.sort_unstable_by_key(|a| a.max_version());
 
         for table in this_tables {
             self.this_size = table.size();
@@ -111,9 +132,9 @@ impl CompactPlan {
             let index_range = lock.next_level.overlap_tables(&self.this_range);
             self.bottom = lock.next_level.tables[index_range].to_vec();
 
-            if self.bottom.len() == 0 {
+            if self.bottom.is_empty() {
                 self.next_range = self.this_range.clone();
-                if !controller.compact_status().try_update(&lock, &self) {
+                if !controller.compact_status().try_update(&lock, self) {
                     continue;
                 }
                 return true;
@@ -127,7 +148,7 @@ impl CompactPlan {
                 continue;
             }
 
-            if !controller.compact_status().try_update(&lock, &self) {
+            if !controller.compact_status().try_update(&lock, self) {
                 continue;
             }
             return true;
@@ -145,9 +166,9 @@ impl CompactPlan {
         };
         let tables = &lock.this_level.tables;
         let mut sorted_tables = tables.to_vec();
-        sorted_tables.sort_unstable_by(|a, b| b.stale_data_size().cmp(&a.stale_data_size()));
+        sorted_tables.sort_unstable_by_key(|t| std::cmp::Reverse(t.stale_data_size()));
 
-        if sorted_tables.len() > 0 && sorted_tables[0].stale_data_size() == 0 {
+        if !sorted_tables.is_empty() && sorted_tables[0].stale_data_size() == 0 {
             return false;
         }
         self.bottom.clear();
@@ -178,7 +199,7 @@ impl CompactPlan {
 
             //collect_bottom_tables
             let mut total_size = table.size();
-            let mut j = match tables.binary_search_by(|a| a.smallest().cmp(&table.smallest())) {
+            let mut j = match tables.binary_search_by(|a| a.smallest().cmp(table.smallest())) {
                 Ok(i) => i,
                 Err(i) => i,
             };
@@ -194,17 +215,17 @@ impl CompactPlan {
                 self.next_range.extend(new_table.into());
                 j += 1;
             }
-            if !controller.compact_status().try_update(&lock, &self) {
+            if !controller.compact_status().try_update(&lock, self) {
                 self.bottom.clear();
                 self.next_range = KeyTsRange::default();
                 continue;
             };
             return true;
         }
-        if self.top.len() == 0 {
+        if self.top.is_empty() {
             return false;
         }
-        controller.compact_status().try_update(&lock, &self)
+        controller.compact_status().try_update(&lock, self)
     }
     async fn fill_tables_level0(&mut self, controller: &LevelsControllerInner) -> bool {
         self.fill_tables_level0_to_levelbase(controller).await
@@ -227,11 +248,11 @@ impl CompactPlan {
             next_level: self.next_level_handler.read().await,
         };
         // let lock = self.lock_levels().await;
-        if lock.this_level.tables.len() == 0 {
+        if lock.this_level.tables.is_empty() {
             return false;
         }
 
-        if self.priority.drop_prefixes().len() > 0 {
+        if !self.priority.drop_prefixes().is_empty() {
             self.this_range = lock.this_level.tables.as_slice().into();
             self.top = lock.this_level.tables.clone();
         } else {
@@ -251,12 +272,12 @@ impl CompactPlan {
         let index_range = lock.next_level.overlap_tables(&self.this_range);
         self.bottom = lock.next_level.tables[index_range].to_vec();
 
-        self.next_range = if self.bottom.len() == 0 {
+        self.next_range = if self.bottom.is_empty() {
             self.this_range.clone()
         } else {
             self.bottom.as_slice().into()
         };
-        controller.compact_status().try_update(&lock, &self)
+        controller.compact_status().try_update(&lock, self)
     }
     async fn fill_tables_level0_to_level0(&mut self, controller: &LevelsControllerInner) -> bool {
         if self.compact_task_id != 0 {
@@ -282,8 +303,7 @@ impl CompactPlan {
             .iter()
             .filter(|t| t.size() < 2 * targets.file_size()[0])
             .filter(|t| now.duration_since(t.created_at()).unwrap() > Duration::from_secs(10))
-            .filter(|t| !status_w.tables().contains(&t.table_id()))
-            .map(|t| t.clone())
+            .filter(|t| !status_w.tables().contains(&t.table_id())).cloned()
             .collect::<Vec<_>>();
         drop(this_level_r);
 
@@ -377,6 +397,17 @@ impl CompactPlan {
     }
 }
 impl CompactStatus {
+    pub(super) fn delete(&self, plan: &CompactPlan) {
+        let mut inner_w = self.write();
+        inner_w.remove(plan.this_level_handler.level(), &plan.this_range);
+        if !plan.next_range.is_empty() {
+            inner_w.remove(plan.next_level_handler.level(), &plan.next_range);
+        }
+        for table in plan.top.iter().chain(plan.bottom.iter()) {
+            inner_w.tables_mut().remove(&table.table_id());
+        }
+        drop(inner_w);
+    }
     fn try_update(&self, _lock: &CompactPlanLockLevel, plan: &CompactPlan) -> bool {
         let mut inner_w = self.write();
         let r = inner_w.try_update(_lock, plan);
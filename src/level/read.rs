@@ -19,7 +19,7 @@ impl LevelsControllerInner {
         let mut max_txn = TxnTs::default();
         let mut max_value = None;
         for level_handler in &self.levels()[start_level..] {
-            if let Some((txn, value)) = level_handler.get(&key_ts).await? {
+            if let Some((txn, value)) = level_handler.get(key_ts).await? {
                 if txn == key_ts.txn_ts() {
                     return Ok(Some((txn, value)));
                 }
@@ -29,10 +29,9 @@ impl LevelsControllerInner {
                 }
             };
         }
-        if max_txn != TxnTs::default() && max_value.is_some() {
-            return Ok(Some((max_txn, max_value.unwrap())));
-        }
-        Ok(None)
+        Ok(max_value
+            .filter(|_| max_txn != TxnTs::default())
+            .map(|value| (max_txn, value)))
     }
 }
 impl LevelHandler {
@@ -86,9 +85,8 @@ impl LevelHandler {
                     }
                 };
             }
-            if max_txn != TxnTs::default() && max_value.is_some() {
-                let value = max_value.unwrap();
-                if !value.meta().is_empty() {
+            if let Some(value) = max_value.filter(|_| max_txn != TxnTs::default()) {
+                if !value.meta().is_empty() || !value.value().is_empty() {
                     return Ok(Some((max_txn, value)));
                 }
             }
@@ -101,16 +99,14 @@ impl LevelHandler {
             table_handlers
                 .tables
                 .iter()
-                .rev()
-                .map(|x| x.clone())
+                .rev().cloned()
                 .collect::<Vec<_>>()
                 .into()
         } else {
             let table_index = table_handlers
                 .tables
-                .binary_search_by(|t| t.biggest().cmp(&key_ts))
-                .ok()
-                .unwrap();
+                .binary_search_by(|t| t.biggest().cmp(key_ts))
+                .unwrap_or_else(|i| i);
             if table_index >= table_handlers.tables.len() {
                 return None;
             }
@@ -1,17 +1,13 @@
-#![feature(
-    ptr_internals,
-    ptr_sub_ptr,
-    slice_as_chunks,
-    step_trait,
-    unchecked_math
-)]
+#![feature(ptr_internals, step_trait)]
+// the unused parts of badger are kept for the later ports.
+#![allow(dead_code, internal_features, non_snake_case)]
 #[macro_use]
 extern crate lazy_static;
 pub mod config;
 pub mod db;
 pub(crate) mod default;
 pub mod errors;
-#[allow(dead_code, unused_imports)]
+#[allow(dead_code, unused_imports, mismatched_lifetime_syntaxes, clippy::all)]
 #[path = "./fb/flatbuffer_generated.rs"]
 mod fb;
 mod iter;
@@ -27,3 +23,7 @@ pub mod txn;
 mod util;
 mod vlog;
 mod write;
+pub use kv::{Entry, Meta, TxnTs};
+pub use pb::badgerpb4::{Kv, Match};
+pub use table::SstWriter;
+pub use util::publisher::Subscription;
@@ -3,7 +3,7 @@ use std::{
     fs::{rename, File, OpenOptions},
     io::{BufReader, Read, Seek, SeekFrom, Write},
     ops::{Deref, DerefMut},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -16,6 +16,7 @@ use crate::{
     config::CompressionType,
     default::DEFAULT_DIR,
     errors::err_file,
+    kv::TxnTs,
     pb::badgerpb4::{manifest_change, ManifestChange, ManifestChangeSet},
     util::{sys::sync_dir, SSTableId}, key_registry::CipherKeyId, level::levels::Level,
 };
@@ -67,6 +68,7 @@ pub(crate) struct TableManifest {
     pub(crate) level: Level,
     pub(crate) keyid: CipherKeyId,
     pub(crate) compression: CompressionType,
+    pub(crate) global_version: Option<TxnTs>,
 }
 
 #[derive(Debug, Clone)]
@@ -249,7 +251,7 @@ fn replay_manifest_file(fp: &File, ext_magic: u16) -> anyhow::Result<(ManifestIn
             bail!("buffer len too greater, Manifest file might be corrupted");
         }
 
-        let mut change_set_buf = vec![0 as u8; change_len];
+        let mut change_set_buf = vec![0_u8; change_len];
         match reader.read_exact(&mut change_set_buf) {
             Ok(_) => {
                 read_size += change_len;
@@ -275,10 +277,11 @@ impl ManifestInfo {
         for (id, table_manifest) in self.tables.iter() {
             changes.push(ManifestChange::new_create(
                 *id,
-                table_manifest.level.into(),
+                table_manifest.level,
                 table_manifest.keyid,
                 table_manifest.compression,
-            ));
+            )
+            .with_global_version(table_manifest.global_version));
         }
 
         changes
@@ -292,7 +295,7 @@ impl ManifestInfo {
     fn apply_manifest_change(&mut self, change: &ManifestChange) -> anyhow::Result<()> {
         match change.op() {
             manifest_change::Operation::Create => {
-                if self.tables.get(&change.table_id()).is_some() {
+                if self.tables.contains_key(&change.table_id()) {
                     bail!("MANIFEST invalid, table {:?} exists", change.table_id());
                 }
                 self.tables.insert(
@@ -301,10 +304,12 @@ impl ManifestInfo {
                         level: change.level.into(),
                         keyid: change.key_id.into(),
                         compression: CompressionType::from(change.compression),
+                        global_version: change.global_version(),
                     },
                 );
                 if self.levels.len() <= change.level as usize {
-                    self.levels.push(LevelManifest::default());
+                    self.levels
+                        .resize_with(change.level as usize + 1, LevelManifest::default);
                 }
                 self.levels[change.level as usize]
                     .tables
@@ -312,13 +317,16 @@ impl ManifestInfo {
                 self.creations += 1;
             }
             manifest_change::Operation::Delete => {
-                if self.tables.get(&change.table_id()).is_none() {
-                    bail!("MANIFEST removes non-existing table {:?}", change.table_id());
-                }
-                self.levels[change.level as usize]
+                // the level of delete change is not set, use the level of creation.
+                let table = match self.tables.remove(&change.table_id()) {
+                    Some(table) => table,
+                    None => {
+                        bail!("MANIFEST removes non-existing table {:?}", change.table_id())
+                    }
+                };
+                self.levels[table.level.to_usize()]
                     .tables
                     .remove(&change.table_id());
-                self.tables.remove(&change.table_id());
                 self.deletions += 1;
             }
         }
@@ -350,4 +358,18 @@ impl Manifest {
         inner.file.sync_all()?;
         Ok(())
     }
+
+    pub(crate) fn sync(&self) -> anyhow::Result<()> {
+        self.lock().file.sync_all()?;
+        Ok(())
+    }
+
+    // write a manifest of the current tables to dir.
+    pub(crate) fn write_to(&self, dir: &Path) -> anyhow::Result<()> {
+        let inner = self.lock();
+        let mut config = inner.config.clone();
+        config.set_dir(dir.to_path_buf());
+        config.help_rewrite(&inner.info)?;
+        Ok(())
+    }
 }
@@ -39,15 +39,15 @@ pub struct MemTableConfig {
 }
 impl Default for MemTableConfig {
     fn default() -> Self {
-        let mem_table_builder = Self {
+        
+        Self {
             dir: PathBuf::from(DEFAULT_DIR),
             read_only: false,
             memtable_size: 64 << 20,
             arena_size: 64 << 20,
             next_fid: Default::default(),
             num_memtables: 5,
-        };
-        mem_table_builder
+        }
     }
 }
 
@@ -74,7 +74,7 @@ impl MemTableConfig {
             }
             immut_memtable.push_back(Arc::new(memtable));
         }
-        if fids.len() != 0 {
+        if !fids.is_empty() {
             self.next_fid
                 .store((*fids.last().unwrap()).into(), Ordering::SeqCst)
         }
@@ -115,6 +115,7 @@ impl MemTableConfig {
         mem_table.reload()?;
         Ok((mem_table, false))
     }
+    #[allow(clippy::wrong_self_convention, clippy::new_ret_no_self)]
     pub(crate) async fn new(&self, key_registry: &KeyRegistry) -> anyhow::Result<MemTable> {
         let mut open_opt = OpenOptions::new();
         open_opt.read(true).write(true).create(true);
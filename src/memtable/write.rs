@@ -18,7 +18,7 @@ impl MemTable {
             return Ok(());
         }
         self.skip_list.push(
-            &entry.key_ts().serialize().as_ref(),
+            entry.key_ts().serialize().as_ref(),
             entry.value_meta().serialize().as_ref(),
         );
         self.max_version = self.max_version.max(entry.version());
@@ -32,8 +32,8 @@ impl MemTable {
             for (entry, _vptr) in next {
                 self.max_version = self.max_version.max(entry.version());
                 self.skip_list.push(
-                    &entry.key_ts().serialize().as_ref(),
-                    &entry.value_meta().serialize().as_ref(),
+                    entry.key_ts().serialize().as_ref(),
+                    entry.value_meta().serialize().as_ref(),
                 )
             }
         }
@@ -45,6 +45,9 @@ pub struct ManifestChange {
     /// Only used for CREATE Op.
     #[prost(uint32, tag = "6")]
     pub compression: u32,
+    /// The version of every key of an ingested table, 0 if none.
+    #[prost(uint64, tag = "7")]
+    pub global_version: u64,
 }
 /// Nested message and enum types in `ManifestChange`.
 pub mod manifest_change {
@@ -1,9 +1,10 @@
 use std::fmt::Display;
 
-use crate::{config::CompressionType, key_registry::CipherKeyId, util::SSTableId, level::levels::Level};
+use crate::{config::CompressionType, key_registry::CipherKeyId, kv::TxnTs, util::SSTableId, level::levels::Level};
 
 use self::badgerpb4::{manifest_change::Operation, Checksum, EncryptionAlgo, ManifestChange};
 use crate::pb::badgerpb4::checksum::Algorithm;
+#[allow(clippy::all)]
 pub mod badgerpb4;
 impl ManifestChange {
     pub fn new_create(
@@ -19,8 +20,13 @@ impl ManifestChange {
             key_id: cipher_key_id.into(),
             encryption_algo: EncryptionAlgo::Aes as i32,
             compression: compression.into(),
+            global_version: Default::default(),
         }
     }
+    pub(crate) fn with_global_version(mut self, global_version: Option<TxnTs>) -> Self {
+        self.global_version = global_version.unwrap_or_default().into();
+        self
+    }
     pub fn new_delete(table_id: SSTableId) -> Self {
         Self {
             id: table_id.into(),
@@ -29,11 +35,15 @@ impl ManifestChange {
             key_id: Default::default(),
             encryption_algo: Default::default(),
             compression: Default::default(),
+            global_version: Default::default(),
         }
     }
     pub(crate) fn table_id(&self) -> SSTableId {
         self.id.into()
     }
+    pub(crate) fn global_version(&self) -> Option<TxnTs> {
+        (self.global_version != 0).then_some(self.global_version.into())
+    }
 }
 impl Algorithm {
     pub(crate) fn calculate(&self, data: &[u8]) -> u64 {
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use anyhow::bail;
+use bytes::Bytes;
 use tokio::sync::RwLock;
 
 #[cfg(feature = "metrics")]
@@ -8,10 +9,18 @@ use crate::util::metrics::{add_num_gets, add_num_gets_with_result, add_num_memta
 use crate::{
     db::DB,
     errors::DBError,
-    kv::{KeyTs, TxnTs, ValueMeta},
+    kv::{KeyTs, Meta, TxnTs, ValueMeta, ValuePointer},
     memtable::MemTable,
 };
 impl DB {
+    /// resolve the user value, if the value_meta only contains the serialized ValuePointer.
+    pub(crate) async fn get_value(&self, value_meta: &ValueMeta) -> anyhow::Result<Bytes> {
+        if !value_meta.meta().contains(Meta::VALUE_POINTER) {
+            return Ok(value_meta.value().clone());
+        }
+        let vptr = ValuePointer::deserialize(value_meta.value());
+        self.vlog.read_value(&vptr).await
+    }
     pub(crate) async fn get(&self, key_ts: &KeyTs) -> anyhow::Result<Option<(TxnTs, ValueMeta)>> {
         if self.is_closed() {
             bail!(DBError::DBClosed);
@@ -69,18 +78,15 @@ impl DB {
                 max_value = value_meta.into();
             }
         };
-        Ok(if max_txn_ts != TxnTs::default() && max_value.is_some() {
-            Some((max_txn_ts, max_value.unwrap()))
-        } else {
-            None
-        })
+        Ok(max_value
+            .filter(|_| max_txn_ts != TxnTs::default())
+            .map(|value| (max_txn_ts, value)))
     }
     pub(crate) async fn get_memtable(&self) -> (Option<Arc<RwLock<MemTable>>>, Vec<Arc<MemTable>>) {
         let mut_memtable = self.memtable.clone();
         let immut_memtables_r = self.immut_memtable.read().await;
         let immut = immut_memtables_r
-            .iter()
-            .map(|x| x.clone())
+            .iter().cloned()
             .rev()
             .collect::<Vec<_>>();
         drop(immut_memtables_r);
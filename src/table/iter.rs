@@ -1,9 +1,10 @@
 use crate::{
     iter::{
-        DoubleEndedSinkIter, DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekIter,
-        KvSinkIter, SinkIter, SinkIterator,
+        DoubleEndedSinkIter, DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekBackIter,
+        KvSeekIter, KvSinkIter, SinkIter, SinkIterator,
     },
-    kv::ValueMeta,
+    kv::{KeyTsBorrow, ValueMeta},
+    util::skip_list::SkipListOwnedIter,
 };
 
 use super::{read::SinkTableIter, Table};
@@ -27,9 +28,6 @@ impl SinkTableConcatIter {
             use_cache,
         }
     }
-    fn double_ended_eq(&self) -> bool {
-        self.key() == self.key_back() && self.value() == self.value_back()
-    }
 }
 impl SinkIter for SinkTableConcatIter {
     type Item = SinkTableIter;
@@ -55,70 +53,54 @@ impl DoubleEndedSinkIter for SinkTableConcatIter {
 }
 impl SinkIterator for SinkTableConcatIter {
     fn next(&mut self) -> Result<bool, anyhow::Error> {
-        if self.double_ended_eq() {
-            return Ok(false);
-        }
-        let new_index = match self.index {
-            Some(index) => {
-                if let Some(cur) = self.iters[index].as_mut() {
-                    if cur.next()? {
-                        return Ok(!self.double_ended_eq());
-                    };
-                    if index == self.tables.len() - 1 {
-                        return Ok(false);
-                    }
-                    index + 1
-                } else {
-                    index
-                }
-            }
-            None => {
-                if self.tables.len() == 0 {
+        let mut index = self.index.unwrap_or_default();
+        while index < self.tables.len() {
+            if let Some(back_index) = self.back_index {
+                if index > back_index {
                     return Ok(false);
                 }
-                0
             }
-        };
-        let mut iter = self.tables[new_index].iter(self.use_cache);
-        if !iter.next()? {
-            return Ok(false);
-        };
-        self.iters[new_index] = Some(iter);
-        Ok(!self.double_ended_eq())
+            // reuse the iter which may be already used by next_back, so they can meet each other.
+            let iter =
+                self.iters[index].get_or_insert_with(|| self.tables[index].iter(self.use_cache));
+            self.index = Some(index);
+            if iter.next()? {
+                return Ok(true);
+            }
+            if Some(index) == self.back_index {
+                return Ok(false);
+            }
+            index += 1;
+        }
+        Ok(false)
     }
 }
 impl DoubleEndedSinkIterator for SinkTableConcatIter {
     fn next_back(&mut self) -> Result<bool, anyhow::Error> {
-        if self.double_ended_eq() {
+        if self.tables.is_empty() {
             return Ok(false);
         }
-        let new_index = match self.back_index {
-            Some(back_index) => {
-                if let Some(cur) = self.iters[back_index].as_mut() {
-                    if cur.next()? {
-                        return Ok(!self.double_ended_eq());
-                    };
-                    if back_index == 0 {
-                        return Ok(false);
-                    }
-                    0
-                } else {
-                    back_index
-                }
-            }
-            None => {
-                if self.tables.len() == 0 {
+        let mut back_index = match self.back_index {
+            Some(back_index) => back_index,
+            None => self.tables.len() - 1,
+        };
+        loop {
+            if let Some(index) = self.index {
+                if back_index < index {
                     return Ok(false);
                 }
-                self.tables.len() - 1
             }
-        };
-        let mut iter = self.tables[new_index].iter(self.use_cache);
-        if !iter.next_back()? {
-            return Ok(false);
-        };
-        self.iters[new_index] = Some(iter);
-        Ok(!self.double_ended_eq())
+            let iter = self.iters[back_index]
+                .get_or_insert_with(|| self.tables[back_index].iter(self.use_cache));
+            self.back_index = Some(back_index);
+            if iter.next_back()? {
+                return Ok(true);
+            }
+            if Some(back_index) == self.index || back_index == 0 {
+                return Ok(false);
+            }
+            back_index -= 1;
+        }
     }
 }
 impl KvSinkIter<ValueMeta> for SinkTableConcatIter {
@@ -132,18 +114,18 @@ impl KvSinkIter<ValueMeta> for SinkTableConcatIter {
 }
 impl KvDoubleEndedSinkIter<ValueMeta> for SinkTableConcatIter {
     fn key_back(&self) -> Option<crate::kv::KeyTsBorrow<'_>> {
-        self.item_back().and_then(|x| x.key())
+        self.item_back().and_then(|x| x.key_back())
     }
 
     fn value_back(&self) -> Option<ValueMeta> {
-        self.item_back().and_then(|x| x.value())
+        self.item_back().and_then(|x| x.value_back())
     }
 }
 impl KvSeekIter for SinkTableConcatIter {
     fn seek(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
         let index = match self
             .tables
-            .binary_search_by(|t| t.biggest().partial_cmp(&k).unwrap())
+            .binary_search_by(|t| KeyTsBorrow::cmp(&t.biggest().serialize(), &k))
         {
             Ok(index) => index,  // t.biggest() ==k
             Err(index) => index, // t.biggest() > k and (t-1).biggest() < k
@@ -152,24 +134,42 @@ impl KvSeekIter for SinkTableConcatIter {
             return Ok(false);
         }
 
-        if let Some(cur) = self.iters[index].as_mut() {
-            return cur.seek(k);
-        } else {
-            let mut iter = self.tables[index].iter(self.use_cache);
-            if iter.seek(k)? {
-                self.index = Some(index);
-                self.iters[index] = Some(iter);
-                return Ok(true);
-            };
-            return Ok(false);
-        }
+        let mut iter = self.tables[index].iter(self.use_cache);
+        let result = iter.seek(k)?;
+        self.index = Some(index);
+        self.iters[index] = Some(iter);
+        Ok(result)
+    }
+}
+impl KvSeekBackIter for SinkTableConcatIter {
+    fn seek_back(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let index = match self
+            .tables
+            .binary_search_by(|t| KeyTsBorrow::cmp(&t.smallest().serialize(), &k))
+        {
+            Ok(index) => index, // t.smallest() ==k
+            Err(0) => return Ok(false),
+            Err(index) => index - 1, // t.smallest() < k and (t+1).smallest() > k
+        };
+
+        let mut iter = self.tables[index].iter(self.use_cache);
+        let result = iter.seek_back(k)?;
+        self.back_index = Some(index);
+        self.iters[index] = Some(iter);
+        Ok(result)
     }
 }
 pub(crate) enum SinkMergeNodeIter {
+    SkipList(SkipListOwnedIter),
     Table(SinkTableIter),
     TableConcat(SinkTableConcatIter),
     Merge(Box<SinkMergeIter>),
 }
+impl From<SkipListOwnedIter> for SinkMergeNodeIter {
+    fn from(value: SkipListOwnedIter) -> Self {
+        Self::SkipList(value)
+    }
+}
 impl From<SinkTableIter> for SinkMergeNodeIter {
     fn from(value: SinkTableIter) -> Self {
         Self::Table(value)
@@ -189,6 +189,7 @@ impl From<SinkMergeIter> for SinkMergeNodeIter {
 impl SinkIterator for SinkMergeNodeIter {
     fn next(&mut self) -> Result<bool, anyhow::Error> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.next(),
             SinkMergeNodeIter::Table(iter) => iter.next(),
             SinkMergeNodeIter::TableConcat(iter) => iter.next(),
             SinkMergeNodeIter::Merge(iter) => iter.next(),
@@ -198,6 +199,7 @@ impl SinkIterator for SinkMergeNodeIter {
 impl DoubleEndedSinkIterator for SinkMergeNodeIter {
     fn next_back(&mut self) -> Result<bool, anyhow::Error> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.next_back(),
             SinkMergeNodeIter::Table(iter) => iter.next_back(),
             SinkMergeNodeIter::TableConcat(iter) => iter.next_back(),
             SinkMergeNodeIter::Merge(iter) => iter.next_back(),
@@ -207,6 +209,7 @@ impl DoubleEndedSinkIterator for SinkMergeNodeIter {
 impl KvSinkIter<ValueMeta> for SinkMergeNodeIter {
     fn key(&self) -> Option<crate::kv::KeyTsBorrow<'_>> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.key(),
             SinkMergeNodeIter::Table(iter) => iter.key(),
             SinkMergeNodeIter::TableConcat(iter) => iter.key(),
             SinkMergeNodeIter::Merge(iter) => iter.key(),
@@ -215,6 +218,7 @@ impl KvSinkIter<ValueMeta> for SinkMergeNodeIter {
 
     fn value(&self) -> Option<ValueMeta> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.value(),
             SinkMergeNodeIter::Table(iter) => iter.value(),
             SinkMergeNodeIter::TableConcat(iter) => iter.value(),
             SinkMergeNodeIter::Merge(iter) => iter.value(),
@@ -224,6 +228,7 @@ impl KvSinkIter<ValueMeta> for SinkMergeNodeIter {
 impl KvDoubleEndedSinkIter<ValueMeta> for SinkMergeNodeIter {
     fn key_back(&self) -> Option<crate::kv::KeyTsBorrow<'_>> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.key_back(),
             SinkMergeNodeIter::Table(iter) => iter.key_back(),
             SinkMergeNodeIter::TableConcat(iter) => iter.key_back(),
             SinkMergeNodeIter::Merge(iter) => iter.key_back(),
@@ -232,6 +237,7 @@ impl KvDoubleEndedSinkIter<ValueMeta> for SinkMergeNodeIter {
 
     fn value_back(&self) -> Option<ValueMeta> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.value_back(),
             SinkMergeNodeIter::Table(iter) => iter.value_back(),
             SinkMergeNodeIter::TableConcat(iter) => iter.value_back(),
             SinkMergeNodeIter::Merge(iter) => iter.value_back(),
@@ -241,12 +247,23 @@ impl KvDoubleEndedSinkIter<ValueMeta> for SinkMergeNodeIter {
 impl KvSeekIter for SinkMergeNodeIter {
     fn seek(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
         match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.seek(k),
             SinkMergeNodeIter::Table(iter) => iter.seek(k),
             SinkMergeNodeIter::TableConcat(iter) => iter.seek(k),
             SinkMergeNodeIter::Merge(iter) => iter.seek(k),
         }
     }
 }
+impl KvSeekBackIter for SinkMergeNodeIter {
+    fn seek_back(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        match self {
+            SinkMergeNodeIter::SkipList(iter) => iter.seek_back(k),
+            SinkMergeNodeIter::Table(iter) => iter.seek_back(k),
+            SinkMergeNodeIter::TableConcat(iter) => iter.seek_back(k),
+            SinkMergeNodeIter::Merge(iter) => iter.seek_back(k),
+        }
+    }
+}
 struct SinkMergeNode {
     valid: bool,
     valid_back: bool,
@@ -333,7 +350,16 @@ impl KvDoubleEndedSinkIter<ValueMeta> for SinkMergeNode {
 }
 impl KvSeekIter for SinkMergeNode {
     fn seek(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
-        self.iter.seek(k)
+        let result = self.iter.seek(k);
+        self.valid = matches!(result, Ok(true));
+        result
+    }
+}
+impl KvSeekBackIter for SinkMergeNode {
+    fn seek_back(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let result = self.iter.seek_back(k);
+        self.valid_back = matches!(result, Ok(true));
+        result
     }
 }
 
@@ -450,7 +476,7 @@ impl SinkIterator for SinkMergeIter {
             }
 
             let result = self.smaller_mut().next()?;
-            if self.bigger().valid {
+            if self.right.is_some() && self.bigger().valid {
                 if result {
                     if self.bigger().key().is_none() && !self.bigger_mut().next()? {
                         continue;
@@ -474,9 +500,9 @@ impl SinkIterator for SinkMergeIter {
 }
 impl DoubleEndedSinkIterator for SinkMergeIter {
     fn next_back(&mut self) -> Result<bool, anyhow::Error> {
-        while self.back_bigger().valid {
+        while self.back_bigger().valid_back {
             if let Some(k) = self.back_bigger().key_back() {
-                if self.back_temp_key.as_slice() == k.as_ref() {
+                if self.back_temp_key.as_slice() != k.as_ref() {
                     if !self.temp_key.is_empty() && self.temp_key == k.as_ref() {
                         return Ok(false);
                     }
@@ -485,7 +511,7 @@ impl DoubleEndedSinkIterator for SinkMergeIter {
                 }
             }
             let result = self.back_bigger_mut().next_back()?;
-            if self.back_smaller().valid {
+            if self.right.is_some() && self.back_smaller().valid_back {
                 if result {
                     if self.back_smaller().key_back().is_none()
                         && !self.back_smaller_mut().next_back()?
@@ -533,36 +559,61 @@ impl KvDoubleEndedSinkIter<ValueMeta> for SinkMergeIter {
 }
 impl KvSeekIter for SinkMergeIter {
     fn seek(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
-        let l = self.left.seek(k)?;
-        let r = match self.right.as_mut() {
-            Some(r) => r.seek(k)?,
-            None => false,
-        };
-        if self.bigger().valid {
-            if self.smaller().valid {
-                self.left_small = !self.left_small
-            } else {
-                if self.bigger().key().is_none() {
-                    self.bigger_mut().next()?;
+        self.left.seek(k)?;
+        self.left_small = true;
+        if let Some(right) = self.right.as_mut() {
+            right.seek(k)?;
+            let ord = match (self.left.key(), right.key()) {
+                (Some(l), Some(r)) => l.cmp(&r),
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                _ => std::cmp::Ordering::Less,
+            };
+            match ord {
+                std::cmp::Ordering::Less => {}
+                std::cmp::Ordering::Equal => {
+                    right.next()?;
                 }
-                if self.bigger().key().is_some() {
-                    match self.smaller().key().cmp(&self.bigger().key()) {
-                        std::cmp::Ordering::Less => {}
-                        std::cmp::Ordering::Equal => {
-                            self.bigger_mut().next()?;
-                        }
-                        std::cmp::Ordering::Greater => {
-                            self.left_small = !self.left_small;
-                        }
-                    };
+                std::cmp::Ordering::Greater => {
+                    self.left_small = false;
                 }
             }
         }
-        if l || r {
-            self.temp_key = self.smaller().key().unwrap().to_vec();
-            Ok(true)
-        } else {
-            Ok(false)
+        match self.smaller().key() {
+            Some(key) => {
+                self.temp_key = key.to_vec();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+impl KvSeekBackIter for SinkMergeIter {
+    fn seek_back(&mut self, k: crate::kv::KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        self.left.seek_back(k)?;
+        self.back_left_big = true;
+        if let Some(right) = self.right.as_mut() {
+            right.seek_back(k)?;
+            let ord = match (self.left.key_back(), right.key_back()) {
+                (Some(l), Some(r)) => l.cmp(&r),
+                (None, Some(_)) => std::cmp::Ordering::Less,
+                _ => std::cmp::Ordering::Greater,
+            };
+            match ord {
+                std::cmp::Ordering::Less => {
+                    self.back_left_big = false;
+                }
+                std::cmp::Ordering::Equal => {
+                    right.next_back()?;
+                }
+                std::cmp::Ordering::Greater => {}
+            }
+        }
+        match self.back_bigger().key_back() {
+            Some(key) => {
+                self.back_temp_key = key.to_vec();
+                Ok(true)
+            }
+            None => Ok(false),
         }
     }
 }
@@ -1,5 +1,6 @@
 pub(crate) mod iter;
 pub(crate) mod read;
+mod sst_writer;
 #[cfg(test)]
 mod test;
 pub(crate) mod write;
@@ -16,6 +17,7 @@ use flatbuffers::InvalidFlatbuffer;
 use prost::Message;
 
 use self::read::SinkBlockIter;
+pub use self::sst_writer::SstWriter;
 use crate::fb::fb::TableIndex;
 use crate::iter::{DoubleEndedSinkIterator, KvDoubleEndedSinkIter};
 use crate::key_registry::NONCE_SIZE;
@@ -31,8 +33,10 @@ use crate::{config::CompressionType, util::mmap::MmapFile};
 
 // ChecksumVerificationMode tells when should DB verify checksum for SSTable blocks.
 #[derive(Debug, Clone, Copy)]
+#[derive(Default)]
 pub enum ChecksumVerificationMode {
     // NoVerification indicates DB should not verify checksum for SSTable blocks.
+    #[default]
     NoVerification,
 
     // OnTableRead indicates checksum should be verified while opening SSTtable.
@@ -45,11 +49,6 @@ pub enum ChecksumVerificationMode {
     // on SSTable opening and on every block read.
     OnTableAndBlockRead,
 }
-impl Default for ChecksumVerificationMode {
-    fn default() -> Self {
-        Self::NoVerification
-    }
-}
 #[derive(Debug, Clone)]
 pub struct TableConfig {
     // Open tables in read only mode.
@@ -70,6 +69,9 @@ pub struct TableConfig {
     compression: CompressionType,
 
     zstd_compression_level: i32,
+
+    // The version of every key of an ingested table, instead of the versions in the file.
+    global_version: Option<TxnTs>,
 }
 impl Default for TableConfig {
     fn default() -> Self {
@@ -82,6 +84,7 @@ impl Default for TableConfig {
             compression: CompressionType::default(),
             zstd_compression_level: 1,
             checksum_algo: badgerpb4::checksum::Algorithm::Crc32c,
+            global_version: None,
         }
     }
 }
@@ -126,6 +129,14 @@ impl TableConfig {
     pub fn compression(&self) -> CompressionType {
         self.compression
     }
+
+    pub(crate) fn set_global_version(&mut self, global_version: Option<TxnTs>) {
+        self.global_version = global_version;
+    }
+
+    pub(crate) fn global_version(&self) -> Option<TxnTs> {
+        self.global_version
+    }
 }
 impl TableConfig {
     pub(crate) async fn open(
@@ -145,7 +156,14 @@ impl TableConfig {
 
         let (table_index, index_start, index_len) = Self::init_index(&mmap_f, cipher.as_ref())?;
 
-        let (smallest, biggest) = self.get_smallest_biggest(&table_index, &mmap_f, &cipher)?;
+        let (mut smallest, mut biggest) =
+            self.get_smallest_biggest(&table_index, &mmap_f, &cipher)?;
+        let mut cheap_index: CheapTableIndex = (&table_index).into();
+        if let Some(global_version) = self.global_version {
+            smallest.set_txn_ts(global_version);
+            biggest.set_txn_ts(global_version);
+            cheap_index.max_version = global_version;
+        }
 
         let inner = TableInner {
             mmap_f,
@@ -160,7 +178,7 @@ impl TableConfig {
             cipher,
             index_start,
             index_len,
-            cheap_index: (&table_index).into(),
+            cheap_index,
         };
 
         match inner.config.checksum_verify_mode {
@@ -183,7 +201,7 @@ impl TableConfig {
         cipher: &Option<AesCipher>,
     ) -> anyhow::Result<(KeyTs, KeyTs)> {
         //get smallest
-        let first_block_offset = table_index.offsets().get(0).unwrap();
+        let first_block_offset = table_index.offsets().first().unwrap();
         let first_block_base_key = first_block_offset.key_ts();
         let smallest = first_block_base_key.to_owned();
 
@@ -222,14 +240,14 @@ impl TableConfig {
         read_pos -= 4;
         // let mut buf = [0; 4];
         // mmap_f.read_slice(read_pos, &mut buf[..])?;
-        let mut buf = mmap_f.read_slice_ref(read_pos as usize, 4)?;
+        let mut buf = mmap_f.read_slice_ref(read_pos, 4)?;
         let checksum_len = buf.get_u32() as usize;
 
         //read checksum
-        read_pos -= checksum_len as usize;
+        read_pos -= checksum_len;
         // let mut buf = vec![0; checksum_len];
         // mmap_f.read_slice_ref(read_pos, &mut buf)?;
-        let buf = mmap_f.read_slice_ref(read_pos, checksum_len as usize)?;
+        let buf = mmap_f.read_slice_ref(read_pos, checksum_len)?;
         let checksum = Checksum::decode(buf)?;
 
         //read index size from the footer
@@ -247,9 +265,9 @@ impl TableConfig {
 
         checksum.verify(data)?;
 
-        let index_buf = TableIndexBuf::from_vec(try_decrypt(cipher, data.as_ref())?)?;
+        let index_buf = TableIndexBuf::from_vec(try_decrypt(cipher, data)?)?;
 
-        debug_assert!(index_buf.offsets.len() != 0);
+        debug_assert!(!index_buf.offsets.is_empty());
 
         Ok((index_buf, index_start, index_len))
     }
@@ -366,7 +384,7 @@ impl TableInner {
 
     #[inline]
     fn get_file_path(&self) -> &PathBuf {
-        &self.mmap_f.path()
+        self.mmap_f.path()
     }
 
     pub(crate) fn sync_mmap(&self) -> io::Result<()> {
@@ -471,7 +489,7 @@ impl TableInner {
         let table_index_buf = self.get_index()?;
         let bloom = table_index_buf.bloom_filter.as_ref().unwrap();
         let bloom: BloomBorrow = bloom.as_ref().into();
-        let may_contain = bloom.may_contain_key(&key.key());
+        let may_contain = bloom.may_contain_key(key.key());
         if !may_contain {
             #[cfg(feature = "metrics")]
             add_num_bloom_not_exist(1);
@@ -502,9 +520,9 @@ impl TableInner {
             .mmap_f
             .read_slice_ref(self.index_start, self.index_len)?;
 
-        let index_buf = TableIndexBuf::from_vec(try_decrypt(self.cipher.as_ref(), data.as_ref())?)?;
+        let index_buf = TableIndexBuf::from_vec(try_decrypt(self.cipher.as_ref(), data)?)?;
         self.index_cache.insert(self.table_id, index_buf.clone());
-        return Ok(index_buf);
+        Ok(index_buf)
     }
     #[cfg(feature = "async_cache")]
     async fn get_block(&self, block_index: BlockIndex, use_cache: bool) -> anyhow::Result<Block> {
@@ -724,8 +742,8 @@ impl TableIndexBuf {
                 assert!(offset.key_ts().is_some());
                 BlockOffsetBuf {
                     key_ts: KeyTs::from(offset.key_ts().unwrap().bytes()),
-                    offset: offset.offset().clone(),
-                    len: offset.len().clone(),
+                    offset: offset.offset(),
+                    len: offset.len(),
                 }
             })
             .collect::<Vec<_>>();
@@ -811,9 +829,9 @@ impl From<u32> for BlockIndex {
         Self(value)
     }
 }
-impl Into<u32> for BlockIndex {
-    fn into(self) -> u32 {
-        self.0
+impl From<BlockIndex> for u32 {
+    fn from(val: BlockIndex) -> Self {
+        val.0
     }
 }
 impl From<usize> for BlockIndex {
@@ -821,9 +839,9 @@ impl From<usize> for BlockIndex {
         Self(value as u32)
     }
 }
-impl Into<usize> for BlockIndex {
-    fn into(self) -> usize {
-        self.0 as usize
+impl From<BlockIndex> for usize {
+    fn from(val: BlockIndex) -> Self {
+        val.0 as usize
     }
 }
 #[derive(Debug, Clone)]
@@ -905,7 +923,7 @@ impl BlockInner {
 
         //read checksum
         read_pos -= checksum_len;
-        let checksum = (&data[read_pos..read_pos + checksum_len]).to_vec();
+        let checksum = data[read_pos..read_pos + checksum_len].to_vec();
 
         data.truncate(read_pos);
 
@@ -1,12 +1,12 @@
 use crate::{
     iter::{
-        DoubleEndedSinkIter, DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekIter,
-        KvSinkIter, SinkIter, SinkIterator,
+        DoubleEndedSinkIter, DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekBackIter,
+        KvSeekIter, KvSinkIter, SinkIter, SinkIterator,
     },
-    kv::{KeyTsBorrow, ValueMeta},
+    kv::{KeyTsBorrow, TxnTs, ValueMeta},
 };
 
-use super::{Block, EntryHeader, TableInner, HEADER_SIZE, Table};
+use super::{Block, EntryHeader, HEADER_SIZE, Table};
 pub(crate) struct SinkTableIter {
     inner: Table,
     use_cache: bool,
@@ -36,6 +36,11 @@ impl DoubleEndedSinkIter for SinkTableIter {
     }
 }
 impl SinkTableIter {
+    fn block_iter(&self, block: Block) -> SinkBlockIter {
+        let mut iter = block.iter();
+        iter.global_version = self.inner.config.global_version();
+        iter
+    }
     fn double_ended_eq(&self) -> bool {
         if let Some(iter) = self.block_iter.as_ref() {
             if let Some(back_iter) = self.back_block_iter.as_ref() {
@@ -44,7 +49,7 @@ impl SinkTableIter {
                 }
             }
         }
-        return false;
+        false
     }
 }
 impl SinkIterator for SinkTableIter {
@@ -71,12 +76,12 @@ impl SinkIterator for SinkTableIter {
             }
         };
         let next_block = self.inner.get_block(new_block_index, self.use_cache)?;
-        self.block_iter = next_block.iter().into();
+        self.block_iter = self.block_iter(next_block).into();
         if self.block_iter.as_mut().unwrap().next()? {
-            return Ok(!self.double_ended_eq());
+            Ok(!self.double_ended_eq())
         } else {
-            return Ok(false);
-        };
+            Ok(false)
+        }
     }
 }
 impl DoubleEndedSinkIterator for SinkTableIter {
@@ -103,12 +108,12 @@ impl DoubleEndedSinkIterator for SinkTableIter {
             }
         };
         let block = self.inner.get_block(new_block_index, self.use_cache)?;
-        self.back_block_iter = block.iter().into();
+        self.back_block_iter = self.block_iter(block).into();
         if self.back_block_iter.as_mut().unwrap().next_back()? {
-            return Ok(!self.double_ended_eq());
+            Ok(!self.double_ended_eq())
         } else {
-            return Ok(false);
-        };
+            Ok(false)
+        }
     }
 }
 impl KvSinkIter<ValueMeta> for SinkTableIter {
@@ -141,31 +146,88 @@ impl KvDoubleEndedSinkIter<ValueMeta> for SinkTableIter {
         None
     }
 }
-impl KvSeekIter for SinkTableIter {
-    fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+impl SinkTableIter {
+    // Ok(index) means the first key of block[index] == k,
+    // Err(index) means the first key of block[index-1] < k < the first key of block[index]
+    fn search_block(&self, k: KeyTsBorrow<'_>) -> anyhow::Result<Result<usize, usize>> {
         let index_buf = self.inner.get_index()?;
-        let search = index_buf
+        Ok(index_buf
             .offsets()
-            .binary_search_by(|b| b.key_ts().partial_cmp(&k).unwrap());
-        let index = match search {
+            .binary_search_by(|b| KeyTsBorrow::cmp(&b.key_ts().serialize(), &k)))
+    }
+}
+impl KvSeekIter for SinkTableIter {
+    fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let Some(global_version) = self.inner.config.global_version() else {
+            return self.seek_in_file(k);
+        };
+        // each key has one version in the file, seek to it at any version,
+        // then skip it if its global version is newer than k.
+        let key_ts = with_version(k.key(), u64::MAX.into());
+        if !self.seek_in_file(key_ts.as_slice().into())? {
+            return Ok(false);
+        }
+        if self.key().is_some_and(|key| key.key() == k.key()) && global_version > k.txn_ts() {
+            return self.next();
+        }
+        Ok(true)
+    }
+}
+impl KvSeekBackIter for SinkTableIter {
+    fn seek_back(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let Some(global_version) = self.inner.config.global_version() else {
+            return self.seek_back_in_file(k);
+        };
+        let key_ts = with_version(k.key(), 0.into());
+        if !self.seek_back_in_file(key_ts.as_slice().into())? {
+            return Ok(false);
+        }
+        if self.key_back().is_some_and(|key| key.key() == k.key()) && global_version < k.txn_ts()
+        {
+            return self.next_back();
+        }
+        Ok(true)
+    }
+}
+fn with_version(key: &[u8], version: TxnTs) -> Vec<u8> {
+    let mut key_ts = Vec::with_capacity(key.len() + 8);
+    key_ts.extend_from_slice(key);
+    key_ts.extend_from_slice(&version.to_u64().to_be_bytes());
+    key_ts
+}
+impl SinkTableIter {
+    // seek by the keys and versions written in the file.
+    fn seek_in_file(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let index = match self.search_block(k)? {
             Ok(index) => index,
-            Err(index) => {
-                if index >= index_buf.offsets().len() {
-                    return Ok(false);
-                }
-                index - 1
-            }
+            Err(index) => index.max(1) - 1,
         };
-        let next_block = self.inner.get_block(index.into(), self.use_cache)?;
-        self.block_iter = next_block.iter().into();
-        match self.block_iter.as_mut() {
-            Some(block) => {
-                return block.seek(k);
-            }
-            None => {
-                unreachable!()
-            }
+        let block = self.inner.get_block(index.into(), self.use_cache)?;
+        let mut block_iter = self.block_iter(block);
+        if block_iter.seek(k)? {
+            self.block_iter = block_iter.into();
+            return Ok(true);
+        }
+        // k is bigger than all keys of this block, so the first key of next block is what we want.
+        if index + 1 >= self.inner.block_offsets_len() {
+            return Ok(false);
         }
+        let block = self.inner.get_block((index + 1).into(), self.use_cache)?;
+        self.block_iter = self.block_iter(block).into();
+        self.block_iter.as_mut().unwrap().next()
+    }
+
+    fn seek_back_in_file(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let index = match self.search_block(k)? {
+            Ok(index) => index,
+            Err(0) => return Ok(false),
+            Err(index) => index - 1,
+        };
+        let block = self.inner.get_block(index.into(), self.use_cache)?;
+        let mut back_block_iter = self.block_iter(block);
+        let result = back_block_iter.seek_back(k)?;
+        self.back_block_iter = back_block_iter.into();
+        Ok(result)
     }
 }
 pub(crate) struct SinkBlockIter {
@@ -178,6 +240,8 @@ pub(crate) struct SinkBlockIter {
     back_key: Vec<u8>,
     back_header: EntryHeader,
     back_entry_index: Option<usize>,
+
+    global_version: Option<TxnTs>,
 }
 impl From<Block> for SinkBlockIter {
     fn from(value: Block) -> Self {
@@ -190,6 +254,7 @@ impl From<Block> for SinkBlockIter {
             back_key: Default::default(),
             back_header: Default::default(),
             back_entry_index: None,
+            global_version: None,
         }
     }
 }
@@ -213,7 +278,7 @@ impl SinkBlockIter {
         let entry_offset = self.inner.entry_offsets[entry_index] as usize;
         let data = &self.inner.data()[entry_offset..];
         let next_header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
-        let prev_overlap = self.header.get_overlap();
+        let prev_overlap = self.prev_overlap(&self.header);
         let next_overlap = next_header.get_overlap();
         if next_overlap > prev_overlap {
             self.key.truncate(prev_overlap);
@@ -225,6 +290,29 @@ impl SinkBlockIter {
         self.key
             .extend_from_slice(&data[HEADER_SIZE..HEADER_SIZE + next_header.get_diff()]);
         self.header = next_header;
+        self.set_global_version(true);
+    }
+    // the overlap may cover the version of the key, which is replaced by the global version,
+    // so the key is rebuilt from the base key then.
+    fn prev_overlap(&self, prev_header: &EntryHeader) -> usize {
+        if self.global_version.is_some() {
+            0
+        } else {
+            prev_header.get_overlap()
+        }
+    }
+    fn set_global_version(&mut self, front: bool) {
+        if let Some(global_version) = self.global_version {
+            let key = if front {
+                &mut self.key
+            } else {
+                &mut self.back_key
+            };
+            if key.len() >= 8 {
+                let split = key.len() - 8;
+                key[split..].copy_from_slice(&global_version.to_u64().to_be_bytes());
+            }
+        }
     }
 }
 //base key 123 1  iter.key=null
@@ -249,22 +337,23 @@ impl SinkIterator for SinkBlockIter {
                     }
                 }
                 self.set_entry_index(id + 1);
-                return Ok(true);
+                Ok(true)
             }
             None => {
-                if self.inner.entry_offsets.len() == 0 {
+                if self.inner.entry_offsets.is_empty() {
                     return Ok(false);
                 }
 
-                if self.base_key.len() == 0 {
+                if self.base_key.is_empty() {
                     let data = self.inner.data();
                     let header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
                     self.base_key = data[HEADER_SIZE..HEADER_SIZE + header.get_diff()].to_vec();
                     self.header = header;
                 }
                 self.key = self.base_key.to_vec();
+                self.set_global_version(true);
                 self.entry_index = 0.into();
-                return Ok(true);
+                Ok(true)
             }
         }
     }
@@ -290,7 +379,7 @@ impl DoubleEndedSinkIterator for SinkBlockIter {
                 let next_back_entry_offset = self.inner.entry_offsets[back_id - 1] as usize;
                 let data = &self.inner.data()[next_back_entry_offset..];
                 let next_back_header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
-                let prev_back_overlap = self.back_header.get_overlap();
+                let prev_back_overlap = self.prev_overlap(&self.back_header);
                 let next_back_overlap = next_back_header.get_overlap();
 
                 if next_back_overlap > prev_back_overlap {
@@ -305,14 +394,15 @@ impl DoubleEndedSinkIterator for SinkBlockIter {
                 );
 
                 self.back_header = next_back_header;
-                return Ok(true);
+                self.set_global_version(false);
+                Ok(true)
             }
             None => {
-                if self.inner.entry_offsets.len() == 0 {
+                if self.inner.entry_offsets.is_empty() {
                     return Ok(false);
                 }
 
-                if self.base_key.len() == 0 {
+                if self.base_key.is_empty() {
                     let data = self.inner.data();
                     let header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
                     self.base_key = data[HEADER_SIZE..HEADER_SIZE + header.get_diff()].to_vec();
@@ -327,7 +417,8 @@ impl DoubleEndedSinkIterator for SinkBlockIter {
                     &data[HEADER_SIZE..HEADER_SIZE + self.back_header.get_diff()],
                 );
                 self.back_entry_index = Some(self.inner.entry_offsets.len() - 1);
-                return Ok(true);
+                self.set_global_version(false);
+                Ok(true)
             }
         }
     }
@@ -335,10 +426,10 @@ impl DoubleEndedSinkIterator for SinkBlockIter {
 
 impl KvSinkIter<ValueMeta> for SinkBlockIter {
     fn key(&self) -> Option<KeyTsBorrow<'_>> {
-        if self.key.len() == 0 {
+        if self.key.is_empty() {
             return None;
         }
-        return Some(self.key.as_slice().into());
+        Some(self.key.as_slice().into())
     }
 
     fn value(&self) -> Option<ValueMeta> {
@@ -359,10 +450,10 @@ impl KvSinkIter<ValueMeta> for SinkBlockIter {
 }
 impl KvDoubleEndedSinkIter<ValueMeta> for SinkBlockIter {
     fn key_back(&self) -> Option<KeyTsBorrow<'_>> {
-        if self.back_key.len() == 0 {
+        if self.back_key.is_empty() {
             return None;
         }
-        return Some(self.back_key.as_slice().into());
+        Some(self.back_key.as_slice().into())
     }
 
     fn value_back(&self) -> Option<ValueMeta> {
@@ -382,36 +473,48 @@ impl KvDoubleEndedSinkIter<ValueMeta> for SinkBlockIter {
         None
     }
 }
-impl KvSeekIter for SinkBlockIter {
-    fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
-        if self.entry_index.is_none() {
-            if !self.next()? {
-                return Ok(false);
-            };
+impl SinkBlockIter {
+    fn init_base_key(&mut self) {
+        if self.base_key.is_empty() {
+            let data = self.inner.data();
+            let header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
+            self.base_key = data[HEADER_SIZE..HEADER_SIZE + header.get_diff()].to_vec();
+            self.header = header;
         }
-
-        let search = self.inner.entry_offsets.binary_search_by(|offset| {
+    }
+    fn search(&self, k: KeyTsBorrow<'_>) -> Result<usize, usize> {
+        self.inner.entry_offsets.binary_search_by(|offset| {
             let entry_offset = *offset as usize;
             let data = &self.inner.data()[entry_offset..];
             let header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
-            if k.len() >= header.get_overlap() {
-                if header.get_overlap() > 8 {
+            if k.len() >= header.get_overlap()
+                && header.get_overlap() > 8 {
                     let split =
                         (header.get_overlap() + header.get_diff() - 8).min(header.get_overlap());
-                    match self.base_key[..split].cmp(&k[..split]) {
-                        std::cmp::Ordering::Equal => {}
-                        ord => return ord,
+                    // only compare the user key part of both sides.
+                    if k.len() >= split + 8 {
+                        match self.base_key[..split].cmp(&k[..split]) {
+                            std::cmp::Ordering::Equal => {}
+                            ord => return ord,
+                        }
                     }
                 }
-            }
             let mut key = vec![0u8; header.get_overlap() + header.get_diff()];
             key[..header.get_overlap()].copy_from_slice(&self.base_key[..header.get_overlap()]);
             key[header.get_overlap()..]
                 .copy_from_slice(&data[HEADER_SIZE..HEADER_SIZE + header.get_diff()]);
             KeyTsBorrow::cmp(&key, &k)
-        });
+        })
+    }
+}
+impl KvSeekIter for SinkBlockIter {
+    fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        if self.entry_index.is_none()
+            && !self.next()? {
+                return Ok(false);
+            };
 
-        let entry_index = match search {
+        let entry_index = match self.search(k) {
             Ok(index) => index,
             Err(index) => {
                 if index >= self.inner.get_entry_offsets().len() {
@@ -421,6 +524,29 @@ impl KvSeekIter for SinkBlockIter {
             }
         };
         self.set_entry_index(entry_index);
-        return Ok(true);
+        Ok(true)
+    }
+}
+impl KvSeekBackIter for SinkBlockIter {
+    fn seek_back(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        if self.inner.entry_offsets.is_empty() {
+            return Ok(false);
+        }
+        self.init_base_key();
+
+        let back_entry_index = match self.search(k) {
+            Ok(index) => index,
+            Err(0) => return Ok(false),
+            Err(index) => index - 1,
+        };
+        let entry_offset = self.inner.entry_offsets[back_entry_index] as usize;
+        let data = &self.inner.data()[entry_offset..];
+        self.back_header = EntryHeader::deserialize(&data[..HEADER_SIZE]);
+        self.back_key = self.base_key[..self.back_header.get_overlap()].to_vec();
+        self.back_key
+            .extend_from_slice(&data[HEADER_SIZE..HEADER_SIZE + self.back_header.get_diff()]);
+        self.set_global_version(false);
+        self.back_entry_index = back_entry_index.into();
+        Ok(true)
     }
 }
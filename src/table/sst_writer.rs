@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use anyhow::bail;
+use bytes::Bytes;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::{
+    errors::DBError,
+    kv::{Entry, KeyTs, KeyTsBorrow, Meta, TxnTs},
+};
+
+use super::{write::TableBuilder, TableConfig};
+
+/// Writes a table file out of db, which is added by DB::ingest_external_files.
+/// The compression is not kept in the file, so it must be written with the table config of db.
+pub struct SstWriter {
+    path: PathBuf,
+    builder: TableBuilder,
+    last: KeyTs,
+}
+impl SstWriter {
+    pub fn new<P: Into<PathBuf>>(path: P, config: TableConfig) -> Self {
+        Self {
+            path: path.into(),
+            builder: TableBuilder::new(config, None),
+            last: KeyTs::default(),
+        }
+    }
+
+    pub fn set<B: Into<Bytes>>(&mut self, key: B, value: B) -> anyhow::Result<()> {
+        self.push(Entry::new(key.into(), value.into()))
+    }
+
+    /// The entries must be pushed in the order of key then version (newest first),
+    /// the entry without version is written at version 1.
+    pub fn push(&mut self, mut entry: Entry) -> anyhow::Result<()> {
+        if entry.key().is_empty() {
+            bail!(DBError::EmptyKey);
+        }
+        if entry.version() == TxnTs::default() {
+            entry.set_version(1.into());
+        }
+        let key_ts = entry.key_ts().clone();
+        if !self.last.is_empty() && key_ts <= self.last {
+            bail!(
+                "Keys are not in sorted order: {:?} after {:?}",
+                key_ts,
+                self.last
+            );
+        }
+        // the values are kept in the table, there is no vlog out of db.
+        let keep = Meta::DELETE | Meta::DISCARD_EARLIER_VERSIONS | Meta::MERGE_ENTRY;
+        entry.set_meta(entry.meta() & keep);
+        let key_ts_bytes = key_ts.serialize();
+        self.builder.push(
+            &KeyTsBorrow::from(key_ts_bytes.as_slice()),
+            entry.value_meta(),
+            None,
+        );
+        self.last = key_ts;
+        Ok(())
+    }
+
+    /// Writes and syncs the file, fails if it already exists.
+    pub async fn finish(mut self) -> anyhow::Result<()> {
+        if self.builder.is_empty() {
+            bail!("Cannot write an empty table to {:?}", self.path);
+        }
+        let data = self.builder.finish().await?;
+        let mut fp = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&self.path)
+            .await?;
+        fp.write_all(&data).await?;
+        fp.sync_all().await?;
+        Ok(())
+    }
+}
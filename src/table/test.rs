@@ -22,7 +22,7 @@ mod test_table_iter {
         let mut table_builder = TableBuilder::build_l0_table(iter, vec![], config, None)?;
         let index_cache = IndexCacheConfig::default().build()?;
         let block_cache = None;
-        Ok(table_builder.build(path, index_cache, block_cache).await?)
+        table_builder.build(path, index_cache, block_cache).await
     }
 
     #[tokio::test]
@@ -133,7 +133,7 @@ mod test_block_iter {
         }
         block_builder.finish_block(crate::pb::badgerpb4::checksum::Algorithm::Crc32c);
         let data = block_builder.data().to_vec();
-        let block_inner = BlockInner::deserialize(0.into(), (0 as usize).into(), 0, data).unwrap();
+        let block_inner = BlockInner::deserialize(0.into(), 0_usize.into(), 0, data).unwrap();
         block_inner.verify().unwrap();
         block_inner.into()
     }
@@ -67,7 +67,7 @@ impl BlockBuilder {
     }
 
     pub(super) fn should_finish_block(&self, key: &KeyTsBorrow, value: &ValueMeta,block_size:usize,is_encrypt:bool) -> bool {
-        if self.entry_offsets.len() == 0 {
+        if self.entry_offsets.is_empty() {
             return false;
         }
         debug_assert!((self.entry_offsets.len() as u32 + 1) * 4 + 4 + 8 + 4 < u32::MAX);
@@ -84,11 +84,11 @@ impl BlockBuilder {
     }
 
     pub(super) fn push_entry(&mut self,key_ts: &KeyTsBorrow,value: &ValueMeta){
-        let diff_key=if self.base_keyts.len()==0 {
+        let diff_key=if self.base_keyts.is_empty() {
             self.base_keyts=key_ts.to_vec();
             key_ts
         }else{
-            &key_ts[self.diff_base_key(&key_ts)..]
+            &key_ts[self.diff_base_key(key_ts)..]
         };
         assert!(key_ts.len()-diff_key.len() <= u16::MAX as usize);
         assert!(diff_key.len() <= u16::MAX as usize);
@@ -113,17 +113,18 @@ impl TableBuilder {
     pub(crate) fn new(table_config: TableConfig,cipher:Option<AesCipher>) -> Self {
         // let pre_alloc_size = MAX_BUFFER_BLOCK_SIZE.min(table_opt.table_size());
         let cur_block = BlockBuilder::new(table_config.block_size);
-        let mut table_builder = Self::default();
-        table_builder.cur_block = cur_block;
         // table_builder.alloc = Vec::with_capacity(pre_alloc_size);
-        table_builder.config = table_config;
-        table_builder.cipher=cipher;
-        table_builder
+        Self {
+            cur_block,
+            config: table_config,
+            cipher,
+            ..Default::default()
+        }
     }
 
     #[inline]
     fn push_internal(&mut self, key_ts: &KeyTsBorrow, value: &ValueMeta,vptr_len:Option<u32>, is_stale: bool) {
-        if self.cur_block.should_finish_block(&key_ts, &value,self.config.block_size,self.cipher.is_some()) {
+        if self.cur_block.should_finish_block(key_ts, value,self.config.block_size,self.cipher.is_some()) {
             if is_stale{
                 self.stale_data_size+=key_ts.len() as u32+4;
             }
@@ -131,12 +132,12 @@ impl TableBuilder {
         };
         self.key_hashes.push(Bloom::hash(key_ts.key()));
         self.max_version=self.max_version.max(key_ts.txn_ts());
-        self.cur_block.push_entry(key_ts, &value);
+        self.cur_block.push_entry(key_ts, value);
         self.on_disk_size+=vptr_len.unwrap_or(0);
     }
 
     pub(crate) fn reacded_capacity(&self)->bool{
-        let mut sum_block_sizes = self.compressed_size.load(Ordering::Acquire);;
+        let mut sum_block_sizes = self.compressed_size.load(Ordering::Acquire);
         if self.config.compression==CompressionType::None && self.cipher.is_none(){
             sum_block_sizes=self.uncompressed_size.load(Ordering::Acquire);
         }
@@ -145,7 +146,7 @@ impl TableBuilder {
         estimate_size as usize > self.config.table_capacity
     }
     fn finish_cur_block(&mut self){
-        if self.cur_block.entry_offsets.len()==0 {
+        if self.cur_block.entry_offsets.is_empty() {
             return;
         }
         self.cur_block.finish_block(self.config.checksum_algo);
@@ -281,14 +282,14 @@ impl TableBuilder {
     {
         let mut table_builder = Self::new(config,cipher);
         while iter.next()? {
-            let key_ts: KeyTsBorrow = iter.key().unwrap().into();
+            let key_ts: KeyTsBorrow = iter.key().unwrap();
             let key_bytes = key_ts.as_ref();
-            if drop_prefixed.len() > 0 && drop_prefixed.iter().any(|x| key_bytes.starts_with(x)) {
+            if !drop_prefixed.is_empty() && drop_prefixed.iter().any(|x| key_bytes.starts_with(x)) {
                 continue;
             }
             let value: ValueMeta = iter.value().unwrap().into();
             let vptr_len=if value.meta().contains(Meta::VALUE_POINTER) {
-                let vp = ValuePointer::deserialize(&value.value());
+                let vp = ValuePointer::deserialize(value.value());
                 vp.len().into()
             }else {
                 None
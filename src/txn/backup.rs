@@ -0,0 +1,193 @@
+use std::io::ErrorKind;
+
+use anyhow::bail;
+use bytes::Bytes;
+use prost::Message;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{
+    db::DB,
+    kv::{Entry, Meta, TxnTs},
+    pb::badgerpb4::Kv,
+};
+
+use super::{IteratorOptions, WriteBatch};
+
+impl DB {
+    /// Writes every live version newer than since as length delimited Kv records, deleted and expired
+    /// versions are also written for the incremental backups (since > 0), so that they are replayed by load.
+    /// Returns the read ts of the backup, which is the since of the next incremental backup.
+    /// With managed_txns the backup reads the newest versions, and returns the max version written.
+    pub async fn backup<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        since: TxnTs,
+    ) -> anyhow::Result<TxnTs> {
+        let is_managed = self.oracle.config().managed_txns();
+        let txn = if is_managed {
+            self.new_txn_at(u64::MAX, false).await?
+        } else {
+            self.new_read_txn().await?
+        };
+        let since = since.to_u64();
+        let opt = IteratorOptions::default()
+            .set_all_versions(true)
+            .set_internal_access(true);
+        let mut iter = txn.iter(opt).await?;
+        iter.rewind().await?;
+
+        let mut upto = since;
+        // the older versions of this key are not live.
+        let mut skip_key: Option<Vec<u8>> = None;
+        while let Some(item) = iter.item() {
+            upto = upto.max(item.version());
+            if item.version() <= since || skip_key.as_deref() == Some(item.key()) {
+                iter.next().await?;
+                continue;
+            }
+            skip_key = None;
+            let dead = item.is_deleted_or_expired();
+            if dead || item.meta().contains(Meta::DISCARD_EARLIER_VERSIONS) {
+                skip_key = Some(item.key().to_vec());
+            }
+            if !dead || since > 0 {
+                let kv = item.to_kv().await?;
+                writer
+                    .write_all(&kv.encode_length_delimited_to_vec())
+                    .await?;
+            }
+            iter.next().await?;
+        }
+        drop(iter);
+        if !is_managed {
+            upto = txn.read_ts.to_u64();
+        }
+        txn.discard().await?;
+        writer.flush().await?;
+        Ok(upto.into())
+    }
+
+    /// Replays the records written by backup into a fresh db, the versions are kept.
+    /// At most max_pending_writes batches are sent but not yet written.
+    pub async fn load<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        max_pending_writes: usize,
+    ) -> anyhow::Result<()> {
+        let mut reader = BufReader::new(reader);
+        let mut batch = WriteBatch::new(self.clone(), None).set_max_pending(max_pending_writes);
+        let mut max_version = TxnTs::default();
+        let mut buf = Vec::new();
+        while let Some(len) = read_len(&mut reader).await? {
+            buf.resize(len, 0);
+            reader.read_exact(&mut buf).await?;
+            let e = entry_from_kv(Kv::decode(buf.as_slice())?)?;
+            max_version = max_version.max(e.version());
+            batch.push_entry(e).await?;
+        }
+        batch.flush().await?;
+        self.oracle.set_max_version(max_version).await
+    }
+}
+// the entry of a kv written by backup or Stream, the versions are kept.
+pub(super) fn entry_from_kv(kv: Kv) -> anyhow::Result<Entry> {
+    if kv.version == 0 {
+        bail!("Invalid version 0 of key {:?}", kv.key);
+    }
+    let mut e = Entry::new(Bytes::from(kv.key), Bytes::from(kv.value));
+    e.set_version(kv.version.into());
+    e.set_expires_at(kv.expires_at);
+    if let Some(user_meta) = kv.user_meta.first() {
+        e.set_user_meta(*user_meta);
+    }
+    if let Some(meta) = kv.meta.first() {
+        // the txn of the entry is not kept, so neither its flags.
+        let keep = Meta::DELETE | Meta::DISCARD_EARLIER_VERSIONS | Meta::MERGE_ENTRY;
+        e.set_meta(Meta::from_bits_retain(*meta) & keep);
+    }
+    Ok(e)
+}
+// read the varint length of the next record, None at the end of reader.
+async fn read_len<R: AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<Option<usize>> {
+    let mut len = 0u64;
+    for i in 0..10 {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(e) => bail!(e),
+        };
+        len |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some(len as usize));
+        }
+    }
+    bail!("Invalid length of Kv record")
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{
+            tests::{delete, set, test_config},
+            DB,
+        },
+        errors::DBError,
+        kv::Entry,
+    };
+
+
+    #[tokio::test]
+    async fn test_backup_load() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let big = Bytes::from(vec![7u8; 1 << 12]);
+        set(&db, "a", Bytes::from("1")).await?;
+        set(&db, "b", big.clone()).await?;
+        set(&db, "c", Bytes::from("1")).await?;
+        delete(&db, "c").await?;
+        let mut txn = db.get_update_txn().await?;
+        let mut e =
+            Entry::new(Bytes::from("d"), Bytes::from("1")).with_ttl(Duration::from_secs(3600));
+        e.set_user_meta(3);
+        txn.set_entry(e).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let mut full = Vec::new();
+        let since = db.backup(&mut full, 0.into()).await?;
+        set(&db, "a", Bytes::from("2")).await?;
+        delete(&db, "b").await?;
+        let mut incremental = Vec::new();
+        let upto = db.backup(&mut incremental, since).await?;
+        assert_eq!(upto, since + 2);
+
+        let dir = tempfile::tempdir()?;
+        let restored = DB::open(test_config(dir.path())).await?;
+        restored.load(full.as_slice(), 2).await?;
+        restored.load(incremental.as_slice(), 2).await?;
+
+        let txn = restored.new_read_txn().await?;
+        assert_eq!(txn.get("a").await?.value().await?, &Bytes::from("2"));
+        for key in ["b", "c"] {
+            let err = txn.get(key).await.unwrap_err();
+            assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        }
+        let item = txn.get("d").await?;
+        assert_eq!(item.user_meta(), 3);
+        assert!(item.expires_at().is_some());
+        txn.discard().await?;
+
+        // the commits after load are newer than the loaded versions.
+        set(&restored, "a", Bytes::from("3")).await?;
+        let txn = restored.new_read_txn().await?;
+        let item = txn.get("a").await?;
+        assert_eq!(item.value().await?, &Bytes::from("3"));
+        assert!(item.version() > u64::from(upto));
+        txn.discard().await?;
+        db.close().await?;
+        restored.close().await
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, bail};
+use bytes::{BufMut, Bytes, BytesMut};
+use log::info;
+
+use crate::{db::DB, errors::DBError, kv::Entry};
+
+use super::{IteratorOptions, BANNED_NAMESPACES_KEY};
+
+impl DB {
+    /// Reads and writes of the keys in the namespace fail with BannedKey and iterators skip them.
+    /// The ban is persisted, but the existing data is not dropped.
+    pub async fn ban_namespace(&self, name_space: u64) -> anyhow::Result<()> {
+        if self.opt.name_space_offset().is_none() {
+            bail!(DBError::NamespaceMode);
+        }
+        // the commit ts of the managed txns is chosen by the caller.
+        if self.oracle.config().managed_txns {
+            bail!(DBError::BanNamespaceManagedTxns);
+        }
+        let mut key = BytesMut::with_capacity(BANNED_NAMESPACES_KEY.len() + 8);
+        key.put_slice(BANNED_NAMESPACES_KEY);
+        key.put_u64(name_space);
+        let mut txn = self.get_update_txn().await?;
+        let result = async {
+            txn.modify_internal(Entry::new(key.freeze(), Bytes::new()))
+                .await?;
+            txn.commit().await
+        }
+        .await;
+        txn.discard().await?;
+        result.map_err(|e| anyhow!("Failed to ban namespace {} for {}", name_space, e))?;
+
+        self.banned_namespaces.write().await.insert(name_space);
+        info!("Namespace {} is banned", name_space);
+        Ok(())
+    }
+
+    /// Returns the banned namespaces in ascending order.
+    pub async fn banned_namespaces(&self) -> Vec<u64> {
+        let mut name_spaces = self
+            .banned_namespaces
+            .read()
+            .await
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        name_spaces.sort_unstable();
+        name_spaces
+    }
+
+    // rebuild the banned namespaces from the persisted keys.
+    pub(crate) async fn init_banned_namespaces(&self) -> anyhow::Result<()> {
+        if self.opt.name_space_offset().is_none() {
+            return Ok(());
+        }
+        let txn = self.new_read_txn().await?;
+        let opt = IteratorOptions::default()
+            .set_prefix(BANNED_NAMESPACES_KEY)
+            .set_internal_access(true);
+        let mut iter = txn.iter(opt).await?;
+        iter.rewind().await?;
+        let mut banned_w = self.banned_namespaces.write().await;
+        while let Some(item) = iter.item() {
+            let suffix = &item.key()[BANNED_NAMESPACES_KEY.len()..];
+            let Ok(buf) = <[u8; 8]>::try_from(suffix) else {
+                bail!("Invalid banned namespace key {:?}", item.key());
+            };
+            banned_w.insert(u64::from_be_bytes(buf));
+            iter.next().await?;
+        }
+        drop(banned_w);
+        drop(iter);
+        txn.discard().await
+    }
+}
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+        txn::IteratorOptions,
+    };
+
+    fn key(name_space: u64, suffix: &str) -> Bytes {
+        let mut key = name_space.to_be_bytes().to_vec();
+        key.extend_from_slice(suffix.as_bytes());
+        key.into()
+    }
+    #[tokio::test]
+    async fn test_ban_namespace() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config.clone()).await?;
+        let err = db.ban_namespace(1).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::NamespaceMode)));
+        db.close().await?;
+        drop(dir);
+
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path()).set_name_space_offset(0);
+        let db = DB::open(config.clone()).await?;
+        let mut txn = db.get_update_txn().await?;
+        for name_space in [1, 2, 3] {
+            txn.set(key(name_space, "k"), Bytes::from("v")).await?;
+        }
+        txn.commit().await?;
+        txn.discard().await?;
+
+        db.ban_namespace(2).await?;
+        assert_eq!(db.banned_namespaces().await, vec![2]);
+
+        let mut txn = db.get_update_txn().await?;
+        let err = txn.get(key(2, "k")).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::BannedKey)));
+        let err = txn.set(key(2, "x"), Bytes::from("v")).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::BannedKey)));
+        txn.discard().await?;
+
+        let txn = db.new_read_txn().await?;
+        let mut iter = txn.iter(IteratorOptions::default()).await?;
+        iter.rewind().await?;
+        let mut keys = Vec::new();
+        while let Some(item) = iter.item() {
+            keys.push(Bytes::copy_from_slice(item.key()));
+            iter.next().await?;
+        }
+        assert_eq!(keys, vec![key(1, "k"), key(3, "k")]);
+        drop(iter);
+        txn.discard().await?;
+
+        // the bans are rebuilt from the persisted keys.
+        db.close().await?;
+        let db = DB::open(config).await?;
+        assert_eq!(db.banned_namespaces().await, vec![2]);
+        let txn = db.new_read_txn().await?;
+        let err = txn.get(key(2, "k")).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::BannedKey)));
+        assert!(txn.get(key(1, "k")).await.is_ok());
+        txn.discard().await?;
+        db.close().await
+    }
+    #[tokio::test]
+    async fn test_ban_namespace_managed_txns() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path()).set_name_space_offset(0);
+        config.txn.set_managed_txns(true);
+        let db = DB::open(config).await?;
+        let err = db.ban_namespace(1).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DBError::BanNamespaceManagedTxns)
+        ));
+        db.close().await
+    }
+}
@@ -0,0 +1,233 @@
+use std::{mem::take, sync::Arc};
+
+use anyhow::bail;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use tokio::sync::Semaphore;
+
+use crate::{
+    db::DB,
+    errors::DBError,
+    kv::{Entry, Meta, TxnTs},
+};
+
+use super::{check_entry, WriteOptions, BADGER_PREFIX};
+
+const DEFAULT_MAX_PENDING: usize = 16;
+
+/// Writes entries in batches without conflict detection, splits them at max_batch_count / max_batch_size,
+/// so it can load any number of keys. Entries are not atomic across batches.
+/// Call flush at the end, the entries not flushed are dropped.
+pub struct WriteBatch {
+    db: DB,
+    // only for managed_txns=true, otherwise get the commit_ts from oracle for each batch.
+    commit_ts: Option<TxnTs>,
+    entries: Vec<Entry>,
+    count: usize,
+    size: usize,
+    max_pending: usize,
+    pending: Arc<Semaphore>,
+    error: Arc<Mutex<Option<anyhow::Error>>>,
+}
+impl DB {
+    pub fn new_write_batch(&self) -> WriteBatch {
+        if self.oracle.config().managed_txns {
+            panic!(
+                "Cannot use new_write_batch with managed_txns=true. Use new_write_batch_at instead"
+            );
+        }
+        WriteBatch::new(self.clone(), None)
+    }
+
+    /// Only for managed_txns=true, all the entries without version are written at commit_ts.
+    pub fn new_write_batch_at(&self, commit_ts: u64) -> WriteBatch {
+        if !self.oracle.config().managed_txns {
+            panic!("Cannot use new_write_batch_at with managed_txns=false. Use new_write_batch instead");
+        }
+        WriteBatch::new(self.clone(), TxnTs::from(commit_ts).into())
+    }
+}
+impl WriteBatch {
+    pub(super) fn new(db: DB, commit_ts: Option<TxnTs>) -> Self {
+        Self {
+            db,
+            commit_ts,
+            entries: Vec::new(),
+            count: 0,
+            size: 0,
+            max_pending: DEFAULT_MAX_PENDING,
+            pending: Arc::new(Semaphore::new(DEFAULT_MAX_PENDING)),
+            error: Default::default(),
+        }
+    }
+
+    /// Max number of batches sent to the write channel but not yet written.
+    pub fn set_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self.pending = Arc::new(Semaphore::new(self.max_pending));
+        self
+    }
+
+    pub async fn set<B: Into<Bytes>>(&mut self, key: B, value: B) -> anyhow::Result<()> {
+        self.set_entry(Entry::new(key.into(), value.into())).await
+    }
+
+    pub async fn delete<B: Into<Bytes>>(&mut self, key: B) -> anyhow::Result<()> {
+        let mut e = Entry::default();
+        e.set_key(key);
+        e.set_meta(Meta::DELETE);
+        self.set_entry(e).await
+    }
+
+    pub async fn set_entry(&mut self, e: Entry) -> anyhow::Result<()> {
+        if e.key().starts_with(BADGER_PREFIX) {
+            bail!(DBError::InvalidKey)
+        }
+        self.push_entry(e).await
+    }
+
+    // internal keys are allowed, the entries with version keep it.
+    pub(super) async fn push_entry(&mut self, mut e: Entry) -> anyhow::Result<()> {
+        if let Some(err) = self.error.lock().as_ref() {
+            bail!("Previous batch failed: {}", err);
+        }
+        check_entry(&self.db, &e).await?;
+        e.apply_ttl(self.db.opt.now());
+
+        let threshold = self.db.opt.vlog_threshold.value_threshold();
+        let max_batch_count = self.db.opt.max_batch_count();
+        let max_batch_size = self.db.opt.max_batch_size();
+        e.try_set_value_threshold(threshold);
+        let size = e.estimate_size(threshold) + 10;
+        if size >= max_batch_size {
+            bail!(DBError::TxnTooBig)
+        }
+        if self.count + 1 >= max_batch_count || self.size + size >= max_batch_size {
+            self.send().await?;
+        }
+        self.count += 1;
+        self.size += size;
+        self.entries.push(e);
+        Ok(())
+    }
+
+    /// Send the remaining entries and wait for all the batches, returns the first error.
+    pub async fn flush(mut self) -> anyhow::Result<()> {
+        let result = self.send().await;
+        let _all = self.pending.acquire_many(self.max_pending as u32).await?;
+        result?;
+        match self.error.lock().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    // the lock is held across the await to keep the timestamps in order.
+    #[allow(clippy::await_holding_lock)]
+    async fn send(&mut self) -> anyhow::Result<()> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let permit = self.pending.clone().acquire_owned().await?;
+        let mut entries = take(&mut self.entries);
+        let size = take(&mut self.size);
+        self.count = 0;
+
+        let oracle = &self.db.oracle;
+        let _guard = oracle.send_write_req.lock();
+        let commit_ts = match self.commit_ts {
+            Some(commit_ts) => commit_ts,
+            None => oracle.new_commit_ts().await?,
+        };
+        for e in entries.iter_mut() {
+            if e.version() == TxnTs::default() {
+                e.set_version(commit_ts);
+            }
+        }
+        let recv = match self
+            .db
+            .send_entires_to_write_channel(entries, size, WriteOptions::default(), None)
+            .await
+        {
+            Ok(recv) => recv,
+            Err(e) => {
+                oracle.done_commit(commit_ts).await?;
+                bail!(e)
+            }
+        };
+        drop(_guard);
+
+        let oracle = oracle.clone();
+        let error = self.error.clone();
+        tokio::spawn(async move {
+            let result = match recv.await {
+                Ok(r) => r,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result.and(oracle.done_commit(commit_ts).await) {
+                error.lock().get_or_insert(e);
+            }
+            drop(permit);
+        });
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+    };
+
+    #[tokio::test]
+    async fn test_write_batch() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.memtable.set_memtable_size(1 << 20);
+        let db = DB::open(config).await?;
+
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+        let value = |i: usize| Bytes::from(format!("{:0200}", i));
+        // more than max_batch_size in total, so it must be split.
+        let mut batch = db.new_write_batch().set_max_pending(2);
+        for i in 0..2000 {
+            batch.set(key(i), value(i)).await?;
+        }
+        batch.delete(key(0)).await?;
+        let err = batch.set("!badger!seq", "").await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::InvalidKey)));
+        batch.flush().await?;
+
+        let txn = db.new_read_txn().await?;
+        let err = txn.get(key(0)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        for i in 1..2000 {
+            assert_eq!(txn.get(key(i)).await?.value().await?, &value(i));
+        }
+        txn.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_write_batch_at() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.txn.set_managed_txns(true);
+        let db = DB::open(config).await?;
+
+        let mut batch = db.new_write_batch_at(10);
+        batch.set("a", "1").await?;
+        batch.flush().await?;
+        let mut batch = db.new_write_batch_at(20);
+        batch.set("a", "2").await?;
+        batch.flush().await?;
+
+        for (read_ts, value) in [(15, "1"), (25, "2")] {
+            let txn = db.new_txn_at(read_ts, false).await?;
+            assert_eq!(txn.get("a").await?.value().await?, &Bytes::from(value));
+            txn.discard().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use anyhow::bail;
+use bytes::Bytes;
+use futures::Stream;
+use log::error;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{
+    db::DB,
+    kv::TxnTs,
+    pb::badgerpb4::{Kv, Match},
+    util::{publisher::Subscription, tire::Trie},
+};
+
+use super::{IteratorOptions, Txn};
+
+// the max number of the kvs in one batch of the history.
+const HISTORY_BATCH_KVS: usize = 1 << 12;
+// the number of the history batches buffered for the stream.
+const HISTORY_CAPACITY: usize = 16;
+
+/// Stream of the changes newer than a version, first the versions <= read_ts kept in the LSM in the
+/// order of the keys (newest version first), then the live updates in the order of commit ts,
+/// one batch per commit. To resume the stream with DB::changes_since after a restart, pass the
+/// last Kv::version consumed once it's above read_ts, otherwise the same since again.
+/// The history is read in the background while the stream is polled. The stream ends early
+/// if reading the history fails, or if the live updates lag 1000 batches behind the writes,
+/// then resume it the same way.
+pub struct Changes {
+    history: Option<Receiver<anyhow::Result<Vec<Kv>>>>,
+    failed: bool,
+    // the versions <= read_ts are in the history.
+    read_ts: TxnTs,
+    subscription: Subscription,
+}
+impl Changes {
+    /// The versions <= read_ts are sent by the history, and the newer ones by the live updates.
+    pub fn read_ts(&self) -> TxnTs {
+        self.read_ts
+    }
+}
+impl Stream for Changes {
+    type Item = Vec<Kv>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.failed {
+            return Poll::Ready(None);
+        }
+        if let Some(history) = self.history.as_mut() {
+            match history.poll_recv(cx) {
+                Poll::Ready(Some(Ok(kvs))) => return Poll::Ready(Some(kvs)),
+                Poll::Ready(Some(Err(e))) => {
+                    // skipping the rest of the history would leave a gap.
+                    error!("Failed to read the history of the changes: {}", e);
+                    self.failed = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) => self.history = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        loop {
+            match Pin::new(&mut self.subscription).poll_next(cx) {
+                Poll::Ready(Some(mut kvs)) => {
+                    let read_ts = self.read_ts.to_u64();
+                    kvs.retain(|kv| kv.version > read_ts);
+                    if !kvs.is_empty() {
+                        return Poll::Ready(Some(kvs));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+impl DB {
+    /// Returns the changes of the keys matching any of the matches with version > since.
+    /// Only the versions not yet discarded by compaction can be replayed, see num_versions_to_keep.
+    pub async fn changes_since(
+        &self,
+        since: TxnTs,
+        matches: Vec<Match>,
+    ) -> anyhow::Result<Changes> {
+        self.changes_since_with(since, matches, HISTORY_BATCH_KVS)
+            .await
+    }
+
+    pub(crate) async fn changes_since_with(
+        &self,
+        since: TxnTs,
+        matches: Vec<Match>,
+        batch_kvs: usize,
+    ) -> anyhow::Result<Changes> {
+        if self.oracle.config().managed_txns() {
+            bail!("changes_since is not supported for managed DB");
+        }
+        let mut trie = Trie::default();
+        for m in matches.iter() {
+            trie.push_match(m, 0)?;
+        }
+        // subscribe before the read_ts is taken, so the commits after read_ts are all published to it.
+        let subscription = self.subscribe(matches).await?;
+        let txn = self.new_read_txn().await?;
+        let read_ts = txn.read_ts;
+
+        let history = if since < read_ts {
+            let (sender, receiver) = mpsc::channel(HISTORY_CAPACITY);
+            tokio::spawn(send_history(txn, since, trie, batch_kvs.max(1), sender));
+            Some(receiver)
+        } else {
+            txn.discard().await?;
+            None
+        };
+        Ok(Changes {
+            history,
+            failed: false,
+            read_ts,
+            subscription,
+        })
+    }
+}
+// send the versions in (since, read_ts] in the order of the keys, at most batch_kvs kvs per batch.
+// each batch seeks to the last key sent, and skips the versions of it which are already sent.
+async fn send_history(
+    txn: Txn,
+    since: TxnTs,
+    trie: Trie,
+    batch_kvs: usize,
+    sender: Sender<anyhow::Result<Vec<Kv>>>,
+) {
+    let result = async {
+        let mut last_sent: Option<(Bytes, TxnTs)> = None;
+        loop {
+            let opt = IteratorOptions::default().set_all_versions(true);
+            let mut iter = txn.iter(opt).await?;
+            match last_sent.as_ref() {
+                Some((key, _)) => iter.seek(key.clone()).await?,
+                None => iter.rewind().await?,
+            }
+            let mut kvs = Vec::new();
+            while let Some(item) = iter.item() {
+                if kvs.len() >= batch_kvs {
+                    break;
+                }
+                let version = TxnTs::from(item.version());
+                let sent = matches!(
+                    &last_sent,
+                    Some((key, last)) if item.key() == key && version >= *last
+                );
+                if !sent && version > since && !trie.get(item.key()).is_empty() {
+                    kvs.push(item.to_kv().await?);
+                }
+                iter.next().await?;
+            }
+            let exhausted = !iter.valid();
+            drop(iter);
+
+            if let Some(kv) = kvs.last() {
+                last_sent = Some((Bytes::from(kv.key.clone()), kv.version.into()));
+                // the stream is dropped.
+                if sender.send(Ok(kvs)).await.is_err() {
+                    return Ok(());
+                }
+            }
+            if exhausted {
+                break;
+            }
+        }
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        let _ = sender.send(Err(e)).await;
+    }
+    if let Err(e) = txn.discard().await {
+        error!("Failed to discard the txn of the history: {}", e);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use crate::{
+        db::{
+            tests::{set, test_config},
+            DB,
+        },
+        pb::badgerpb4::Match,
+    };
+
+    fn keys_versions(kvs: &[crate::Kv]) -> Vec<(&[u8], u64)> {
+        kvs.iter()
+            .map(|kv| (kv.key.as_slice(), kv.version))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_changes_since() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let matches = vec![Match {
+            prefix: b"a".to_vec(),
+            ignore_bytes: String::new(),
+        }];
+        set(&db, "a1", "1").await?;
+        set(&db, "b1", "1").await?;
+        set(&db, "a2", "1").await?;
+        set(&db, "a1", "2").await?;
+        let txn = db.new_read_txn().await?;
+        let since = txn.get("a2").await?.version();
+        txn.discard().await?;
+
+        let mut changes = db
+            .changes_since((since - 1).into(), matches.clone())
+            .await?;
+        assert_eq!(changes.read_ts(), (since + 1).into());
+        set(&db, "a3", "1").await?;
+        // the history is in the order of the keys.
+        let kvs = changes.next().await.unwrap();
+        assert_eq!(
+            keys_versions(&kvs),
+            vec![(b"a1".as_slice(), since + 1), (b"a2".as_slice(), since)]
+        );
+        assert_eq!(kvs[0].value, b"2");
+        let kvs = changes.next().await.unwrap();
+        assert_eq!(keys_versions(&kvs), vec![(b"a3".as_slice(), since + 2)]);
+
+        // resume from the last version consumed above read_ts.
+        drop(changes);
+        let mut changes = db.changes_since((since + 1).into(), matches).await?;
+        set(&db, "a4", "1").await?;
+        let kvs = changes.next().await.unwrap();
+        assert_eq!(keys_versions(&kvs), vec![(b"a3".as_slice(), since + 2)]);
+        let kvs = changes.next().await.unwrap();
+        assert_eq!(keys_versions(&kvs), vec![(b"a4".as_slice(), since + 3)]);
+        db.close().await
+    }
+    #[tokio::test]
+    async fn test_changes_since_batches() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let matches = vec![Match {
+            prefix: b"a".to_vec(),
+            ignore_bytes: String::new(),
+        }];
+        for value in ["1", "2", "3"] {
+            for i in 0..5 {
+                set(&db, format!("a{i}"), value).await?;
+                set(&db, format!("b{i}"), value).await?;
+            }
+        }
+        let mut expected = Vec::new();
+        let txn = db.new_read_txn().await?;
+        for i in 0..5 {
+            let version = txn.get(format!("a{i}")).await?.version();
+            // the versions of a key are 10 commits apart, newest first.
+            for j in 0..3 {
+                expected.push((format!("a{i}").into_bytes(), version - j * 10));
+            }
+        }
+        txn.discard().await?;
+
+        // the batches end in the middle of the versions of a key.
+        let mut changes = db.changes_since_with(0.into(), matches, 4).await?;
+        let mut history = Vec::new();
+        while history.len() < expected.len() {
+            let kvs = changes.next().await.unwrap();
+            assert!(kvs.len() <= 4);
+            history.extend(kvs.into_iter().map(|kv| (kv.key, kv.version)));
+        }
+        assert_eq!(history, expected);
+        set(&db, "a0", "4").await?;
+        let kvs = changes.next().await.unwrap();
+        assert_eq!(
+            keys_versions(&kvs),
+            vec![(b"a0".as_slice(), expected[0].1 + 10)]
+        );
+        drop(changes);
+        db.close().await
+    }
+}
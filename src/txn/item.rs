@@ -1,17 +1,23 @@
-use std::{ops::Deref, sync::Arc};
+use std::{ops::Deref, sync::Arc, time::SystemTime};
 
-use crate::kv::{KeyTs, ValueMeta};
+use anyhow::bail;
+use bytes::Bytes;
+use tokio::sync::OnceCell;
+
+use crate::{
+    db::{WeakDB, DB},
+    errors::DBError,
+    kv::{KeyTs, Meta, PhyTs, ValueMeta},
+    pb::badgerpb4::Kv,
+};
 #[derive(Debug)]
+#[derive(Default)]
 pub(crate) enum PrefetchStatus {
     Prefetched,
+    #[default]
     NoPrefetched,
 }
-impl Default for PrefetchStatus {
-    fn default() -> Self {
-        Self::NoPrefetched
-    }
-}
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Item(Arc<ItemInner>);
 impl From<ItemInner> for Item {
     fn from(value: ItemInner) -> Self {
@@ -30,18 +36,201 @@ pub struct ItemInner {
     key_ts: KeyTs,
     value_meta: ValueMeta,
     status: PrefetchStatus,
+    // only used to read value from vlog when status is NoPrefetched,
+    // it's weak so a kept item doesn't keep the db open.
+    db: Option<WeakDB>,
+    vlog_value: OnceCell<Bytes>,
+    // the time of db when this item is read, to check the expiration.
+    now: PhyTs,
 }
 
 impl ItemInner {
-    pub(crate) fn set_key_ts(&mut self, key_ts: KeyTs) {
-        self.key_ts = key_ts;
+    pub(crate) fn new(key_ts: KeyTs, value_meta: ValueMeta, db: &DB) -> Self {
+        let mut item = Self {
+            key_ts,
+            value_meta,
+            now: db.opt.now(),
+            ..Default::default()
+        };
+        if item.value_meta.meta().contains(Meta::VALUE_POINTER) {
+            // read the value from vlog lazily, see Item::value
+            item.db = db.downgrade().into();
+        } else {
+            item.status = PrefetchStatus::Prefetched;
+        }
+        item
+    }
+
+    /// replace the ValuePointer with the value in vlog, meta is kept as it is.
+    pub(crate) async fn prefetch_value(&mut self) -> anyhow::Result<()> {
+        if let PrefetchStatus::NoPrefetched = self.status {
+            if let Some(db) = self.db.take() {
+                let Some(db) = db.upgrade() else {
+                    bail!(DBError::DBClosed);
+                };
+                let value = db.get_value(&self.value_meta).await?;
+                self.value_meta.set_value(value);
+            }
+            self.status = PrefetchStatus::Prefetched;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn value_meta(&self) -> &ValueMeta {
+        &self.value_meta
+    }
+
+    // the value pointer is an internal detail, so it is removed from meta.
+    pub(crate) async fn to_kv(&self) -> anyhow::Result<Kv> {
+        Ok(Kv {
+            key: self.key().to_vec(),
+            value: self.value_copy().await?,
+            user_meta: vec![self.user_meta()],
+            version: self.version(),
+            expires_at: self.value_meta.expires_at().to_u64(),
+            meta: vec![self.meta().difference(Meta::VALUE_POINTER).bits()],
+            stream_id: 0,
+            stream_done: false,
+        })
+    }
+}
+impl ItemInner {
+    /// Returns the key.
+    pub fn key(&self) -> &[u8] {
+        self.key_ts.key()
     }
 
-    pub(crate) fn set_value_meta(&mut self, value_meta: ValueMeta) {
-        self.value_meta = value_meta;
+    /// Returns the commit ts of this version.
+    pub fn version(&self) -> u64 {
+        self.key_ts.txn_ts().to_u64()
+    }
+
+    /// Returns the time at which this version expires, None means never.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        let expires_at = self.value_meta.expires_at();
+        if expires_at == PhyTs::default() {
+            return None;
+        }
+        Some(expires_at.into())
+    }
+
+    pub fn user_meta(&self) -> u8 {
+        self.value_meta.user_meta()
+    }
+
+    /// Returns the meta flags of this version, e.g. Meta::DELETE for a tombstone.
+    pub fn meta(&self) -> Meta {
+        self.value_meta.meta()
+    }
+
+    /// Checked at the time of db when this item is read.
+    pub fn is_deleted_or_expired(&self) -> bool {
+        self.value_meta.is_deleted_or_expired(self.now)
+    }
+
+    /// Returns the value, the value in vlog will be read at the first call and then be cached in this item.
+    pub async fn value(&self) -> anyhow::Result<&Bytes> {
+        match self.status {
+            PrefetchStatus::Prefetched => Ok(self.value_meta.value()),
+            PrefetchStatus::NoPrefetched => {
+                if !self.value_meta.meta().contains(Meta::VALUE_POINTER) {
+                    return Ok(self.value_meta.value());
+                }
+                self.vlog_value
+                    .get_or_try_init(|| async {
+                        match self.db.as_ref() {
+                            Some(db) => match db.upgrade() {
+                                Some(db) => db.get_value(&self.value_meta).await,
+                                None => bail!(DBError::DBClosed),
+                            },
+                            None => bail!("Cannot read value from vlog without db"),
+                        }
+                    })
+                    .await
+            }
+        }
+    }
+
+    /// Returns a copy of the value, which can outlive this item.
+    pub async fn value_copy(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.value().await?.to_vec())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        db::{
+            tests::{drop_and_wait, test_config},
+            DB,
+        },
+        errors::DBError,
+        kv::Meta,
+    };
+
+    #[tokio::test]
+    async fn test_read_value_pointer() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config).await?;
+
+        let value = Bytes::from(vec![7u8; 1 << 12]);
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("big"), value.clone()).await?;
+        txn.set(Bytes::from("small"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let txn = db.get_update_txn().await?;
+        let item = txn.get("big").await?;
+        assert!(item.value_meta().meta().contains(Meta::VALUE_POINTER));
+        assert_eq!(item.value().await?, &value);
+        assert_eq!(item.value_copy().await?, value.to_vec());
+        let item = txn.get("small").await?;
+        assert_eq!(item.key(), b"small");
+        assert_eq!(item.value().await?, &Bytes::from("v"));
+        assert!(!item.is_deleted_or_expired());
+        txn.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_read_value_pointer_after_drop() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("big"), Bytes::from(vec![7u8; 1 << 12])).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let txn = db.get_update_txn().await?;
+        let item = txn.get("big").await?;
+        txn.discard().await?;
+        // the item doesn't keep the db open.
+        drop_and_wait(db).await?;
+
+        let err = item.value().await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::DBClosed)));
+        Ok(())
     }
+    #[tokio::test]
+    async fn test_read_buffered_value_pointer() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config).await?;
 
-    pub(crate) fn set_status(&mut self, status: PrefetchStatus) {
-        self.status = status;
+        // smaller than the write buffer of the vlog file, read back before any later write.
+        let value = Bytes::from(vec![7u8; 2 << 10]);
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("medium"), value.clone()).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        let txn = db.get_update_txn().await?;
+        let item = txn.get("medium").await?;
+        assert!(item.value_meta().meta().contains(Meta::VALUE_POINTER));
+        assert_eq!(item.value().await?, &value);
+        txn.discard().await?;
+        Ok(())
     }
 }
@@ -0,0 +1,751 @@
+use std::{ops::Bound, sync::atomic::Ordering};
+
+use anyhow::bail;
+use bytes::Bytes;
+
+use crate::{
+    errors::DBError,
+    iter::{
+        DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekBackIter, KvSeekIter, KvSinkIter,
+        SinkIterator,
+    },
+    kv::{KeyTs, KeyTsBorrow, Meta, TxnTs, ValueMeta},
+    table::{
+        iter::{SinkMergeIter, SinkMergeNodeIter, SinkTableConcatIter},
+        Table,
+    },
+    util::skip_list::SkipList,
+};
+
+use super::{
+    item::{Item, ItemInner},
+    merge::resolve_versions,
+    KeyRange, Txn, BADGER_PREFIX,
+};
+#[derive(Debug, Clone, Default)]
+pub struct IteratorOptions {
+    // Read the values stored in vlog while iterating, instead of reading them in Item::value.
+    prefetch_values: bool,
+    // Iterate in descending order of keys.
+    reverse: bool,
+    // Only iterate over the keys with this prefix.
+    prefix: Bytes,
+    // Return every version <= read_ts of each key, including the deleted and expired ones.
+    all_versions: bool,
+    // Also return the internal keys with BADGER_PREFIX.
+    internal_access: bool,
+}
+impl IteratorOptions {
+    pub fn set_prefetch_values(mut self, prefetch_values: bool) -> Self {
+        self.prefetch_values = prefetch_values;
+        self
+    }
+
+    pub fn set_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn set_prefix<B: Into<Bytes>>(mut self, prefix: B) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn set_all_versions(mut self, all_versions: bool) -> Self {
+        self.all_versions = all_versions;
+        self
+    }
+
+    pub(crate) fn set_internal_access(mut self, internal_access: bool) -> Self {
+        self.internal_access = internal_access;
+        self
+    }
+
+    pub fn prefetch_values(&self) -> bool {
+        self.prefetch_values
+    }
+
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn all_versions(&self) -> bool {
+        self.all_versions
+    }
+}
+/// Iterate over the snapshot of db at the read_ts of txn,
+/// only the newest version (<= read_ts) of each key is visible unless all_versions is set.
+pub struct TxnIter<'a> {
+    txn: &'a Txn,
+    opt: IteratorOptions,
+    skip_lists: Vec<SkipList>,
+    level0_tables: Vec<Table>,
+    level_tables: Vec<Vec<Table>>,
+    inner: Option<SinkMergeIter>,
+    inner_valid: bool,
+    item: Option<Item>,
+    // where the current scan begins, None if the reads are not tracked.
+    read_from: Option<Bound<Bytes>>,
+    read_range: Option<KeyRange>,
+}
+impl Txn {
+    /// Returns a TxnIter, you should call rewind or seek before reading any item.
+    pub async fn iter(&self, opt: IteratorOptions) -> anyhow::Result<TxnIter<'_>> {
+        if self.discarded() {
+            bail!(DBError::DiscardedTxn);
+        }
+        if self.db().is_closed() {
+            bail!(DBError::DBClosed);
+        }
+        let (mut_memtable, immut_memtables) = self.db().get_memtable().await;
+        let mut skip_lists = Vec::with_capacity(immut_memtables.len() + 1);
+        if let Some(memtable) = mut_memtable {
+            let memtable_r = memtable.read().await;
+            skip_lists.push(memtable_r.skip_list.clone());
+            drop(memtable_r);
+        }
+        skip_lists.extend(immut_memtables.iter().map(|x| x.skip_list.clone()));
+
+        let mut level0_tables = Vec::new();
+        let mut level_tables = Vec::new();
+        for (index, handler) in self.db().level_controller.levels().iter().enumerate() {
+            let handler_r = handler.read().await;
+            if index == 0 {
+                // newer table has bigger id
+                level0_tables = handler_r.tables.iter().rev().cloned().collect();
+            } else if !handler_r.tables.is_empty() {
+                level_tables.push(handler_r.tables.clone());
+            }
+            drop(handler_r);
+        }
+
+        self.num_iters().fetch_add(1, Ordering::AcqRel);
+        Ok(TxnIter {
+            txn: self,
+            opt,
+            skip_lists,
+            level0_tables,
+            level_tables,
+            inner: None,
+            inner_valid: false,
+            item: None,
+            read_from: None,
+            read_range: None,
+        })
+    }
+}
+impl TxnIter<'_> {
+    fn new_merge_iter(&self) -> Option<SinkMergeIter> {
+        let mut iters: Vec<SinkMergeNodeIter> = Vec::with_capacity(
+            self.skip_lists.len() + self.level0_tables.len() + self.level_tables.len(),
+        );
+        for skip_list in self.skip_lists.iter() {
+            iters.push(skip_list.owned_iter().into());
+        }
+        for table in self.level0_tables.iter() {
+            iters.push(table.iter(true).into());
+        }
+        for tables in self.level_tables.iter() {
+            iters.push(SinkTableConcatIter::new(tables.clone(), true).into());
+        }
+        SinkMergeIter::new(iters)
+    }
+
+    /// Move to the first key (the last key if reverse).
+    pub async fn rewind(&mut self) -> anyhow::Result<()> {
+        let from = if !self.opt.reverse {
+            match self.opt.prefix.is_empty() {
+                true => Bound::Unbounded,
+                false => Bound::Included(self.opt.prefix.clone()),
+            }
+        } else {
+            match prefix_successor(&self.opt.prefix) {
+                Some(end) => Bound::Excluded(end),
+                None => Bound::Unbounded,
+            }
+        };
+        self.begin_read_range(from);
+        self.inner = self.new_merge_iter();
+        self.inner_valid = match self.inner.as_mut() {
+            Some(inner) => {
+                if !self.opt.reverse {
+                    if self.opt.prefix.is_empty() {
+                        inner.next()?
+                    } else {
+                        let k = KeyTs::new(self.opt.prefix.clone(), TxnTs::from(u64::MAX));
+                        inner.seek(k.serialize().as_slice().into())?
+                    }
+                } else {
+                    match prefix_successor(&self.opt.prefix) {
+                        Some(end) => {
+                            let k = KeyTs::new(end, TxnTs::from(u64::MAX));
+                            inner.seek_back(k.serialize().as_slice().into())?
+                        }
+                        None => inner.next_back()?,
+                    }
+                }
+            }
+            None => false,
+        };
+        self.parse().await
+    }
+
+    /// Move to the smallest key >= key (the biggest key <= key if reverse).
+    pub async fn seek<B: Into<Bytes>>(&mut self, key: B) -> anyhow::Result<()> {
+        let key: Bytes = key.into();
+        if key.is_empty() {
+            return self.rewind().await;
+        }
+        if self.opt.reverse {
+            // the keys beyond the prefix are clamped to the end of it, as rewind does.
+            if let Some(end) = prefix_successor(&self.opt.prefix) {
+                if key >= end {
+                    return self.rewind().await;
+                }
+            }
+        }
+        let key = if !self.opt.reverse && key < self.opt.prefix {
+            self.opt.prefix.clone()
+        } else {
+            key
+        };
+        self.begin_read_range(Bound::Included(key.clone()));
+        self.inner = self.new_merge_iter();
+        self.inner_valid = match self.inner.as_mut() {
+            Some(inner) => {
+                if !self.opt.reverse {
+                    let k = KeyTs::new(key, self.txn.read_ts);
+                    inner.seek(k.serialize().as_slice().into())?
+                } else {
+                    // ts is in descending order, so the 0 is the last version of key.
+                    let k = KeyTs::new(key, TxnTs::default());
+                    inner.seek_back(k.serialize().as_slice().into())?
+                }
+            }
+            None => false,
+        };
+        self.parse().await
+    }
+
+    pub async fn next(&mut self) -> anyhow::Result<()> {
+        self.parse().await
+    }
+
+    pub fn valid(&self) -> bool {
+        self.item.is_some()
+    }
+
+    pub fn item(&self) -> Option<&Item> {
+        self.item.as_ref()
+    }
+
+    async fn parse(&mut self) -> anyhow::Result<()> {
+        self.parse_item().await?;
+        self.update_read_range();
+        Ok(())
+    }
+
+    fn begin_read_range(&mut self, from: Bound<Bytes>) {
+        self.flush_read_range();
+        if self.txn.track_read() {
+            self.read_from = Some(from);
+        }
+    }
+
+    // the range from the seek position to the current item, or to the end of the prefix if exhausted.
+    fn update_read_range(&mut self) {
+        let from = match self.read_from.as_ref() {
+            Some(from) => from.clone(),
+            None => return,
+        };
+        let to = match (self.item.as_ref(), self.opt.reverse) {
+            (Some(item), _) => Bound::Included(Bytes::copy_from_slice(item.key())),
+            (None, false) => match prefix_successor(&self.opt.prefix) {
+                Some(end) => Bound::Excluded(end),
+                None => Bound::Unbounded,
+            },
+            (None, true) => match self.opt.prefix.is_empty() {
+                true => Bound::Unbounded,
+                false => Bound::Included(self.opt.prefix.clone()),
+            },
+        };
+        self.read_range = if self.opt.reverse {
+            Some((to, from))
+        } else {
+            Some((from, to))
+        };
+    }
+
+    fn flush_read_range(&mut self) {
+        self.read_from = None;
+        if let Some(range) = self.read_range.take() {
+            self.txn.read_ranges().lock().push(range);
+        }
+    }
+
+    // find the next visible key, and move inner to the entry after all versions of this key.
+    async fn parse_item(&mut self) -> anyhow::Result<()> {
+        self.item = None;
+        let inner = match self.inner.as_mut() {
+            Some(inner) => inner,
+            None => return Ok(()),
+        };
+        let reverse = self.opt.reverse;
+        let read_ts = self.txn.read_ts;
+        let now = self.txn.db().opt.now();
+        while self.inner_valid {
+            let key = match current(inner, reverse) {
+                Some((key_ts, _)) => key_ts.key().clone(),
+                None => break,
+            };
+            if !key.starts_with(&self.opt.prefix) {
+                break;
+            }
+            // versions are in descending order, or ascending order if reverse.
+            // only keep the newest version and the merge operands above the first one which isn't.
+            let mut versions: Vec<(KeyTs, ValueMeta)> = Vec::new();
+            while let Some((key_ts, value_meta)) = current(inner, reverse) {
+                if key_ts.key() != &key {
+                    break;
+                }
+                let found = key_ts.txn_ts() <= read_ts;
+                if found {
+                    if reverse {
+                        if !value_meta.meta().contains(Meta::MERGE_ENTRY) {
+                            versions.clear();
+                        }
+                        versions.push((key_ts, value_meta));
+                    } else if !matches!(
+                        versions.last(),
+                        Some((_, v)) if !v.meta().contains(Meta::MERGE_ENTRY)
+                    ) {
+                        versions.push((key_ts, value_meta));
+                    }
+                }
+                self.inner_valid = if reverse {
+                    inner.next_back()?
+                } else {
+                    inner.next()?
+                };
+                if !self.inner_valid || (found && self.opt.all_versions) {
+                    break;
+                }
+            }
+            if key.starts_with(BADGER_PREFIX) && !self.opt.internal_access {
+                continue;
+            }
+            if self.txn.db().is_banned(&key).await.is_err() {
+                continue;
+            }
+            if reverse {
+                versions.reverse();
+            }
+            let visible = if self.opt.all_versions {
+                versions.pop()
+            } else {
+                resolve_versions(self.txn.db(), versions, now).await?
+            };
+            if let Some((key_ts, value_meta)) = visible {
+                let mut item = ItemInner::new(key_ts, value_meta, self.txn.db());
+                if self.opt.prefetch_values {
+                    item.prefetch_value().await?;
+                }
+                self.item = Some(item.into());
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+impl Drop for TxnIter<'_> {
+    fn drop(&mut self) {
+        self.flush_read_range();
+        self.txn.num_iters().fetch_sub(1, Ordering::AcqRel);
+    }
+}
+fn current(inner: &SinkMergeIter, reverse: bool) -> Option<(KeyTs, ValueMeta)> {
+    let (key, value): (Option<KeyTsBorrow<'_>>, _) = if reverse {
+        (inner.key_back(), inner.value_back())
+    } else {
+        (inner.key(), inner.value())
+    };
+    match (key, value) {
+        (Some(key), Some(value)) => Some((key.into(), value)),
+        _ => None,
+    }
+}
+// the smallest key which is bigger than all keys with this prefix.
+fn prefix_successor(prefix: &[u8]) -> Option<Bytes> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last != u8::MAX {
+            end.push(last + 1);
+            return Some(end.into());
+        }
+    }
+    None
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::Ordering, Arc},
+        time::{Duration, SystemTime},
+    };
+
+    use bytes::Bytes;
+
+    use crate::{
+        config::ManualClock,
+        db::{tests::test_config, DB},
+        errors::DBError,
+        kv::{Entry, Meta},
+    };
+
+    use super::{IteratorOptions, TxnIter};
+
+    async fn open_db(dir: &std::path::Path) -> anyhow::Result<DB> {
+        DB::open(test_config(dir)).await
+    }
+    async fn collect(iter: &mut TxnIter<'_>) -> anyhow::Result<Vec<(Bytes, Bytes)>> {
+        let mut result = Vec::new();
+        while let Some(item) = iter.item() {
+            result.push((
+                Bytes::copy_from_slice(item.key()),
+                item.value().await?.clone(),
+            ));
+            iter.next().await?;
+        }
+        Ok(result)
+    }
+    fn kv(k: &str, v: &str) -> (Bytes, Bytes) {
+        (Bytes::from(k.to_string()), Bytes::from(v.to_string()))
+    }
+    #[tokio::test]
+    async fn test_txn_iter() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open_db(dir.path()).await?;
+
+        let mut txn = db.get_update_txn().await?;
+        for (k, v) in [
+            ("a1", "1"),
+            ("a2", "2"),
+            ("b1", "3"),
+            ("b2", "4"),
+            ("c1", "5"),
+        ] {
+            txn.set(Bytes::from(k), Bytes::from(v)).await?;
+        }
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let snapshot = db.get_update_txn().await?;
+
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("a2"), Bytes::from("22")).await?;
+        txn.delete(Bytes::from("b1")).await?;
+        let big = Bytes::from(vec![7u8; 1 << 12]);
+        txn.set(Bytes::from("c2"), big.clone()).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let txn = db.get_update_txn().await?;
+        let mut iter = txn.iter(IteratorOptions::default()).await?;
+        assert_eq!(txn.num_iters().load(Ordering::Acquire), 1);
+        iter.rewind().await?;
+        assert_eq!(
+            collect(&mut iter).await?,
+            vec![
+                kv("a1", "1"),
+                kv("a2", "22"),
+                kv("b2", "4"),
+                kv("c1", "5"),
+                (Bytes::from("c2"), big.clone())
+            ]
+        );
+        drop(iter);
+        assert_eq!(txn.num_iters().load(Ordering::Acquire), 0);
+
+        let opt = IteratorOptions::default()
+            .set_reverse(true)
+            .set_prefetch_values(true);
+        let mut iter = txn.iter(opt).await?;
+        iter.rewind().await?;
+        let keys = collect(&mut iter)
+            .await?
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["c2", "c1", "b2", "a2", "a1"]);
+        drop(iter);
+
+        let mut iter = txn.iter(IteratorOptions::default().set_prefix("b")).await?;
+        iter.rewind().await?;
+        assert_eq!(collect(&mut iter).await?, vec![kv("b2", "4")]);
+        drop(iter);
+
+        let opt = IteratorOptions::default().set_prefix("a").set_reverse(true);
+        let mut iter = txn.iter(opt).await?;
+        iter.rewind().await?;
+        assert_eq!(
+            collect(&mut iter).await?,
+            vec![kv("a2", "22"), kv("a1", "1")]
+        );
+        drop(iter);
+
+        let mut iter = txn.iter(IteratorOptions::default()).await?;
+        iter.seek("b").await?;
+        assert_eq!(iter.item().map(|x| x.key()), Some(b"b2".as_ref()));
+        drop(iter);
+
+        let mut iter = txn
+            .iter(IteratorOptions::default().set_reverse(true))
+            .await?;
+        iter.seek("b3").await?;
+        assert_eq!(iter.item().map(|x| x.key()), Some(b"b2".as_ref()));
+        drop(iter);
+        txn.discard().await?;
+
+        // the older snapshot should not see the newer versions.
+        let mut iter = snapshot.iter(IteratorOptions::default()).await?;
+        iter.rewind().await?;
+        assert_eq!(
+            collect(&mut iter).await?,
+            vec![
+                kv("a1", "1"),
+                kv("a2", "2"),
+                kv("b1", "3"),
+                kv("b2", "4"),
+                kv("c1", "5")
+            ]
+        );
+        drop(iter);
+        snapshot.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_txn_iter_all_versions() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open_db(dir.path()).await?;
+
+        let mut versions = Vec::new();
+        for op in [Some("1"), Some("2"), None, Some("3")] {
+            let mut txn = db.get_update_txn().await?;
+            match op {
+                Some(v) => txn.set(Bytes::from("a"), Bytes::from(v)).await?,
+                None => txn.delete(Bytes::from("a")).await?,
+            }
+            txn.set(Bytes::from("b"), Bytes::from("b")).await?;
+            txn.commit().await?;
+            txn.discard().await?;
+            versions.push(db.get_update_txn().await?);
+        }
+        // read_ts is after the delete, so the last set is not visible.
+        let txn = versions.remove(2);
+
+        let opt = IteratorOptions::default().set_all_versions(true);
+        let mut iter = txn.iter(opt.clone()).await?;
+        iter.rewind().await?;
+        let mut result = Vec::new();
+        while let Some(item) = iter.item() {
+            result.push((
+                Bytes::copy_from_slice(item.key()),
+                item.value().await?.clone(),
+                item.meta().contains(Meta::DELETE),
+                item.version(),
+            ));
+            iter.next().await?;
+        }
+        let a = |v: &str, deleted, version| {
+            (
+                Bytes::from("a"),
+                Bytes::from(v.to_string()),
+                deleted,
+                version,
+            )
+        };
+        let b = |version| (Bytes::from("b"), Bytes::from("b"), false, version);
+        assert_eq!(
+            result,
+            vec![
+                a("", true, 3),
+                a("2", false, 2),
+                a("1", false, 1),
+                b(3),
+                b(2),
+                b(1)
+            ]
+        );
+        drop(iter);
+
+        let mut iter = txn.iter(opt.set_reverse(true)).await?;
+        iter.rewind().await?;
+        let mut result = Vec::new();
+        while let Some(item) = iter.item() {
+            result.push((Bytes::copy_from_slice(item.key()), item.version()));
+            iter.next().await?;
+        }
+        assert_eq!(
+            result,
+            vec![
+                (Bytes::from("b"), 1),
+                (Bytes::from("b"), 2),
+                (Bytes::from("b"), 3),
+                (Bytes::from("a"), 1),
+                (Bytes::from("a"), 2),
+                (Bytes::from("a"), 3)
+            ]
+        );
+        drop(iter);
+        txn.discard().await?;
+        for txn in versions {
+            txn.discard().await?;
+        }
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_txn_iter_conflict() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open_db(dir.path()).await?;
+
+        let mut txn = db.get_update_txn().await?;
+        for k in ["a1", "a3", "b1"] {
+            txn.set(Bytes::from(k), Bytes::from("v")).await?;
+        }
+        txn.commit().await?;
+        txn.discard().await?;
+
+        // scan the whole prefix "a"
+        let mut scan_all = db.get_update_txn().await?;
+        let mut iter = scan_all
+            .iter(IteratorOptions::default().set_prefix("a"))
+            .await?;
+        iter.rewind().await?;
+        assert_eq!(collect(&mut iter).await?.len(), 2);
+        drop(iter);
+
+        // only read "a1" and stop.
+        let mut scan_part = db.get_update_txn().await?;
+        let mut iter = scan_part.iter(IteratorOptions::default()).await?;
+        iter.rewind().await?;
+        assert_eq!(iter.item().map(|x| x.key()), Some(b"a1".as_ref()));
+        drop(iter);
+
+        // scan the prefix "b" in reverse.
+        let mut scan_b = db.get_update_txn().await?;
+        let opt = IteratorOptions::default().set_reverse(true).set_prefix("b");
+        let mut iter = scan_b.iter(opt).await?;
+        iter.rewind().await?;
+        assert_eq!(iter.item().map(|x| x.key()), Some(b"b1".as_ref()));
+        drop(iter);
+
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("a2"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        scan_all.set(Bytes::from("x"), Bytes::from("v")).await?;
+        let err = scan_all.commit().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DBError::Conflict { key, .. }) if key == "a2"
+        ));
+        scan_all.discard().await?;
+
+        scan_part.set(Bytes::from("y"), Bytes::from("v")).await?;
+        scan_part.commit().await?;
+        scan_part.discard().await?;
+
+        scan_b.set(Bytes::from("z"), Bytes::from("v")).await?;
+        scan_b.commit().await?;
+        scan_b.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_txn_iter_reverse_prefix_seek() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = open_db(dir.path()).await?;
+
+        let mut txn = db.get_update_txn().await?;
+        for k in ["a1", "b1"] {
+            txn.set(Bytes::from(k), Bytes::from("v")).await?;
+        }
+        txn.commit().await?;
+        txn.discard().await?;
+
+        // the key beyond the prefix is clamped to the end of the prefix.
+        let mut scan = db.get_update_txn().await?;
+        let opt = IteratorOptions::default().set_prefix("a").set_reverse(true);
+        let mut iter = scan.iter(opt).await?;
+        iter.seek("c").await?;
+        assert_eq!(iter.item().map(|x| x.key()), Some(b"a1".as_ref()));
+        iter.next().await?;
+        assert!(!iter.valid());
+        drop(iter);
+
+        // so the read range doesn't cover the keys between the prefix and the seek key.
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("b0"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        scan.set(Bytes::from("x"), Bytes::from("v")).await?;
+        scan.commit().await?;
+        scan.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_ttl() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let clock = Arc::new(ManualClock::new(start));
+        let config = test_config(dir.path()).set_clock(clock.clone());
+        let db = DB::open(config).await?;
+
+        let mut txn = db.get_update_txn().await?;
+        txn.set_with_ttl(
+            Bytes::from("long"),
+            Bytes::from("v"),
+            Duration::from_secs(10),
+        )
+        .await?;
+        let entry = Entry::new(Bytes::from("short"), Bytes::from("v"))
+            .with_ttl(Duration::from_secs(5))
+            .with_meta(7);
+        txn.set_entry(entry).await?;
+        txn.set(Bytes::from("forever"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let txn = db.get_update_txn().await?;
+        let item = txn.get("long").await?;
+        assert_eq!(item.expires_at(), Some(start + Duration::from_secs(10)));
+        let item = txn.get("short").await?;
+        assert_eq!(item.user_meta(), 7);
+        assert_eq!(txn.get("forever").await?.expires_at(), None);
+
+        clock.advance(Duration::from_secs(6));
+        // the item is checked at the time it was read.
+        assert!(!item.is_deleted_or_expired());
+        let err = txn.get("short").await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        let mut iter = txn.iter(IteratorOptions::default()).await?;
+        iter.rewind().await?;
+        let mut keys = Vec::new();
+        while let Some(item) = iter.item() {
+            keys.push(item.key().to_vec());
+            iter.next().await?;
+        }
+        assert_eq!(keys, vec![b"forever".to_vec(), b"long".to_vec()]);
+        drop(iter);
+
+        clock.advance(Duration::from_secs(5));
+        let err = txn.get("long").await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        txn.discard().await?;
+        Ok(())
+    }
+}
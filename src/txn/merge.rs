@@ -0,0 +1,287 @@
+use std::fmt::Debug;
+
+use anyhow::bail;
+use bytes::Bytes;
+
+use crate::{
+    db::DB,
+    kv::{Entry, KeyTs, Meta, PhyTs, TxnTs, ValueMeta},
+};
+
+use super::Txn;
+/// Combines the operands written by Txn::merge with the older value of the key.
+/// The operands may be merged without the existing value during compaction,
+/// so the operator must be associative, e.g. counters or append-only lists.
+pub trait MergeOperator: Debug + Send + Sync {
+    /// operands are in the order they were committed, existing is None if the key doesn't exist.
+    fn merge(&self, key: &[u8], existing: Option<&[u8]>, operands: &[Bytes]) -> Bytes;
+}
+impl Txn {
+    /// Write an operand, which will be folded into the value of key by the MergeOperator of db.
+    /// The operands may be folded together before the older value is known, in this txn or
+    /// during compaction, so the operator must be associative.
+    pub async fn merge<B: Into<Bytes>>(&mut self, key: B, operand: B) -> anyhow::Result<()> {
+        let operator = match self.db().opt.merge_operator() {
+            Some(operator) => operator.clone(),
+            None => bail!("Merge operator is not set"),
+        };
+        let key: Bytes = key.into();
+        let operand: Bytes = operand.into();
+        let e = match self.pending_writes().and_then(|p| p.get(&key)) {
+            // fold with the pending write in this txn, otherwise it will be replaced.
+            Some(old) if old.meta().contains(Meta::MERGE_ENTRY) => {
+                let value = operator.merge(&key, None, &[old.value().clone(), operand]);
+                let mut e = Entry::new(key, value);
+                e.set_meta(Meta::MERGE_ENTRY);
+                e
+            }
+            Some(old) => {
+                let existing = (!old.is_deleted()).then_some(old.value().as_ref());
+                let value = operator.merge(&key, existing, &[operand]);
+                Entry::new(key, value)
+            }
+            None => {
+                let mut e = Entry::new(key, operand);
+                e.set_meta(Meta::MERGE_ENTRY);
+                e
+            }
+        };
+        self.set_entry(e).await
+    }
+
+    // all versions <= txn_ts of key until the first version which is not a merge operand, newest first.
+    // the versions are read from db directly, so only the key read by Txn::get is tracked.
+    pub(super) async fn merge_versions(
+        &self,
+        key: &Bytes,
+        mut txn_ts: TxnTs,
+        mut value_meta: ValueMeta,
+    ) -> anyhow::Result<Vec<(KeyTs, ValueMeta)>> {
+        let mut versions = Vec::new();
+        loop {
+            let is_operand = value_meta.meta().contains(Meta::MERGE_ENTRY);
+            versions.push((KeyTs::new(key.clone(), txn_ts), value_meta));
+            if !is_operand || txn_ts.to_u64() == 0 {
+                break;
+            }
+            let seek = KeyTs::new(key.clone(), txn_ts - 1);
+            match self.db().get(&seek).await? {
+                Some((ts, v)) if !v.value().is_empty() || !v.meta().is_empty() => {
+                    txn_ts = ts;
+                    value_meta = v;
+                }
+                _ => break,
+            }
+        }
+        Ok(versions)
+    }
+}
+/// Resolve the visible value from the versions (newest first) of one key,
+/// returns None if the newest version is deleted or expired.
+pub(crate) async fn resolve_versions(
+    db: &DB,
+    mut versions: Vec<(KeyTs, ValueMeta)>,
+    now: PhyTs,
+) -> anyhow::Result<Option<(KeyTs, ValueMeta)>> {
+    let newest = match versions.first() {
+        Some((_, value_meta)) => value_meta,
+        None => return Ok(None),
+    };
+    if newest.is_deleted_or_expired(now) {
+        return Ok(None);
+    }
+    if !newest.meta().contains(Meta::MERGE_ENTRY) {
+        return Ok(Some(versions.swap_remove(0)));
+    }
+    let operator = match db.opt.merge_operator() {
+        Some(operator) => operator,
+        None => bail!("Merge operator is not set"),
+    };
+    let mut operands = Vec::new();
+    let mut existing = None;
+    for (_, value_meta) in versions.iter() {
+        let is_operand = value_meta.meta().contains(Meta::MERGE_ENTRY);
+        if !value_meta.is_deleted_or_expired(now) {
+            let value = db.get_value(value_meta).await?;
+            match is_operand {
+                true => operands.push(value),
+                false => existing = Some(value),
+            }
+        }
+        if !is_operand {
+            break;
+        }
+    }
+    operands.reverse();
+    let (key_ts, mut value_meta) = versions.swap_remove(0);
+    value_meta.set_value(operator.merge(key_ts.key(), existing.as_deref(), &operands));
+    value_meta
+        .meta_mut()
+        .remove(Meta::MERGE_ENTRY | Meta::VALUE_POINTER);
+    Ok(Some((key_ts, value_meta)))
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        txn::IteratorOptions,
+    };
+
+    use super::MergeOperator;
+
+    #[derive(Debug)]
+    struct Append;
+    impl MergeOperator for Append {
+        fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Bytes]) -> Bytes {
+            let mut value = existing.map(|e| e.to_vec()).unwrap_or_default();
+            for operand in operands {
+                value.extend_from_slice(operand);
+            }
+            value.into()
+        }
+    }
+    #[tokio::test]
+    async fn test_merge() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path()).set_merge_operator(Arc::new(Append));
+        let db = DB::open(config).await?;
+
+        let big = Bytes::from(vec![b'z'; 1 << 12]);
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("k"), Bytes::from("a")).await?;
+        txn.merge(Bytes::from("z"), big.clone()).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        for (k, operand) in [
+            ("k", Bytes::from("b")),
+            ("k", "c".into()),
+            ("z", big.clone()),
+        ] {
+            let mut txn = db.get_update_txn().await?;
+            txn.merge(Bytes::from(k), operand).await?;
+            txn.commit().await?;
+            txn.discard().await?;
+        }
+
+        let mut txn = db.get_update_txn().await?;
+        assert_eq!(txn.get("k").await?.value().await?, &Bytes::from("abc"));
+        assert_eq!(txn.get("z").await?.value().await?.len(), 2 << 12);
+        // merge operands in the same txn.
+        txn.merge(Bytes::from("x"), Bytes::from("1")).await?;
+        txn.merge(Bytes::from("x"), Bytes::from("2")).await?;
+        assert_eq!(txn.get("x").await?.value().await?, &Bytes::from("12"));
+        // merge with a pending set.
+        txn.set(Bytes::from("y"), Bytes::from("s")).await?;
+        txn.merge(Bytes::from("y"), Bytes::from("t")).await?;
+        // merge with the committed value.
+        txn.merge(Bytes::from("k"), Bytes::from("d")).await?;
+        assert_eq!(txn.get("k").await?.value().await?, &Bytes::from("abcd"));
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let mut txn = db.get_update_txn().await?;
+        txn.delete(Bytes::from("y")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        let mut txn = db.get_update_txn().await?;
+        txn.merge(Bytes::from("y"), Bytes::from("u")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let txn = db.get_update_txn().await?;
+        let mut iter = txn.iter(IteratorOptions::default()).await?;
+        iter.rewind().await?;
+        let mut result = Vec::new();
+        while let Some(item) = iter.item() {
+            if item.key() != b"z" {
+                result.push((item.key().to_vec(), item.value_copy().await?));
+            }
+            iter.next().await?;
+        }
+        assert_eq!(
+            result,
+            vec![
+                (b"k".to_vec(), b"abcd".to_vec()),
+                (b"x".to_vec(), b"12".to_vec()),
+                (b"y".to_vec(), b"u".to_vec())
+            ]
+        );
+        drop(iter);
+        txn.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_merge_read_conflict() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path()).set_merge_operator(Arc::new(Append));
+        let db = DB::open(config).await?;
+        let mut txn = db.get_update_txn().await?;
+        // only the merge operands are written to k.
+        txn.merge(Bytes::from("k"), Bytes::from("a")).await?;
+        txn.set(Bytes::from("l"), Bytes::from("a")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        let mut txn = db.get_update_txn().await?;
+        txn.merge(Bytes::from("k"), Bytes::from("b")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let mut reader = db.get_update_txn().await?;
+        assert_eq!(reader.get("k").await?.value().await?, &Bytes::from("ab"));
+
+        // the neighbouring key is not read by the merge.
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("l"), Bytes::from("b")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        reader.set(Bytes::from("x"), Bytes::from("v")).await?;
+        reader.commit().await?;
+        reader.discard().await?;
+
+        let mut reader = db.get_update_txn().await?;
+        assert_eq!(reader.get("k").await?.value().await?, &Bytes::from("ab"));
+        let mut txn = db.get_update_txn().await?;
+        txn.merge(Bytes::from("k"), Bytes::from("c")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        reader.set(Bytes::from("x"), Bytes::from("v")).await?;
+        assert!(reader.commit().await.is_err());
+        reader.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_merge_iter_versions() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path()).set_merge_operator(Arc::new(Append));
+        let db = DB::open(config).await?;
+        for (set, value) in [(true, "x"), (false, "y"), (true, "a"), (false, "b")] {
+            let mut txn = db.get_update_txn().await?;
+            match set {
+                true => txn.set(Bytes::from("k"), Bytes::from(value)).await?,
+                false => txn.merge(Bytes::from("k"), Bytes::from(value)).await?,
+            }
+            txn.commit().await?;
+            txn.discard().await?;
+        }
+
+        // the versions below the newest set are not merged in both directions.
+        let txn = db.get_update_txn().await?;
+        for reverse in [false, true] {
+            let mut iter = txn
+                .iter(IteratorOptions::default().set_reverse(reverse))
+                .await?;
+            iter.rewind().await?;
+            let item = iter.item().unwrap();
+            assert_eq!(item.key(), b"k");
+            assert_eq!(item.value_copy().await?, b"ab");
+            iter.next().await?;
+            assert!(!iter.valid());
+        }
+        txn.discard().await?;
+        Ok(())
+    }
+}
@@ -1,10 +1,19 @@
+mod backup;
+mod banned;
+mod batch;
+mod changes;
 mod item;
+mod iter;
+mod merge;
 pub(crate) mod oracle;
+mod retry;
+mod sequence;
+mod stream;
+mod stream_writer;
 mod water_mark;
 
-use std::future::Future;
-use std::pin::Pin;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 
 use ahash::RandomState;
 use anyhow::bail;
@@ -15,10 +24,20 @@ use crate::kv::Entry;
 use crate::kv::Meta;
 use crate::{db::DB, errors::DBError, kv::KeyTs};
 
-use self::item::PrefetchStatus;
-use self::item::{Item, ItemInner};
+pub use self::batch::WriteBatch;
+pub use self::changes::Changes;
+use self::item::ItemInner;
+pub use self::item::Item;
+pub use self::iter::{IteratorOptions, TxnIter};
+use self::merge::resolve_versions;
+pub use self::merge::MergeOperator;
+pub use self::retry::{RetryPolicy, RetryStats};
+pub use self::sequence::Sequence;
+pub use self::stream::Stream;
+pub use self::stream_writer::StreamWriter;
 use std::{
     collections::{HashMap, HashSet},
+    ops::Bound,
     sync::atomic::{AtomicBool, AtomicI32},
 };
 
@@ -28,20 +47,30 @@ use tokio::sync::oneshot::Receiver;
 use crate::kv::TxnTs;
 
 /// Prefix for internal keys used by badger.
-const BADGER_PREFIX: &[u8] = b"!badger!";
+pub(crate) const BADGER_PREFIX: &[u8] = b"!badger!";
 /// For indicating end of entries in txn.
 const TXN_KEY: &[u8] = b"!badger!txn";
 /// For storing the banned namespaces.
-const BANNED_NAMESPACES_KEY: &[u8] = b"!badger!banned";
+pub(crate) const BANNED_NAMESPACES_KEY: &[u8] = b"!badger!banned";
+/// Prefix for the keys of sequences.
+const SEQUENCE_PREFIX: &[u8] = b"!badger!seq!";
+
+/// Range of keys read by an iterator, used to detect the conflicts of scans.
+pub(crate) type KeyRange = (Bound<Bytes>, Bound<Bytes>);
 
 lazy_static! {
     pub(crate) static ref HASH: RandomState = ahash::RandomState::with_seed(thread_rng().gen());
 }
 
 //Transaction
+#[allow(async_fn_in_trait)]
 pub trait TxnUpdate {
     async fn update(self, txn: &mut Txn) -> anyhow::Result<()>;
 }
+#[allow(async_fn_in_trait)]
+pub trait TxnView {
+    async fn view(self, txn: &Txn) -> anyhow::Result<()>;
+}
 impl DB {
     pub async fn update<F: TxnUpdate>(&self, f: F) -> anyhow::Result<()> {
         #[inline]
@@ -73,29 +102,79 @@ impl DB {
         let txn = Txn::new(self.clone(), true, false).await?;
         Ok(txn)
     }
+
+    /// Only for managed_txns=true, read_ts and commit_ts are managed by the caller.
+    pub async fn new_txn_at(&self, read_ts: u64, update: bool) -> anyhow::Result<Txn> {
+        if self.is_closed() {
+            bail!(DBError::DBClosed);
+        }
+        if !self.oracle.config().managed_txns {
+            panic!("Cannot use new_txn_at with managed_txns=false. Use get_update_txn instead");
+        }
+        let mut txn = Txn::new(self.clone(), update, true).await?;
+        txn.read_ts = read_ts.into();
+        Ok(txn)
+    }
+
+    /// Versions <= discard_ts can be dropped by compaction, only for managed_txns=true.
+    /// discard_ts must be monotonic: a value lower than the current one is ignored,
+    /// and the txns can't commit_at below it anymore.
+    pub fn set_discard_ts(&self, discard_ts: u64) {
+        if !self.oracle.config().managed_txns {
+            panic!("Cannot use set_discard_ts with managed_txns=false");
+        }
+        self.oracle.set_discard_ts(discard_ts.into());
+    }
+
+    pub async fn view<F: TxnView>(&self, f: F) -> anyhow::Result<()> {
+        let txn = self.new_read_txn().await?;
+        let result = f.view(&txn).await;
+        txn.discard().await?;
+        result
+    }
+
+    /// read-only txn, set/delete will return DBError::ReadOnlyTxn.
+    pub async fn new_read_txn(&self) -> anyhow::Result<Txn> {
+        if self.is_closed() {
+            bail!(DBError::DBClosed);
+        }
+        let is_managed = self.oracle.config().managed_txns;
+        let mut txn = Txn::new(self.clone(), false, is_managed).await?;
+        if is_managed {
+            // read the newest version of all keys
+            txn.read_ts = TxnTs::from(u64::MAX);
+        }
+        Ok(txn)
+    }
 }
 impl Txn {
     pub async fn get<B: Into<Bytes>>(&self, key: B) -> anyhow::Result<Item> {
         let key: Bytes = key.into();
-        if key.len() == 0 {
+        if key.is_empty() {
             bail!(DBError::EmptyKey);
         } else if self.discarded() {
             bail!(DBError::DiscardedTxn);
         }
         self.db().is_banned(&key).await?;
-        let mut item = ItemInner::default();
+        let mut pending_merge = None;
         if self.update() {
             if let Some(pending_writes) = self.pending_writes() {
                 if let Some(entry) = pending_writes.get(key.as_ref()) {
-                    if entry.key().as_ref() == key {
-                        if entry.value_meta().is_deleted_or_expired() {
+                    if entry.meta().contains(Meta::MERGE_ENTRY) {
+                        // fold with the committed versions below.
+                        let mut key_ts = entry.key_ts().clone();
+                        key_ts.set_txn_ts(self.read_ts);
+                        pending_merge = Some((key_ts, entry.value_meta().clone()));
+                    } else if entry.key().as_ref() == key {
+                        if entry
+                            .value_meta()
+                            .is_deleted_or_expired(self.db().opt.now())
+                        {
                             bail!(DBError::KeyNotFound);
                         }
                         let mut key_ts = entry.key_ts().clone();
                         key_ts.set_txn_ts(self.read_ts);
-                        item.set_key_ts(key_ts);
-                        item.set_value_meta(entry.value_meta().clone());
-                        item.set_status(PrefetchStatus::Prefetched);
+                        let item = ItemInner::new(key_ts, entry.value_meta().clone(), self.db());
                         return Ok(item.into());
                     }
                 };
@@ -104,31 +183,49 @@ impl Txn {
             let mut reads_m = self.read_key_hash().lock();
             reads_m.push(hash);
             drop(reads_m);
+            if self.txn_config.keep_conflict_keys {
+                self.read_keys().lock().push(key.clone());
+            }
         }
 
         let mut seek = KeyTs::new(key.clone(), self.read_ts);
-        let (txn_ts, value_meta) = match self.db().get(&seek).await? {
+        let mut versions = match self.db().get(&seek).await? {
             Some((txn_ts, value_meta)) => {
                 if value_meta.value().is_empty() && value_meta.meta().is_empty() {
-                    bail!(DBError::KeyNotFound)
-                }
-                if value_meta.is_deleted_or_expired() {
-                    bail!(DBError::KeyNotFound)
+                    Vec::new()
+                } else if value_meta.meta().contains(Meta::MERGE_ENTRY) {
+                    self.merge_versions(&key, txn_ts, value_meta).await?
+                } else {
+                    seek.set_txn_ts(txn_ts);
+                    vec![(seek, value_meta)]
                 }
-                (txn_ts, value_meta)
             }
-            None => bail!(DBError::KeyNotFound),
+            None => Vec::new(),
         };
-        seek.set_txn_ts(txn_ts);
-        item.set_key_ts(seek);
-        item.set_value_meta(value_meta);
-        Ok(item.into())
+        if let Some(pending) = pending_merge {
+            versions.insert(0, pending);
+        }
+        match resolve_versions(self.db(), versions, self.db().opt.now()).await? {
+            Some((key_ts, value_meta)) => Ok(ItemInner::new(key_ts, value_meta, self.db()).into()),
+            None => bail!(DBError::KeyNotFound),
+        }
     }
 
     pub async fn set<B: Into<Bytes>>(&mut self, key: B, value: B) -> anyhow::Result<()> {
         self.set_entry(Entry::new(key.into(), value.into())).await
     }
 
+    /// The key will expire after ttl, measured by the clock of db.
+    pub async fn set_with_ttl<B: Into<Bytes>>(
+        &mut self,
+        key: B,
+        value: B,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        self.set_entry(Entry::new(key.into(), value.into()).with_ttl(ttl))
+            .await
+    }
+
     pub async fn delete<B: Into<Bytes>>(&mut self, key: B) -> anyhow::Result<()> {
         let mut e = Entry::default();
         e.set_key(key);
@@ -137,13 +234,13 @@ impl Txn {
     }
 
     pub async fn set_entry(&mut self, e: Entry) -> anyhow::Result<()> {
-        self.modify(e.into()).await
+        self.modify(e).await
     }
 
     pub async fn commit(&mut self) -> anyhow::Result<()> {
         match self.pending_writes().as_ref() {
             Some(s) => {
-                if s.len() == 0 {
+                if s.is_empty() {
                     return Ok(());
                 }
             }
@@ -152,13 +249,46 @@ impl Txn {
             }
         };
         self.commit_pre_check()?;
-        let (commit_ts, recv) = self.commit_and_send().await?;
-        let result = recv.await;
-        self.db().oracle.done_commit(commit_ts).await?;
-        result??;
+        let (_, recv) = self.commit_and_send().await?;
+        recv.await??;
         Ok(())
     }
 
+    /// Returns as soon as the entries are queued to be written, the commit_ts is already assigned,
+    /// so commits keep the same order as the calls; use CommitHandle::wait to get the result.
+    pub async fn commit_async(&mut self) -> anyhow::Result<CommitHandle> {
+        match self.pending_writes().as_ref() {
+            Some(s) => {
+                if s.is_empty() {
+                    return Ok(CommitHandle::default());
+                }
+            }
+            None => {
+                return Ok(CommitHandle::default());
+            }
+        };
+        self.commit_pre_check()?;
+        let (commit_ts, recv) = self.commit_and_send().await?;
+        Ok(CommitHandle {
+            commit_ts,
+            recv: recv.into(),
+        })
+    }
+
+    /// Overrides the write options of the commit, e.g. to sync only the critical txns.
+    pub fn set_write_options(&mut self, write_options: WriteOptions) {
+        self.write_options = write_options;
+    }
+
+    /// Commit with the commit_ts managed by the caller, only for managed_txns=true.
+    pub async fn commit_at(&mut self, commit_ts: u64) -> anyhow::Result<()> {
+        if !self.txn_config.managed_txns {
+            panic!("Cannot use commit_at with managed_txns=false. Use commit instead");
+        }
+        self.commit_ts = commit_ts.into();
+        self.commit().await
+    }
+
     pub async fn discard(mut self) -> anyhow::Result<()> {
         if self.discarded() {
             return Ok(());
@@ -174,6 +304,67 @@ impl Txn {
         Ok(())
     }
 }
+/// Checks shared by all the write paths, the size of the batch is checked by the caller.
+pub(super) async fn check_entry(db: &DB, e: &Entry) -> anyhow::Result<()> {
+    let exceeds_size = |prefix: &str, max: usize, key: &[u8]| {
+        bail!(
+            "{} with size {} exceeded {} limit. {}:\n{:?}",
+            prefix,
+            key.len(),
+            max,
+            prefix,
+            if key.len() > 1024 { &key[..1024] } else { key }
+        )
+    };
+    const MAX_KEY_SIZE: usize = 65000;
+    let vlog_file_size = db.opt.vlog.vlog_file_size();
+    if e.key().is_empty() {
+        bail!(DBError::EmptyKey)
+    }
+    if e.key().len() > MAX_KEY_SIZE {
+        exceeds_size("Key", MAX_KEY_SIZE, e.key())?;
+    }
+    if e.value().len() > vlog_file_size {
+        exceeds_size("Value", vlog_file_size, e.value().as_ref())?
+    }
+    db.is_banned(e.key()).await?;
+    Ok(())
+}
+#[derive(Default)]
+pub struct CommitHandle {
+    commit_ts: TxnTs,
+    recv: Option<Receiver<anyhow::Result<()>>>,
+}
+impl CommitHandle {
+    /// Returns 0 if there is nothing to commit.
+    pub fn commit_ts(&self) -> u64 {
+        self.commit_ts.to_u64()
+    }
+
+    /// Wait until the entries are written to vlog and memtable.
+    pub async fn wait(self) -> anyhow::Result<()> {
+        match self.recv {
+            Some(recv) => recv.await?,
+            None => Ok(()),
+        }
+    }
+}
+/// Options applied to the writes of a single commit.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WriteOptions {
+    // Flush the wal and sync the vlog before the commit returns, like Config::sync_writes does for all the writes.
+    sync: bool,
+}
+impl WriteOptions {
+    pub fn set_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    pub fn sync(&self) -> bool {
+        self.sync
+    }
+}
 #[derive(Debug, Clone, Copy)]
 pub struct TxnConfig {
     read_only: bool,
@@ -185,6 +376,33 @@ pub struct TxnConfig {
     // This is only useful for databases built on top of Badger (like Dgraph).
     // Not recommended for most users.
     managed_txns: bool,
+    // Keep the keys read by txns to check conflicts exactly, instead of only the hashes of keys.
+    keep_conflict_keys: bool,
+}
+impl TxnConfig {
+    pub fn set_detect_conflicts(&mut self, detect_conflicts: bool) {
+        self.detect_conflicts = detect_conflicts;
+    }
+
+    pub fn set_managed_txns(&mut self, managed_txns: bool) {
+        self.managed_txns = managed_txns;
+    }
+
+    pub fn set_keep_conflict_keys(&mut self, keep_conflict_keys: bool) {
+        self.keep_conflict_keys = keep_conflict_keys;
+    }
+
+    pub fn detect_conflicts(&self) -> bool {
+        self.detect_conflicts
+    }
+
+    pub fn managed_txns(&self) -> bool {
+        self.managed_txns
+    }
+
+    pub fn keep_conflict_keys(&self) -> bool {
+        self.keep_conflict_keys
+    }
 }
 impl Default for TxnConfig {
     fn default() -> Self {
@@ -192,6 +410,7 @@ impl Default for TxnConfig {
             read_only: false,
             detect_conflicts: true,
             managed_txns: false,
+            keep_conflict_keys: false,
         }
     }
 }
@@ -201,9 +420,12 @@ pub struct Txn {
     size: usize,
     count: usize,
     txn_config: TxnConfig,
+    write_options: WriteOptions,
     db: DB,
     conflict_keys: Option<HashSet<u64>>,
     read_key_hash: Mutex<Vec<u64>>,
+    read_keys: Mutex<Vec<Bytes>>,
+    read_ranges: Mutex<Vec<KeyRange>>,
     pending_writes: Option<HashMap<Bytes, Entry>>, // Vec<u8> -> String
     duplicate_writes: Vec<Entry>,
     num_iters: AtomicI32,
@@ -235,6 +457,8 @@ impl Txn {
             },
 
             read_key_hash: Mutex::new(Vec::new()),
+            read_keys: Mutex::new(Vec::new()),
+            read_ranges: Mutex::new(Vec::new()),
             pending_writes: if update { HashMap::new().into() } else { None },
             duplicate_writes: Default::default(),
             discarded: false,
@@ -243,26 +467,24 @@ impl Txn {
             update,
             num_iters: AtomicI32::new(0),
             txn_config,
+            write_options: WriteOptions::default(),
         };
         Ok(s)
     }
 
     #[inline]
-    pub(super) async fn modify(&mut self, mut e: Entry) -> anyhow::Result<()> {
-        let exceeds_size = |prefix: &str, max: usize, key: &[u8]| {
-            bail!(
-                "{} with size {} exceeded {} limit. {}:\n{:?}",
-                prefix,
-                key.len(),
-                max,
-                prefix,
-                if key.len() > 1024 { &key[..1024] } else { key }
-            )
-        };
+    pub(super) async fn modify(&mut self, e: Entry) -> anyhow::Result<()> {
+        if e.key().starts_with(BADGER_PREFIX) {
+            bail!(DBError::InvalidKey)
+        }
+        self.modify_internal(e).await
+    }
+
+    /// Like modify, but allow the keys with BADGER_PREFIX.
+    pub(super) async fn modify_internal(&mut self, mut e: Entry) -> anyhow::Result<()> {
         let threshold = self.db().opt.vlog_threshold.value_threshold();
         let max_batch_count = self.db().opt.max_batch_count();
         let max_batch_size = self.db().opt.max_batch_size();
-        let vlog_file_size = self.db().opt.vlog.vlog_file_size();
         let mut check_size = |e: &mut Entry| {
             let count = self.count + 1;
             e.try_set_value_threshold(threshold);
@@ -275,27 +497,15 @@ impl Txn {
             Ok(())
         };
 
-        const MAX_KEY_SIZE: usize = 65000;
         if !self.update {
             bail!(DBError::ReadOnlyTxn)
         }
         if self.discarded {
             bail!(DBError::DiscardedTxn)
         }
-        if e.key().is_empty() {
-            bail!(DBError::EmptyKey)
-        }
-        if e.key().starts_with(BADGER_PREFIX) {
-            bail!(DBError::InvalidKey)
-        }
-        if e.key().len() > MAX_KEY_SIZE {
-            exceeds_size("Key", MAX_KEY_SIZE, e.key())?;
-        }
-        if e.value().len() > vlog_file_size {
-            exceeds_size("Value", vlog_file_size, e.value().as_ref())?
-        }
-        self.db.is_banned(&e.key()).await?;
+        check_entry(&self.db, &e).await?;
 
+        e.apply_ttl(self.db.opt.now());
         check_size(&mut e)?;
 
         if let Some(c) = self.conflict_keys.as_mut() {
@@ -319,7 +529,7 @@ impl Txn {
         }
         let mut keep_togther = true;
         if let Some(s) = self.pending_writes.as_ref() {
-            for (_, e) in s {
+            for e in s.values() {
                 if e.version() != TxnTs::default() {
                     keep_togther = false;
                     break;
@@ -332,6 +542,8 @@ impl Txn {
         Ok(())
     }
 
+    // the lock is held across the await to keep the timestamps in order.
+    #[allow(clippy::await_holding_lock)]
     pub(super) async fn commit_and_send(
         &mut self,
     ) -> anyhow::Result<(TxnTs, Receiver<anyhow::Result<()>>)> {
@@ -372,19 +584,19 @@ impl Txn {
         self.duplicate_writes
             .drain(0..)
             .take(duplicate_writes_len)
-            .for_each(|e| process_entry(e));
+            .for_each(process_entry);
 
         if keep_together {
             debug_assert!(commit_ts != TxnTs::default());
             let mut entry = Entry::new(TXN_KEY.into(), commit_ts.to_u64().to_string().into());
             entry.set_version(commit_ts);
             entry.set_meta(Meta::FIN_TXN);
-            entries.push(entry.into())
+            entries.push(entry)
         }
 
         let receiver = match self
             .db
-            .send_entires_to_write_channel(entries, self.size)
+            .send_entires_to_write_channel(entries, self.size, self.write_options, commit_ts.into())
             .await
         {
             Ok(n) => n,
@@ -432,10 +644,23 @@ impl Txn {
     pub(super) fn conflict_keys(&self) -> Option<&HashSet<u64>> {
         self.conflict_keys.as_ref()
     }
+
+    pub(super) fn read_keys(&self) -> &Mutex<Vec<Bytes>> {
+        &self.read_keys
+    }
+
+    pub(super) fn read_ranges(&self) -> &Mutex<Vec<KeyRange>> {
+        &self.read_ranges
+    }
+
+    // only the txn with conflict detection need to track the reads.
+    pub(super) fn track_read(&self) -> bool {
+        self.update && self.conflict_keys.is_some()
+    }
 }
 #[cfg(test)]
 mod tests {
-    use std::{future::Future, pin::Pin};
+    
     trait Update {
         async fn update(self, txn: &mut Txn) -> anyhow::Result<()>;
     }
@@ -444,12 +669,12 @@ mod tests {
     struct Txn;
     struct UpdateK;
     impl Update for UpdateK {
-        async fn update(self, txn: &mut Txn) -> anyhow::Result<()> {
+        async fn update(self, _txn: &mut Txn) -> anyhow::Result<()> {
             println!("abc");
             Ok(())
         }
     }
-    async fn update_data(txn: &mut Txn) -> anyhow::Result<()> {
+    async fn update_data(_txn: &mut Txn) -> anyhow::Result<()> {
         println!("abc");
         Ok(())
     }
@@ -460,15 +685,15 @@ mod tests {
         //     F: FnMut(&mut Txn) -> Pin<Box<dyn Future<Output = anyhow::Result<()>>>>,
         {
             let mut t = Txn;
-            let _ = f.update(&mut t);
+            drop(f.update(&mut t));
             Ok(())
         }
     }
     // fn ab<F>(f: Pin<Box<fn(&mut Txn) -> impl Future<Output = anyhow::Result<()>>>>) {}
     #[tokio::test]
     async fn test_txn() {
-        let t = TxnManager;
-        let k = Box::pin(update_data);
+        let _t = TxnManager;
+        let _k = Box::pin(update_data);
         // ab(k);
         // t.update_async(|txn| async {
 
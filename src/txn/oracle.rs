@@ -1,10 +1,11 @@
 use std::{
     collections::{HashSet, VecDeque},
-    ops::Deref,
+    ops::{Bound, Deref, RangeBounds},
     sync::Arc,
 };
 
 use anyhow::{bail, Ok};
+use bytes::Bytes;
 use parking_lot::{Mutex, MutexGuard};
 use tokio::sync::RwLock;
 
@@ -13,7 +14,7 @@ use crate::{
     util::closer::Closer,
 };
 
-use super::{water_mark::WaterMark, Txn, TxnConfig};
+use super::{water_mark::WaterMark, KeyRange, Txn, TxnConfig, HASH};
 #[derive(Debug, Clone)]
 pub(crate) struct Oracle(Arc<OracleInner>);
 impl Deref for Oracle {
@@ -30,7 +31,7 @@ pub(crate) struct OracleInner {
     read_mark: WaterMark,
     txn_mark: WaterMark,
     config: TxnConfig,
-    pub(super) send_write_req: Mutex<()>,
+    pub(crate) send_write_req: Mutex<()>,
 }
 impl Deref for OracleInner {
     type Target = Mutex<OracleCore>;
@@ -55,6 +56,72 @@ pub(crate) struct OracleCore {
 struct CommittedTxn {
     ts: TxnTs,
     conflict_keys: HashSet<u64>,
+    // sorted keys written by this txn, to check the read ranges and to report the conflict key.
+    keys: Vec<Bytes>,
+}
+impl CommittedTxn {
+    fn new(ts: TxnTs, conflict_keys: HashSet<u64>, mut keys: Vec<Bytes>) -> Self {
+        keys.sort_unstable();
+        Self {
+            ts,
+            conflict_keys,
+            keys,
+        }
+    }
+
+    // the key written by this txn with the hash.
+    fn key_of_hash(&self, hash: u64) -> Option<Bytes> {
+        self.keys.iter().find(|k| HASH.hash_one(k) == hash).cloned()
+    }
+
+    // returns the hash and the key written by this txn which conflicts with the reads.
+    fn find_conflict(
+        &self,
+        read_key_hash: &[u64],
+        read_keys: Option<&[Bytes]>,
+        read_ranges: &[KeyRange],
+    ) -> Option<(u64, Bytes)> {
+        match read_keys {
+            Some(read_keys) => {
+                for key in read_keys {
+                    if self.conflict_keys.contains(&HASH.hash_one(key))
+                        && self.keys.binary_search(key).is_ok()
+                    {
+                        return Some((HASH.hash_one(key), key.clone()));
+                    }
+                }
+            }
+            None => {
+                for hash in read_key_hash {
+                    if !self.conflict_keys.contains(hash) {
+                        continue;
+                    }
+                    if let Some(key) = self.key_of_hash(*hash) {
+                        return Some((*hash, key));
+                    }
+                }
+            }
+        }
+        for range in read_ranges {
+            if let Some(conflict) = self.find_range_conflict(range) {
+                return Some(conflict);
+            }
+        }
+        None
+    }
+
+    // the first key written by this txn in the range.
+    fn find_range_conflict(&self, range: &KeyRange) -> Option<(u64, Bytes)> {
+        let index = self.keys.partition_point(|k| match &range.0 {
+            Bound::Included(s) => k < s,
+            Bound::Excluded(s) => k <= s,
+            Bound::Unbounded => false,
+        });
+        self.keys
+            .get(index)
+            .filter(|key| RangeBounds::<Bytes>::contains(range, *key))
+            .map(|key| (HASH.hash_one(key), key.clone()))
+    }
 }
 impl Oracle {
     pub(crate) fn new(config: TxnConfig, max_version: TxnTs) -> Self {
@@ -64,8 +131,10 @@ impl Oracle {
 impl OracleInner {
     fn new(config: TxnConfig, max_version: TxnTs) -> Self {
         let closer = Closer::new(2);
-        let mut inner = OracleCore::default();
-        inner.next_txn_ts = max_version + 1;
+        let inner = OracleCore {
+            next_txn_ts: max_version + 1,
+            ..Default::default()
+        };
         Self {
             inner: Mutex::new(inner),
             read_mark: WaterMark::new("badger.PendingReads", closer.clone(), max_version),
@@ -76,18 +145,30 @@ impl OracleInner {
         }
     }
 
+    // stop the water marks.
+    pub(crate) async fn stop(&self) {
+        self.signal_stop();
+        let _ = self.closer.wait().await;
+    }
+
+    pub(crate) fn signal_stop(&self) {
+        self.closer.signal();
+    }
+
     #[inline]
-    pub(crate)  fn discard_at_or_below(&self) -> TxnTs {
+    pub(crate) fn discard_at_or_below(&self) -> TxnTs {
         if self.config.managed_txns {
             let lock = self.inner.lock();
             let ts = lock.discard_ts;
             drop(lock);
-            return ts.into();
+            return ts;
         }
-        return self.read_mark.done_until();
+        self.read_mark.done_until()
     }
 
+    // the inner lock is held across the awaits below to keep the timestamps in order.
     #[inline]
+    #[allow(clippy::await_holding_lock)]
     pub(crate) async fn get_latest_read_ts(&self) -> anyhow::Result<TxnTs> {
         if self.config.managed_txns {
             panic!("ReadTimestamp should not be retrieved for managed DB");
@@ -101,24 +182,41 @@ impl OracleInner {
         Ok(read_ts)
     }
     #[inline]
+    #[allow(clippy::await_holding_lock)]
     pub(crate) async fn get_latest_commit_ts(&self, txn: &Txn) -> anyhow::Result<TxnTs> {
         let mut inner_lock = self.inner.lock();
 
         //check read-write conflict
         let read_key_hash_r = txn.read_key_hash().lock();
-        if read_key_hash_r.len() != 0 {
+        let read_keys_r = txn.read_keys().lock();
+        let read_keys = self
+            .config
+            .keep_conflict_keys
+            .then_some(read_keys_r.as_slice());
+        let read_ranges_r = txn.read_ranges().lock();
+        if !read_key_hash_r.is_empty() || !read_ranges_r.is_empty() {
             for commit_txn in inner_lock.committed_txns.iter() {
-                if commit_txn.ts > txn.read_ts {
-                    for hash in read_key_hash_r.iter() {
-                        if commit_txn.conflict_keys.contains(hash) {
-                            drop(read_key_hash_r);
-                            drop(inner_lock);
-                            bail!(DBError::Conflict)
-                        };
-                    }
+                if commit_txn.ts <= txn.read_ts {
+                    continue;
+                }
+                if let Some((key_hash, key)) =
+                    commit_txn.find_conflict(&read_key_hash_r, read_keys, &read_ranges_r)
+                {
+                    let commit_ts = commit_txn.ts.to_u64();
+                    drop(read_ranges_r);
+                    drop(read_keys_r);
+                    drop(read_key_hash_r);
+                    drop(inner_lock);
+                    bail!(DBError::Conflict {
+                        key_hash,
+                        key,
+                        commit_ts
+                    })
                 }
             }
         }
+        drop(read_ranges_r);
+        drop(read_keys_r);
         drop(read_key_hash_r);
 
         let commit_ts = if !self.config.managed_txns {
@@ -130,21 +228,48 @@ impl OracleInner {
             self.txn_mark.begin(txn_ts).await?;
             txn_ts
         } else {
+            // the committed txns below discard_ts are cleaned up, so the conflicts can't be detected.
+            if txn.commit_ts < inner_lock.discard_ts {
+                let discard_ts = inner_lock.discard_ts.to_u64();
+                drop(inner_lock);
+                bail!(DBError::CommitTsBelowDiscardTs {
+                    commit_ts: txn.commit_ts.to_u64(),
+                    discard_ts
+                })
+            }
             txn.commit_ts
         };
 
         debug_assert!(commit_ts >= inner_lock.last_cleanup_ts);
 
-        if self.config.managed_txns {
-            inner_lock.committed_txns.push(CommittedTxn {
-                ts: commit_ts,
-                conflict_keys: txn.conflict_keys().unwrap().clone(),
-            });
+        if self.config.detect_conflicts {
+            if let Some(conflict_keys) = txn.conflict_keys() {
+                let keys = txn
+                    .pending_writes()
+                    .map(|p| p.keys().cloned().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                inner_lock.committed_txns.push(CommittedTxn::new(
+                    commit_ts,
+                    conflict_keys.clone(),
+                    keys,
+                ));
+            }
         }
         drop(inner_lock);
         Ok(commit_ts)
     }
 
+    /// Commit ts for the writes which skip the conflict detection, like WriteBatch.
+    #[allow(clippy::await_holding_lock)]
+    pub(crate) async fn new_commit_ts(&self) -> anyhow::Result<TxnTs> {
+        let mut inner_lock = self.inner.lock();
+        let commit_ts = inner_lock.next_txn_ts;
+        inner_lock.next_txn_ts += 1;
+        self.txn_mark.begin(commit_ts).await?;
+        drop(inner_lock);
+        Ok(commit_ts)
+    }
+
     fn cleanup_committed_txns(&self, guard: &mut MutexGuard<OracleCore>) {
         if !self.config.detect_conflicts {
             return;
@@ -168,6 +293,31 @@ impl OracleInner {
             .collect();
     }
 
+    /// Moves the timestamps past the versions written without a commit ts, e.g. by DB::load.
+    #[allow(clippy::await_holding_lock)]
+    pub(crate) async fn set_max_version(&self, max_version: TxnTs) -> anyhow::Result<()> {
+        if self.config.managed_txns {
+            return Ok(());
+        }
+        let mut inner_lock = self.inner.lock();
+        if max_version < inner_lock.next_txn_ts {
+            return Ok(());
+        }
+        inner_lock.next_txn_ts = max_version + 1;
+        self.txn_mark.begin(max_version).await?;
+        drop(inner_lock);
+        self.done_commit(max_version).await
+    }
+
+    pub(crate) fn set_discard_ts(&self, discard_ts: TxnTs) {
+        let mut inner_lock = self.inner.lock();
+        if discard_ts <= inner_lock.discard_ts {
+            return;
+        }
+        inner_lock.discard_ts = discard_ts;
+        self.cleanup_committed_txns(&mut inner_lock);
+    }
+
     pub(crate) fn config(&self) -> TxnConfig {
         self.config
     }
@@ -195,3 +345,161 @@ pub(crate) async fn max_txn_ts(
     }
     Ok(max_version)
 }
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+        txn::{IteratorOptions, Txn, TxnView},
+    };
+
+    struct TxnTestView;
+    impl TxnView for TxnTestView {
+        async fn view(self, txn: &Txn) -> anyhow::Result<()> {
+            let item = txn.get("key").await?;
+            assert_eq!(item.value().await?, &Bytes::from("value"));
+            Ok(())
+        }
+    }
+    #[tokio::test]
+    async fn test_read_txn() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config).await?;
+
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("key"), Bytes::from("value")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        db.view(TxnTestView).await?;
+
+        let mut txn = db.new_read_txn().await?;
+        let err = txn
+            .set(Bytes::from("key"), Bytes::from("v2"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::ReadOnlyTxn)));
+        let err = txn.delete(Bytes::from("key")).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::ReadOnlyTxn)));
+        assert!(txn.conflict_keys().is_none());
+        assert_eq!(txn.get("key").await?.value().await?, &Bytes::from("value"));
+        txn.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_managed_txn() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.txn.set_managed_txns(true);
+        let db = DB::open(config).await?;
+
+        let mut txn = db.new_txn_at(5, true).await?;
+        txn.set(Bytes::from("key"), Bytes::from("v10")).await?;
+        txn.commit_at(10).await?;
+        txn.discard().await?;
+
+        let mut txn = db.new_txn_at(10, true).await?;
+        txn.set(Bytes::from("key"), Bytes::from("v20")).await?;
+        txn.commit_at(20).await?;
+        txn.discard().await?;
+
+        let txn = db.new_txn_at(9, false).await?;
+        let err = txn.get("key").await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        txn.discard().await?;
+
+        for (read_ts, version, value) in [(10, 10, "v10"), (19, 10, "v10"), (25, 20, "v20")] {
+            let txn = db.new_txn_at(read_ts, false).await?;
+            let item = txn.get("key").await?;
+            assert_eq!(item.version(), version);
+            assert_eq!(item.value().await?, &Bytes::from(value));
+            txn.discard().await?;
+        }
+
+        db.set_discard_ts(15);
+        assert_eq!(db.oracle.discard_at_or_below(), 15.into());
+        db.set_discard_ts(12);
+        assert_eq!(db.oracle.discard_at_or_below(), 15.into());
+
+        let mut txn = db.new_txn_at(20, true).await?;
+        txn.set(Bytes::from("key"), Bytes::from("v12")).await?;
+        let err = txn.commit_at(12).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DBError::CommitTsBelowDiscardTs {
+                commit_ts: 12,
+                discard_ts: 15
+            })
+        ));
+        txn.discard().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_conflict_error() -> anyhow::Result<()> {
+        for keep_conflict_keys in [false, true] {
+            let dir = tempfile::tempdir()?;
+            let mut config = test_config(dir.path());
+            config.txn.set_keep_conflict_keys(keep_conflict_keys);
+            let db = DB::open(config).await?;
+
+            let mut txn = db.get_update_txn().await?;
+            txn.set(Bytes::from("key"), Bytes::from("v1")).await?;
+            txn.commit().await?;
+            txn.discard().await?;
+
+            let mut reader = db.get_update_txn().await?;
+            reader.get("key").await?;
+            assert!(reader.get("other").await.is_err());
+
+            let mut writer = db.get_update_txn().await?;
+            writer.set(Bytes::from("key"), Bytes::from("v2")).await?;
+            let handle = writer.commit_async().await?;
+            let winner_ts = handle.commit_ts();
+            handle.wait().await?;
+            writer.discard().await?;
+
+            reader.set(Bytes::from("x"), Bytes::from("v")).await?;
+            let err = reader.commit().await.unwrap_err();
+            match err.downcast_ref() {
+                Some(DBError::Conflict {
+                    key_hash,
+                    key,
+                    commit_ts,
+                }) => {
+                    assert_eq!(*key_hash, crate::txn::HASH.hash_one(Bytes::from("key")));
+                    assert_eq!(key, "key");
+                    assert_eq!(*commit_ts, winner_ts);
+                }
+                _ => panic!("expected conflict error, got {err}"),
+            }
+            reader.discard().await?;
+        }
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_range_conflict() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+
+        let mut scan = db.get_update_txn().await?;
+        let mut iter = scan.iter(IteratorOptions::default().set_prefix("m")).await?;
+        iter.rewind().await?;
+        assert!(!iter.valid());
+        drop(iter);
+
+        // the written keys are around the range but not in it.
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("a"), Bytes::from("v")).await?;
+        txn.set(Bytes::from("z"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        scan.set(Bytes::from("x"), Bytes::from("v")).await?;
+        scan.commit().await?;
+        scan.discard().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use rand::{thread_rng, Rng};
+
+use crate::{db::DB, errors::DBError};
+
+use super::TxnUpdate;
+/// How DB::update_with_retry retries the txn after DBError::Conflict.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    // give up once the next retry would start after this duration since the first attempt.
+    deadline: Option<Duration>,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            deadline: None,
+        }
+    }
+}
+impl RetryPolicy {
+    pub fn set_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn set_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn set_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn set_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline.into();
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    pub fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    // exponential backoff with equal jitter: a random duration in [d/2, d].
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.initial_backoff.saturating_mul(
+            1u32.checked_shl(attempt.saturating_sub(1))
+                .unwrap_or(u32::MAX),
+        );
+        let backoff = exp.min(self.max_backoff);
+        let half = backoff / 2;
+        half + half.mul_f64(thread_rng().gen::<f64>())
+    }
+}
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RetryStats {
+    attempts: u32,
+    backoff: Duration,
+    elapsed: Duration,
+}
+impl RetryStats {
+    /// Number of times the closure was run, including the successful one.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Total time slept between attempts.
+    pub fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+impl Display for RetryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Gave up after {} attempts in {:?}",
+            self.attempts, self.elapsed
+        )
+    }
+}
+impl DB {
+    /// Like DB::update, but run f again on a new txn when the commit fails with DBError::Conflict.
+    /// The other errors are returned as they are. When it gives up on the conflicts, the last one
+    /// is returned with the RetryStats as its context, so both of them can be downcast from the error.
+    pub async fn update_with_retry<F: TxnUpdate + Clone>(
+        &self,
+        f: F,
+        policy: RetryPolicy,
+    ) -> anyhow::Result<RetryStats> {
+        let start = Instant::now();
+        let mut stats = RetryStats::default();
+        loop {
+            stats.attempts += 1;
+            let err = match self.update(f.clone()).await {
+                Ok(_) => {
+                    stats.elapsed = start.elapsed();
+                    return Ok(stats);
+                }
+                Err(e) => e,
+            };
+            if !matches!(err.downcast_ref(), Some(DBError::Conflict { .. })) {
+                return Err(err);
+            }
+            if stats.attempts >= policy.max_attempts {
+                stats.elapsed = start.elapsed();
+                return Err(err.context(stats));
+            }
+            let backoff = policy.backoff(stats.attempts);
+            if let Some(deadline) = policy.deadline {
+                if start.elapsed() + backoff > deadline {
+                    stats.elapsed = start.elapsed();
+                    return Err(err.context(stats));
+                }
+            }
+            stats.backoff += backoff;
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+        txn::{Txn, TxnUpdate},
+    };
+
+    use super::{RetryPolicy, RetryStats};
+
+    #[derive(Clone)]
+    struct Incr;
+    impl TxnUpdate for Incr {
+        async fn update(self, txn: &mut Txn) -> anyhow::Result<()> {
+            let count = match txn.get("counter").await {
+                Ok(item) => String::from_utf8(item.value_copy().await?)?.parse::<u64>()?,
+                Err(e) => match e.downcast_ref() {
+                    Some(DBError::KeyNotFound) => 0,
+                    _ => return Err(e),
+                },
+            };
+            tokio::task::yield_now().await;
+            txn.set(Bytes::from("counter"), Bytes::from((count + 1).to_string()))
+                .await
+        }
+    }
+    // commit a write to the read key from another txn on the first conflicts attempts.
+    #[derive(Clone)]
+    struct Conflict {
+        db: DB,
+        conflicts: Arc<AtomicU32>,
+    }
+    impl TxnUpdate for Conflict {
+        async fn update(self, txn: &mut Txn) -> anyhow::Result<()> {
+            txn.get("counter").await?;
+            if self.conflicts.load(Ordering::Acquire) > 0 {
+                self.conflicts.fetch_sub(1, Ordering::AcqRel);
+                self.db.update(Incr).await?;
+            }
+            txn.set(Bytes::from("other"), Bytes::from("v")).await
+        }
+    }
+    #[derive(Clone)]
+    struct Missing;
+    impl TxnUpdate for Missing {
+        async fn update(self, txn: &mut Txn) -> anyhow::Result<()> {
+            txn.get("missing").await?;
+            Ok(())
+        }
+    }
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default()
+            .set_initial_backoff(Duration::from_millis(10))
+            .set_max_backoff(Duration::from_millis(50));
+        for (attempt, max) in [(1, 10), (2, 20), (3, 40), (4, 50), (40, 50)] {
+            let backoff = policy.backoff(attempt);
+            let max = Duration::from_millis(max);
+            assert!(backoff >= max / 2 && backoff <= max);
+        }
+    }
+    #[tokio::test]
+    async fn test_update_with_retry() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config).await?;
+
+        let policy = RetryPolicy::default()
+            .set_max_attempts(100)
+            .set_initial_backoff(Duration::from_millis(1));
+        let (a, b, c) = tokio::join!(
+            db.update_with_retry(Incr, policy),
+            db.update_with_retry(Incr, policy),
+            db.update_with_retry(Incr, policy)
+        );
+        for result in [a, b, c] {
+            assert!(result.is_ok());
+        }
+
+        let txn = db.get_update_txn().await?;
+        assert_eq!(txn.get("counter").await?.value_copy().await?, b"3");
+        txn.discard().await?;
+
+        let conflicts = Arc::new(AtomicU32::new(3));
+        let f = Conflict {
+            db: db.clone(),
+            conflicts: conflicts.clone(),
+        };
+        let stats = db.update_with_retry(f, policy).await.unwrap();
+        assert_eq!(stats.attempts(), 4);
+        assert!(stats.backoff() > Duration::ZERO);
+        assert_eq!(conflicts.load(Ordering::Acquire), 0);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_update_with_retry_give_up() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        db.update(Incr).await?;
+        let f = Conflict {
+            db: db.clone(),
+            conflicts: Arc::new(AtomicU32::new(u32::MAX)),
+        };
+
+        // stop after max_attempts.
+        let policy = RetryPolicy::default()
+            .set_max_attempts(3)
+            .set_initial_backoff(Duration::from_millis(1));
+        let err = db.update_with_retry(f.clone(), policy).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::Conflict { .. })));
+        let stats = err.downcast_ref::<RetryStats>().unwrap();
+        assert_eq!(stats.attempts(), 3);
+
+        // stop when the next retry would start after the deadline.
+        let policy = RetryPolicy::default()
+            .set_initial_backoff(Duration::from_secs(1))
+            .set_deadline(Duration::from_millis(100));
+        let err = db.update_with_retry(f, policy).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::Conflict { .. })));
+        let stats = err.downcast_ref::<RetryStats>().unwrap();
+        assert_eq!(stats.attempts(), 1);
+        assert_eq!(stats.backoff(), Duration::ZERO);
+        assert!(stats.elapsed() < Duration::from_millis(500));
+
+        // the other errors are not retried and returned as they are.
+        let err = db.update_with_retry(Missing, policy).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        assert!(err.downcast_ref::<RetryStats>().is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use anyhow::bail;
+use bytes::{BufMut, Bytes, BytesMut};
+use tokio::sync::Mutex;
+
+use crate::{db::DB, errors::DBError, kv::Entry};
+
+use super::{RetryPolicy, Txn, TxnUpdate, SEQUENCE_PREFIX};
+
+/// Hands out monotonically increasing u64 ids, leasing `bandwidth` ids at a time
+/// so that only one write per lease hits the db.
+#[derive(Debug)]
+pub struct Sequence {
+    db: DB,
+    key: Bytes,
+    bandwidth: u64,
+    lease: Mutex<Lease>,
+}
+#[derive(Debug, Default)]
+struct Lease {
+    next: u64,
+    leased: u64,
+}
+impl DB {
+    /// Ids of the sequence are persisted under a reserved key, so the same `key`
+    /// can still be used as a normal key. Only for managed_txns=false.
+    pub async fn get_sequence<B: Into<Bytes>>(
+        &self,
+        key: B,
+        bandwidth: u64,
+    ) -> anyhow::Result<Sequence> {
+        if self.oracle.config().managed_txns() {
+            bail!(DBError::SequenceManagedTxns);
+        }
+        if bandwidth == 0 {
+            bail!(DBError::ZeroBandwidth);
+        }
+        let key: Bytes = key.into();
+        let mut internal_key = BytesMut::with_capacity(SEQUENCE_PREFIX.len() + key.len());
+        internal_key.put_slice(SEQUENCE_PREFIX);
+        internal_key.put_slice(&key);
+        let seq = Sequence {
+            db: self.clone(),
+            key: internal_key.freeze(),
+            bandwidth,
+            lease: Mutex::new(Lease::default()),
+        };
+        seq.update_lease(&mut *seq.lease.lock().await).await?;
+        Ok(seq)
+    }
+}
+impl Sequence {
+    pub async fn next(&self) -> anyhow::Result<u64> {
+        let mut lease = self.lease.lock().await;
+        if lease.next >= lease.leased {
+            self.update_lease(&mut lease).await?;
+        }
+        let value = lease.next;
+        lease.next += 1;
+        Ok(value)
+    }
+
+    /// Give back the unused ids of the current lease, so the next lease starts from them.
+    /// Later calls of next will lease again.
+    pub async fn release(&self) -> anyhow::Result<()> {
+        let mut lease = self.lease.lock().await;
+        let mut txn = self.db.get_update_txn().await?;
+        let result = Self::release_lease(&mut txn, &self.key, &lease).await;
+        txn.discard().await?;
+        result?;
+        lease.leased = lease.next;
+        Ok(())
+    }
+
+    async fn release_lease(txn: &mut Txn, key: &Bytes, lease: &Lease) -> anyhow::Result<()> {
+        let item = txn.get(key.clone()).await?;
+        if decode(item.value().await?)? != lease.leased {
+            bail!("Sequence {:?} was leased by others, cannot release it", key);
+        }
+        txn.modify_internal(Entry::new(key.clone(), encode(lease.next)))
+            .await?;
+        txn.commit().await
+    }
+
+    async fn update_lease(&self, lease: &mut Lease) -> anyhow::Result<()> {
+        let update = LeaseUpdate {
+            key: self.key.clone(),
+            bandwidth: self.bandwidth,
+            start: Default::default(),
+        };
+        self.db
+            .update_with_retry(update.clone(), RetryPolicy::default())
+            .await?;
+        let start = update.start.load(Ordering::Acquire);
+        lease.next = start;
+        lease.leased = start + self.bandwidth;
+        Ok(())
+    }
+}
+#[derive(Debug, Clone)]
+struct LeaseUpdate {
+    key: Bytes,
+    bandwidth: u64,
+    start: Arc<AtomicU64>,
+}
+impl TxnUpdate for LeaseUpdate {
+    async fn update(self, txn: &mut Txn) -> anyhow::Result<()> {
+        let start = match txn.get(self.key.clone()).await {
+            Ok(item) => decode(item.value().await?)?,
+            Err(e) => match e.downcast_ref() {
+                Some(DBError::KeyNotFound) => 0,
+                _ => return Err(e),
+            },
+        };
+        let Some(leased) = start.checked_add(self.bandwidth) else {
+            bail!("Sequence {:?} is exhausted", self.key);
+        };
+        txn.modify_internal(Entry::new(self.key.clone(), encode(leased)))
+            .await?;
+        self.start.store(start, Ordering::Release);
+        Ok(())
+    }
+}
+fn encode(num: u64) -> Bytes {
+    Bytes::copy_from_slice(&num.to_be_bytes())
+}
+fn decode(value: &[u8]) -> anyhow::Result<u64> {
+    match <[u8; 8]>::try_from(value) {
+        Ok(buf) => Ok(u64::from_be_bytes(buf)),
+        Err(_) => bail!("Invalid sequence value of length {}", value.len()),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+    };
+
+    #[tokio::test]
+    async fn test_sequence() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config).await?;
+
+        let err = db.get_sequence("seq", 0).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::ZeroBandwidth)));
+
+        let seq = db.get_sequence("seq", 10).await?;
+        for i in 0..25 {
+            assert_eq!(seq.next().await?, i);
+        }
+        seq.release().await?;
+        let seq = db.get_sequence("seq", 10).await?;
+        assert_eq!(seq.next().await?, 25);
+
+        // the reserved key does not collide with the user key.
+        let txn = db.new_read_txn().await?;
+        let err = txn.get("seq").await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        txn.discard().await?;
+
+        let other = db.get_sequence("seq", 3).await?;
+        // other leased after seq, so seq cannot release its lease.
+        assert!(seq.release().await.is_err());
+
+        // sequences on the same key never hand out the same id.
+        let mut ids = HashSet::new();
+        for _ in 0..20 {
+            let (a, b) = tokio::join!(seq.next(), other.next());
+            assert!(ids.insert(a?));
+            assert!(ids.insert(b?));
+        }
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_sequence_managed_txns() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.txn.set_managed_txns(true);
+        let db = DB::open(config).await?;
+        let err = db.get_sequence("seq", 10).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DBError::SequenceManagedTxns)
+        ));
+        db.close().await
+    }
+}
@@ -0,0 +1,366 @@
+use std::{collections::VecDeque, future::Future, mem::take, sync::Arc};
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+use prost::Message;
+use tokio::{
+    sync::mpsc::{self, Sender},
+    task::JoinSet,
+};
+
+use crate::{db::DB, kv::TxnTs, pb::badgerpb4::Kv};
+
+use super::{Item, IteratorOptions, Txn};
+
+const DEFAULT_NUM_GO: usize = 8;
+// send the kvs of a range once they are bigger than this.
+const MAX_STREAM_BATCH_SIZE: usize = 4 << 20;
+// more ranges than the readers only help to balance them.
+const RANGES_PER_GO: usize = 4;
+
+type ChooseKey = Arc<dyn Fn(&Item) -> bool + Send + Sync>;
+type KeyToList = Arc<dyn Fn(&[u8], Vec<Kv>) -> anyhow::Result<Vec<Kv>> + Send + Sync>;
+// start key and the end key (excluded) of a range, None means the end of prefix.
+type KeyRange = (Bytes, Option<Bytes>);
+
+/// Reads the keys of db at a fixed read_ts, the keyspace is split at the boundaries of tables
+/// and the ranges are read concurrently. The kvs of a range are tagged with its stream_id,
+/// and its last batch ends with a kv with stream_done set.
+pub struct Stream {
+    db: DB,
+    // only for managed_txns=true, otherwise read at the latest read_ts.
+    read_ts: Option<TxnTs>,
+    prefix: Bytes,
+    since_ts: TxnTs,
+    num_go: usize,
+    choose_key: Option<ChooseKey>,
+    key_to_list: Option<KeyToList>,
+}
+impl DB {
+    pub fn new_stream(&self) -> Stream {
+        if self.oracle.config().managed_txns {
+            panic!("Cannot use new_stream with managed_txns=true. Use new_stream_at instead");
+        }
+        Stream::new(self.clone(), None)
+    }
+
+    /// Only for managed_txns=true, reads the versions <= read_ts.
+    pub fn new_stream_at(&self, read_ts: TxnTs) -> Stream {
+        if !self.oracle.config().managed_txns {
+            panic!("Cannot use new_stream_at with managed_txns=false. Use new_stream instead");
+        }
+        Stream::new(self.clone(), read_ts.into())
+    }
+}
+impl Stream {
+    fn new(db: DB, read_ts: Option<TxnTs>) -> Self {
+        Self {
+            db,
+            read_ts,
+            prefix: Bytes::new(),
+            since_ts: TxnTs::default(),
+            num_go: DEFAULT_NUM_GO,
+            choose_key: None,
+            key_to_list: None,
+        }
+    }
+
+    /// Only read the keys with this prefix.
+    pub fn set_prefix<B: Into<Bytes>>(mut self, prefix: B) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Only read the versions newer than since_ts.
+    pub fn set_since_ts(mut self, since_ts: TxnTs) -> Self {
+        self.since_ts = since_ts;
+        self
+    }
+
+    /// Number of ranges read concurrently.
+    pub fn set_num_go(mut self, num_go: usize) -> Self {
+        self.num_go = num_go.max(1);
+        self
+    }
+
+    /// Called with the newest version of each key above since_ts, the key is skipped if it
+    /// returns false.
+    pub fn set_choose_key<F>(mut self, choose_key: F) -> Self
+    where
+        F: Fn(&Item) -> bool + Send + Sync + 'static,
+    {
+        self.choose_key = Some(Arc::new(choose_key));
+        self
+    }
+
+    /// Converts the versions of a key (newest first) to the kvs to send. By default only the newest
+    /// version is sent, unless it is deleted or expired.
+    pub fn set_key_to_list<F>(mut self, key_to_list: F) -> Self
+    where
+        F: Fn(&[u8], Vec<Kv>) -> anyhow::Result<Vec<Kv>> + Send + Sync + 'static,
+    {
+        self.key_to_list = Some(Arc::new(key_to_list));
+        self
+    }
+
+    /// Reads all the ranges and calls send with the batches one at a time,
+    /// returns the first error of reading or send.
+    pub async fn orchestrate<F, Fut>(self, mut send: F) -> anyhow::Result<()>
+    where
+        F: FnMut(Vec<Kv>) -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        let txn = match self.read_ts {
+            Some(read_ts) => self.db.new_txn_at(read_ts.to_u64(), false).await?,
+            None => self.db.new_read_txn().await?,
+        };
+        let ranges = self
+            .ranges()
+            .await
+            .into_iter()
+            .enumerate()
+            .map(|(index, range)| (index as u32 + 1, range))
+            .collect::<VecDeque<_>>();
+        let ranges = Arc::new(Mutex::new(ranges));
+        let txn = Arc::new(txn);
+        let stream = Arc::new(self);
+
+        let (sender, mut receiver) = mpsc::channel::<Vec<Kv>>(stream.num_go);
+        let mut readers = JoinSet::new();
+        for _ in 0..stream.num_go {
+            let stream = stream.clone();
+            let txn = txn.clone();
+            let ranges = ranges.clone();
+            let sender = sender.clone();
+            readers.spawn(async move {
+                loop {
+                    let next = ranges.lock().pop_front();
+                    match next {
+                        Some((stream_id, range)) => {
+                            stream.read_range(&txn, stream_id, range, &sender).await?
+                        }
+                        None => return anyhow::Ok(()),
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut result = Ok(());
+        while let Some(kvs) = receiver.recv().await {
+            if let Err(e) = send(kvs).await {
+                result = Err(e);
+                break;
+            }
+        }
+        // the readers fail to send once it is dropped.
+        drop(receiver);
+        while let Some(r) = readers.join_next().await {
+            let r = r.map_err(anyhow::Error::from).and_then(|r| r);
+            if result.is_ok() {
+                result = r;
+            }
+        }
+        // the iterators are dropped with the readers.
+        if let Ok(txn) = Arc::try_unwrap(txn) {
+            txn.discard().await?;
+        }
+        result
+    }
+
+    // split the keyspace of prefix at the biggest keys of the tables.
+    async fn ranges(&self) -> Vec<KeyRange> {
+        let mut splits = Vec::new();
+        for handler in self.db.level_controller.levels() {
+            let handler_r = handler.read().await;
+            splits.extend(
+                handler_r
+                    .tables
+                    .iter()
+                    .map(|table| table.biggest().key().clone())
+                    .filter(|key| key.starts_with(&self.prefix)),
+            );
+            drop(handler_r);
+        }
+        splits.sort_unstable();
+        splits.dedup();
+        let max_splits = self.num_go * RANGES_PER_GO;
+        if splits.len() > max_splits {
+            splits = (0..max_splits)
+                .map(|i| splits[i * splits.len() / max_splits].clone())
+                .collect();
+        }
+
+        let mut ranges = Vec::with_capacity(splits.len() + 1);
+        let mut start = self.prefix.clone();
+        for split in splits {
+            if split > start {
+                ranges.push((start, Some(split.clone())));
+                start = split;
+            }
+        }
+        ranges.push((start, None));
+        ranges
+    }
+
+    async fn read_range(
+        &self,
+        txn: &Txn,
+        stream_id: u32,
+        (start, end): KeyRange,
+        sender: &Sender<Vec<Kv>>,
+    ) -> anyhow::Result<()> {
+        let opt = IteratorOptions::default()
+            .set_prefix(self.prefix.clone())
+            .set_all_versions(true);
+        let mut iter = txn.iter(opt).await?;
+        iter.seek(start).await?;
+
+        let mut batch = Vec::new();
+        let mut batch_size = 0;
+        while let Some(item) = iter.item() {
+            if end.as_ref().is_some_and(|end| item.key() >= end.as_ref()) {
+                break;
+            }
+            let key = Bytes::copy_from_slice(item.key());
+            // the versions of key newer than since_ts, newest first.
+            let mut items = Vec::new();
+            while let Some(item) = iter.item() {
+                if item.key() != key.as_ref() {
+                    break;
+                }
+                if TxnTs::from(item.version()) > self.since_ts {
+                    items.push(item.clone());
+                }
+                iter.next().await?;
+            }
+            let newest = match items.first() {
+                Some(newest) => newest,
+                None => continue,
+            };
+            if let Some(choose_key) = self.choose_key.as_ref() {
+                if !choose_key(newest) {
+                    continue;
+                }
+            }
+            let kvs = match self.key_to_list.as_ref() {
+                Some(key_to_list) => {
+                    let mut versions = Vec::with_capacity(items.len());
+                    for item in items.iter() {
+                        versions.push(item.to_kv().await?);
+                    }
+                    key_to_list(&key, versions)?
+                }
+                None if newest.is_deleted_or_expired() => Vec::new(),
+                None => vec![newest.to_kv().await?],
+            };
+            for mut kv in kvs {
+                kv.stream_id = stream_id;
+                batch_size += kv.encoded_len();
+                batch.push(kv);
+            }
+            if batch_size >= MAX_STREAM_BATCH_SIZE {
+                sender.send(take(&mut batch)).await?;
+                batch_size = 0;
+            }
+        }
+        batch.push(Kv {
+            stream_id,
+            stream_done: true,
+            ..Default::default()
+        });
+        sender.send(batch).await?;
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        pb::badgerpb4::Kv,
+    };
+
+    #[tokio::test]
+    async fn test_stream() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.memtable.set_memtable_size(1 << 20);
+        let db = DB::open(config).await?;
+
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+        let value = |i: usize| Bytes::from(format!("{:0200}", i));
+        // several tables, so the keyspace is split.
+        let mut batch = db.new_write_batch();
+        for i in 0..10000 {
+            batch.set(key(i), value(i)).await?;
+        }
+        batch.set(Bytes::from("other"), value(0)).await?;
+        batch.flush().await?;
+        let mut batch = db.new_write_batch();
+        batch.set(key(1), value(2)).await?;
+        batch.delete(key(2)).await?;
+        batch.set(Bytes::from("v"), value(1)).await?;
+        batch.flush().await?;
+        let mut batch = db.new_write_batch();
+        batch.set(Bytes::from("v"), value(2)).await?;
+        batch.flush().await?;
+
+        let mut kvs: Vec<Kv> = Vec::new();
+        db.new_stream()
+            .set_prefix("key")
+            .set_num_go(4)
+            .set_choose_key(|item| item.key() != b"key00003")
+            .orchestrate(|batch| {
+                kvs.extend(batch);
+                async { Ok(()) }
+            })
+            .await?;
+        let (done, kvs): (Vec<_>, Vec<_>) = kvs.into_iter().partition(|kv| kv.stream_done);
+        let stream_ids = done.iter().map(|kv| kv.stream_id).collect::<HashSet<_>>();
+        assert_eq!(stream_ids.len(), done.len());
+        assert!(kvs.iter().all(|kv| stream_ids.contains(&kv.stream_id)));
+
+        let mut kvs = kvs;
+        kvs.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(kvs.len(), 10000 - 2);
+        assert_eq!(kvs[1].key, key(1));
+        assert_eq!(kvs[1].value, value(2));
+        assert_eq!(kvs[2].key, key(4));
+
+        // all the versions of v.
+        let mut versions = Vec::new();
+        db.new_stream()
+            .set_prefix("v")
+            .set_key_to_list(|_, versions| Ok(versions))
+            .orchestrate(|batch| {
+                versions.extend(batch.into_iter().filter(|kv| !kv.stream_done));
+                async { Ok(()) }
+            })
+            .await?;
+        let values = versions.into_iter().map(|kv| kv.value).collect::<Vec<_>>();
+        assert_eq!(values, vec![value(2), value(1)]);
+
+        // only the versions above since_ts.
+        let txn = db.new_read_txn().await?;
+        let newest = txn.get("v").await?.version();
+        txn.discard().await?;
+        let mut versions = Vec::new();
+        db.new_stream()
+            .set_prefix("v")
+            .set_since_ts((newest - 1).into())
+            .set_choose_key(move |item| item.version() == newest)
+            .set_key_to_list(|_, versions| Ok(versions))
+            .orchestrate(|batch| {
+                versions.extend(batch.into_iter().filter(|kv| !kv.stream_done));
+                async { Ok(()) }
+            })
+            .await?;
+        let values = versions.into_iter().map(|kv| kv.value).collect::<Vec<_>>();
+        assert_eq!(values, vec![value(2)]);
+        db.close().await
+    }
+}
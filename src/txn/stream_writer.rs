@@ -0,0 +1,352 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::remove_file,
+    mem::{replace, take},
+};
+
+use anyhow::bail;
+use log::error;
+use tokio::sync::oneshot;
+
+use crate::{
+    db::DB,
+    key_registry::AesCipher,
+    kv::{Entry, KeyTs, KeyTsBorrow, Meta, TxnTs, ValuePointer},
+    level::drop::BlockedWrites,
+    pb::badgerpb4::{Kv, ManifestChange},
+    table::{write::TableBuilder, Table},
+    util::DBFileId,
+    write::WriteReq,
+};
+
+use super::backup::entry_from_kv;
+
+/// Writes the kvs sent by Stream into an empty db, the tables are built straight into the bottom
+/// level instead of going through the memtables and the compactions. The kvs of a stream must be
+/// sorted by key then version (newest first), and the streams must not overlap with each other.
+/// The writes of db are blocked until it's finished or dropped, the tables built are deleted
+/// if it's dropped before finished. The large values are written into new vlog files.
+pub struct StreamWriter {
+    db: DB,
+    cipher: Option<AesCipher>,
+    writers: HashMap<u32, SortedWriter>,
+    done: HashSet<u32>,
+    tables: Vec<Table>,
+    max_version: TxnTs,
+    _blocked: BlockedWrites,
+}
+// builds the tables of a stream.
+struct SortedWriter {
+    builder: TableBuilder,
+    last: KeyTs,
+}
+impl DB {
+    /// Fails if db is not empty.
+    pub async fn new_stream_writer(&self) -> anyhow::Result<StreamWriter> {
+        let blocked = self.block_pending_writes().await?;
+        let memtable = self.memtable.as_ref().unwrap();
+        let mut is_empty = memtable.read().await.skip_list.is_empty();
+        for handler in self.level_controller.levels() {
+            is_empty &= handler.read().await.tables.is_empty();
+        }
+        if !is_empty {
+            bail!("StreamWriter can only write into an empty DB");
+        }
+        self.vlog.rotate().await?;
+        Ok(StreamWriter {
+            db: self.clone(),
+            cipher: self.key_registry.latest_cipher().await?,
+            writers: HashMap::new(),
+            done: HashSet::new(),
+            tables: Vec::new(),
+            max_version: TxnTs::default(),
+            _blocked: blocked,
+        })
+    }
+}
+impl StreamWriter {
+    /// Writes a batch of kvs, the kv with stream_done set finishes its stream.
+    pub async fn write(&mut self, kvs: Vec<Kv>) -> anyhow::Result<()> {
+        let mut streams = Vec::with_capacity(kvs.len());
+        let mut entries = Vec::with_capacity(kvs.len());
+        for kv in kvs {
+            if self.done.contains(&kv.stream_id) {
+                bail!("Stream {} is already done", kv.stream_id);
+            }
+            streams.push((kv.stream_id, kv.stream_done));
+            if !kv.stream_done {
+                let e = entry_from_kv(kv)?;
+                self.max_version = self.max_version.max(e.version());
+                entries.push(e);
+            }
+        }
+
+        // the large values are written to the vlog, the result is not waited by anyone.
+        let (sender, _) = oneshot::channel();
+        let mut reqs = vec![WriteReq::new(entries, sender)];
+        self.db.vlog.write(&mut reqs).await?;
+        let mut entries_vptrs = take(reqs[0].entries_vptrs_mut()).into_iter();
+        drop(reqs);
+
+        for (stream_id, stream_done) in streams {
+            if stream_done {
+                if let Some(writer) = self.writers.remove(&stream_id) {
+                    if !writer.builder.is_empty() {
+                        let table = self.build_table(writer.builder).await?;
+                        self.tables.push(table);
+                    }
+                }
+                self.done.insert(stream_id);
+                continue;
+            }
+            let (entry, vptr) = entries_vptrs.next().unwrap();
+            let writer = self
+                .writers
+                .entry(stream_id)
+                .or_insert_with(|| SortedWriter {
+                    builder: TableBuilder::new(self.db.opt.table.clone(), self.cipher.clone()),
+                    last: KeyTs::default(),
+                });
+            let key_ts = entry.key_ts().clone();
+            if !writer.last.is_empty() && key_ts <= writer.last {
+                bail!(
+                    "Keys are not in sorted order in stream {}: {:?} after {:?}",
+                    stream_id,
+                    key_ts,
+                    writer.last
+                );
+            }
+            // the versions of a key are kept in the same table.
+            let split = !writer.last.is_empty()
+                && key_ts.key() != writer.last.key()
+                && writer.builder.reacded_capacity();
+            let builder = if split {
+                let new_builder = TableBuilder::new(self.db.opt.table.clone(), self.cipher.clone());
+                Some(replace(&mut writer.builder, new_builder))
+            } else {
+                None
+            };
+            writer.push(entry, vptr);
+            writer.last = key_ts;
+            if let Some(builder) = builder {
+                let table = self.build_table(builder).await?;
+                self.tables.push(table);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes the streams not yet done, then adds all the tables into the bottom level at once.
+    pub async fn finish(mut self) -> anyhow::Result<()> {
+        for (_, writer) in take(&mut self.writers) {
+            if !writer.builder.is_empty() {
+                let table = self.build_table(writer.builder).await?;
+                self.tables.push(table);
+            }
+        }
+        // the tables are deleted on drop until they are in the manifest.
+        self.tables.sort_by(|a, b| a.smallest().cmp(b.smallest()));
+        let overlap = self
+            .tables
+            .windows(2)
+            .find(|w| w[0].biggest().key() >= w[1].smallest().key());
+        if let Some(w) = overlap {
+            bail!(
+                "The key ranges of streams overlap: {:?} and {:?}",
+                w[0].biggest(),
+                w[1].smallest()
+            );
+        }
+
+        let handler = self.db.level_controller.levels().last().unwrap();
+        let changes = self
+            .tables
+            .iter()
+            .map(|table| {
+                ManifestChange::new_create(
+                    table.table_id(),
+                    handler.level(),
+                    table
+                        .cipher()
+                        .and_then(|x| x.cipher_key_id().into())
+                        .unwrap_or_default(),
+                    table.config().compression(),
+                )
+            })
+            .collect();
+        // the tables point to the vlog, so sync it before they are in the manifest.
+        self.db.vlog.rotate().await?;
+        self.db.level_controller.manifest().push_changes(changes)?;
+        let tables = take(&mut self.tables);
+        handler.replace(&[], &tables).await;
+        self.db.oracle.set_max_version(self.max_version).await
+    }
+
+    async fn build_table(&self, mut builder: TableBuilder) -> anyhow::Result<Table> {
+        let file_id = self.db.level_controller.get_reserve_file_id();
+        let file_path = file_id.join_dir(self.db.opt.level_controller.dir());
+        builder
+            .build(
+                file_path,
+                self.db.index_cache.clone(),
+                self.db.block_cache.clone(),
+            )
+            .await
+    }
+}
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        for table in take(&mut self.tables) {
+            let path = table
+                .table_id()
+                .join_dir(self.db.opt.level_controller.dir());
+            drop(table);
+            if let Err(e) = remove_file(&path) {
+                error!("Failed to remove the table {:?}: {}", path, e);
+            }
+        }
+    }
+}
+impl SortedWriter {
+    fn push(&mut self, mut entry: Entry, vptr: ValuePointer) {
+        let vptr_len = if vptr.is_empty() {
+            entry.meta_mut().remove(Meta::VALUE_POINTER);
+            None
+        } else {
+            entry.meta_mut().insert(Meta::VALUE_POINTER);
+            entry.set_value(vptr.serialize());
+            Some(vptr.len())
+        };
+        let key_ts = entry.key_ts().serialize();
+        self.builder.push(
+            &KeyTsBorrow::from(key_ts.as_slice()),
+            entry.value_meta(),
+            vptr_len,
+        );
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::fs::read_dir;
+
+    use bytes::Bytes;
+
+    use crate::{
+        db::{tests::test_config, DB},
+        errors::DBError,
+        pb::badgerpb4::Kv,
+    };
+
+    #[tokio::test]
+    async fn test_stream_writer() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config(dir.path());
+        config.memtable.set_memtable_size(1 << 20);
+        let db = DB::open(config).await?;
+        let key = |i: usize| Bytes::from(format!("key{:05}", i));
+        let value = |i: usize| match i % 100 {
+            // the large values are in the vlog.
+            0 => Bytes::from(vec![i as u8; 1 << 12]),
+            _ => Bytes::from(format!("{:0100}", i)),
+        };
+        let mut batch = db.new_write_batch();
+        for i in 0..5000 {
+            batch.set(key(i), value(i)).await?;
+        }
+        batch.flush().await?;
+        let mut batch = db.new_write_batch();
+        batch.delete(key(1)).await?;
+        batch.flush().await?;
+
+        let mut batches = Vec::new();
+        db.new_stream()
+            .set_num_go(4)
+            .set_key_to_list(|_, versions| Ok(versions))
+            .orchestrate(|kvs| {
+                batches.push(kvs);
+                async { Ok(()) }
+            })
+            .await?;
+        // the batch may be committed in several txns.
+        let max_version = batches.iter().flatten().map(|kv| kv.version).max().unwrap();
+        db.close().await?;
+
+        let dir = tempfile::tempdir()?;
+        let loaded = DB::open(test_config(dir.path())).await?;
+        let mut writer = loaded.new_stream_writer().await?;
+        for kvs in batches {
+            writer.write(kvs).await?;
+        }
+        writer.finish().await?;
+        let handler = loaded.level_controller.levels().last().unwrap();
+        assert!(!handler.read().await.tables.is_empty());
+        assert!(loaded.level_controller.levels()[0]
+            .read()
+            .await
+            .tables
+            .is_empty());
+        loaded.close().await?;
+
+        // the tables and the vlog are persisted.
+        let loaded = DB::open(test_config(dir.path())).await?;
+        let txn = loaded.new_read_txn().await?;
+        for i in (0..5000).filter(|i| *i != 1) {
+            let item = txn.get(key(i)).await?;
+            assert_eq!(item.value().await?, &value(i));
+            assert!(item.version() <= max_version);
+        }
+        let err = txn.get(key(1)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DBError::KeyNotFound)));
+        txn.discard().await?;
+
+        // the commits after it are newer than the loaded versions.
+        let mut txn = loaded.get_update_txn().await?;
+        txn.set(key(1), value(1)).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        let txn = loaded.new_read_txn().await?;
+        assert!(txn.get(key(1)).await?.version() > max_version);
+        txn.discard().await?;
+
+        // only for an empty db.
+        assert!(loaded.new_stream_writer().await.is_err());
+        loaded.close().await
+    }
+    #[tokio::test]
+    async fn test_stream_writer_drop() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let num_tables = || -> anyhow::Result<usize> {
+            let mut count = 0;
+            for entry in read_dir(dir.path())? {
+                if entry?.path().extension() == Some("sst".as_ref()) {
+                    count += 1;
+                }
+            }
+            Ok(count)
+        };
+        let kv = |stream_id: u32, key: &str, stream_done: bool| Kv {
+            key: key.into(),
+            value: vec![1u8; 1 << 12],
+            version: 1,
+            stream_id,
+            stream_done,
+            ..Default::default()
+        };
+
+        let mut writer = db.new_stream_writer().await?;
+        writer
+            .write(vec![kv(1, "a", false), kv(1, "", true), kv(2, "b", false)])
+            .await?;
+        assert_eq!(num_tables()?, 1);
+        drop(writer);
+        assert_eq!(num_tables()?, 0);
+
+        // the writes are unblocked, and the db is still empty.
+        let mut writer = db.new_stream_writer().await?;
+        writer.write(vec![kv(1, "c", false)]).await?;
+        writer.finish().await?;
+        assert_eq!(num_tables()?, 1);
+        db.close().await
+    }
+}
@@ -39,7 +39,7 @@ pub(crate) struct Arena {
 impl Arena {
     pub(crate) fn new(size: usize) -> Arena {
         let chunk_align = CHUNK_ALIGN;
-        let size = size as usize + 8;
+        let size = size + 8;
         let mut request_size = Self::round_up_to(size, chunk_align).unwrap();
         if request_size >= DEFAULT_PAGE_SIZE.to_owned() {
             request_size = Self::round_up_to(request_size, DEFAULT_PAGE_SIZE.to_owned()).unwrap();
@@ -65,12 +65,13 @@ impl Arena {
             end,
             layout,
         };
-        s.alloc(0 as u64);
+        s.alloc(0_u64);
         s
     }
     pub(crate) fn alloc<T>(&self, value: T) -> &mut T {
         self.alloc_with(|| value)
     }
+    #[allow(clippy::mut_from_ref)]
     pub(crate) fn alloc_with<F, T>(&self, f: F) -> &mut T
     where
         F: FnOnce() -> T,
@@ -90,6 +91,7 @@ impl Arena {
             &mut *dst
         }
     }
+    #[allow(clippy::mut_from_ref)]
     pub(crate) unsafe fn get_mut<T>(&self, offset: u32) -> Option<&mut T> {
         if offset < 8 {
             None
@@ -134,15 +136,14 @@ impl Arena {
         let old_ptr = unsafe {
             self.start.as_ptr().add(
                 self.ptr_offset
-                    .fetch_add(alloc_size, std::sync::atomic::Ordering::AcqRel)
-                    as usize,
+                    .fetch_add(alloc_size, std::sync::atomic::Ordering::AcqRel),
             )
         };
         debug_assert_eq!(old_ptr as usize % 8, 0);
         unsafe {
             let new_ptr = old_ptr.add(alloc_size);
             if new_ptr > end_ptr {
-                let new_total = new_ptr.sub_ptr(start_ptr);
+                let new_total = new_ptr.offset_from_unsigned(start_ptr);
                 panic!(
                     "Arena too small, toWrite:{}, newTotal:{}, limit:{}",
                     layout.size(),
@@ -254,7 +255,7 @@ mod tests {
         dbg!(&p);
         // let t = p as *const u8;
         let k = p as *const KeyTs;
-        let p = k as *const u8;
+        let _p = k as *const u8;
         let offset = arena.offset(k);
         dbg!(&offset);
         let p = unsafe { arena.get_mut::<KeyTs>(offset.unwrap()) }.unwrap();
@@ -288,7 +289,7 @@ mod tests {
             }));
         }
         for ele in handles {
-            ele.await;
+            ele.await.unwrap();
         }
     }
     #[test]
@@ -308,7 +309,7 @@ mod tests {
             }));
         }
         for ele in handles {
-            ele.join();
+            ele.join().unwrap();
         }
     }
 }
@@ -29,21 +29,21 @@ impl<'a> BloomBorrow<'a> {
         }
         let k = filter[filter.len() - 1];
         let bit_len = (filter.len() - 1) * 8;
-        let delta = hash >> 17 | hash << 15;
+        let delta = hash.rotate_left(15);
         for _ in 0..k {
             let bit_pos = hash as usize % bit_len;
             if filter[bit_pos / 8] & (1 << (bit_pos % 8)) == 0 {
                 return false;
             }
-            hash = unsafe { hash.unchecked_add(delta) };
+            hash = hash.wrapping_add(delta);
         }
-        return true;
+        true
     }
 }
 impl Bloom {
     pub(crate) fn new(key_hashes: &[u32], false_positive_rate: f64) -> Self {
         let bits_per_key = Self::bits_per_key(false_positive_rate);
-        let k = (-1.0 * false_positive_rate.ln()).max(1.0).min(30.0) as u32;
+        let k = (-false_positive_rate.ln()).clamp(1.0, 30.0) as u32;
         let mut bit_len = (key_hashes.len() * bits_per_key as usize).max(64);
         let byte_len = (bit_len as f64 / 8.0).ceil() as usize;
         bit_len = byte_len * 8;
@@ -53,16 +53,16 @@ impl Bloom {
         while cap < want {
             cap += cap / 4;
         }
-        let mut filter = Vec::with_capacity(cap as usize);
+        let mut filter = Vec::with_capacity(cap);
         filter.resize_with(want,||0u8);
 
         for hash in key_hashes {
             let mut hash = *hash;
-            let delta = hash >> 17 | hash << 15;
+            let delta = hash.rotate_left(15);
             (0..k).for_each(|_| {
                 let bit_pos = hash as usize % bit_len;
                 filter[bit_pos / 8] |= 1 << (bit_pos % 8);
-                hash = unsafe { hash.unchecked_add(delta) };
+                hash = hash.wrapping_add(delta);
             });
         }
         filter[byte_len] = k as u8;
@@ -70,49 +70,44 @@ impl Bloom {
     }
     #[inline]
     pub(crate) fn may_contain_key(&self, bytes: &[u8]) -> bool {
-        BloomBorrow(&self).may_contain(Self::hash(bytes))
+        BloomBorrow(self).may_contain(Self::hash(bytes))
     }
 
     #[inline]
-    pub(crate) fn may_contain(&self, mut hash: u32) -> bool {
-        BloomBorrow(&self).may_contain(hash)
+    pub(crate) fn may_contain(&self, hash: u32) -> bool {
+        BloomBorrow(self).may_contain(hash)
     }
     #[inline]
     pub(crate) fn bits_per_key(false_positive_rate: f64) -> u32 {
-        (-1.0 * false_positive_rate.log2()).ceil().max(0.0) as u32
+        (-false_positive_rate.log2()).ceil().max(0.0) as u32
     }
 
     pub(crate) fn hash(mut bytes: &[u8]) -> u32 {
         const SEED: u32 = 0xbc9f1d34;
         const M: u32 = 0xc6a4a793;
-        let mut hash = SEED ^ unsafe { (bytes.len() as u32).unchecked_mul(M) };
+        let mut hash = SEED ^ (bytes.len() as u32).wrapping_mul(M);
         while bytes.len() >= 4 {
-            hash = unsafe {
-                hash.unchecked_add(
-                    bytes[0] as u32
-                        | (bytes[1] as u32) << 8
-                        | (bytes[2] as u32) << 16
-                        | (bytes[3] as u32) << 24,
-                )
-            };
-            hash = unsafe { hash.unchecked_mul(M) };
+            hash = hash.wrapping_add(
+                bytes[0] as u32
+                    | (bytes[1] as u32) << 8
+                    | (bytes[2] as u32) << 16
+                    | (bytes[3] as u32) << 24,
+            );
+            hash = hash.wrapping_mul(M);
             hash ^= hash >> 16;
             bytes = &bytes[4..];
         }
         let len = bytes.len();
         if len == 3 {
-            hash = unsafe {
-                hash.unchecked_add(
-                    (bytes[0] as u32) | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16,
-                )
-            }
+            hash = hash
+                .wrapping_add((bytes[0] as u32) | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16)
         } else if len == 2 {
-            hash = unsafe { hash.unchecked_add((bytes[0] as u32) | (bytes[1] as u32) << 8) }
+            hash = hash.wrapping_add((bytes[0] as u32) | (bytes[1] as u32) << 8)
         } else if len == 1 {
-            hash = unsafe { hash.unchecked_add(bytes[0] as u32) }
+            hash = hash.wrapping_add(bytes[0] as u32)
         };
         if len != 0 {
-            hash = unsafe { hash.unchecked_mul(M) };
+            hash = hash.wrapping_mul(M);
             hash ^= hash >> 24;
         }
         hash
@@ -153,7 +148,7 @@ mod tests {
             if x < 1000 {
                 return x + 100;
             }
-            return x + 1000;
+            x + 1000
         };
         let mut medicore_filters = 0;
         let mut good_filters = 0;
@@ -76,7 +76,7 @@ impl BlockCacheConfig {
                 .build();
             return Ok(BlockCache { cache }.into());
         }
-        return Ok(None);
+        Ok(None)
     }
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -136,7 +136,7 @@ impl BlockCache {
         #[cfg(feature = "moka")]
         {
             self.cache.insert(key, block);
-            return true;
+            true
         }
     }
 }
@@ -202,7 +202,7 @@ impl IndexCacheConfig {
         let cache = moka::future::CacheBuilder::new(num_in_cache)
             .initial_capacity(num_in_cache as usize / 2)
             .build();
-        return Ok(IndexCache { cache }.into());
+        Ok(IndexCache { cache })
     }
 }
 impl IndexCache {
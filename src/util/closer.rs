@@ -1,4 +1,11 @@
-use std::{ops::Deref, sync::Arc};
+use std::{
+    future::Future,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use tokio::sync::{futures::Notified, Notify, Semaphore};
 #[derive(Debug, Clone)]
@@ -41,7 +48,7 @@ impl CloseNotify {
     pub(crate) fn notify(&self) {
         self.0.notify_one();
     }
-    pub(crate) fn notified(&self) -> Notified {
+    pub(crate) fn notified(&self) -> Notified<'_> {
         self.0.notified()
     }
 }
@@ -51,6 +58,7 @@ pub(crate) struct Closer(Arc<CloserInner>);
 struct CloserInner {
     wait_group: WaitGroupInner,
     notify: Notify,
+    signaled: AtomicBool,
 }
 impl Closer {
     pub(crate) fn new(count: u32) -> Self {
@@ -61,17 +69,26 @@ impl Closer {
                     count,
                 },
                 notify: Notify::new(),
+                signaled: AtomicBool::new(false),
             }
             .into(),
         )
     }
 
+    /// Wakes all the tasks waiting on captured, including the ones which call captured later.
     pub(crate) fn signal(&self) {
-        self.0.notify.notify_one();
+        self.0.signaled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
     }
 
-    pub(crate) fn captured(&self) -> Notified<'_> {
-        self.0.notify.notified()
+    pub(crate) fn captured(&self) -> impl Future<Output = ()> + '_ {
+        // register before checking the flag, so that a signal in between is not missed.
+        let notified = self.0.notify.notified();
+        async move {
+            if !self.0.signaled.load(Ordering::SeqCst) {
+                notified.await;
+            }
+        }
     }
 }
 impl Deref for Closer {
@@ -18,6 +18,7 @@ pub(crate) struct DBLockGuard {
     lock_guards: Vec<DirLockGuard>,
 }
 #[derive(Debug, Clone)]
+#[derive(Default)]
 pub struct DBLockGuardConfig {
     dirs: HashSet<PathBuf>,
     // BypassLockGuard will bypass the lock guard on badger. Bypassing lock
@@ -26,15 +27,6 @@ pub struct DBLockGuardConfig {
     bypass_lock_guard: bool,
     read_only: bool,
 }
-impl Default for DBLockGuardConfig {
-    fn default() -> Self {
-        Self {
-            dirs: Default::default(),
-            bypass_lock_guard: false,
-            read_only: false,
-        }
-    }
-}
 impl Deref for DBLockGuardConfig {
     type Target = HashSet<PathBuf>;
 
@@ -75,7 +67,6 @@ pub(crate) struct DirLockGuard {
     read_only: bool,
 }
 ///we need lock on dir , but rust std::fs cannot provide dir's fd for 'flock',so use libc;
-
 impl DirLockGuard {
     pub(crate) fn acquire_lock(
         dir: &PathBuf,
@@ -105,6 +96,7 @@ impl DirLockGuard {
             let mut pid_f = OpenOptions::new()
                 .create(true)
                 .write(true)
+                .truncate(false)
                 .open(&abs_pid_path)
                 .map_err(|e| anyhow!("cannot open pid lock file {:?} : {}", abs_pid_path, e))?;
             pid_f
@@ -63,7 +63,7 @@ impl<F: DBFileId> LogFile<F> {
                     log_file.set_size(VLOG_HEADER_SIZE);
                 }
                 Err(e) => {
-                    match remove_file(&log_file.path()) {
+                    match remove_file(log_file.path()) {
                         Ok(_) => {
                             bail!("Cannot logfile.boostrap {:?} for {}", &log_file.path(), e);
                         }
@@ -148,7 +148,7 @@ impl<F: DBFileId> LogFile<F> {
     pub(crate) fn try_decrypt(&self, ciphertext: &[u8], offset: usize) -> Option<Vec<u8>> {
         if let Some(c) = &self.cipher {
             let nonce = self.generate_nonce(offset);
-            return c.decrypt_with_slice(nonce.as_slice(), ciphertext);
+            c.decrypt_with_slice(nonce.as_slice(), ciphertext)
         } else {
             None
         }
@@ -157,7 +157,7 @@ impl<F: DBFileId> LogFile<F> {
     pub(crate) fn try_encrypt(&self, plaintext: &[u8], offset: usize) -> Option<Vec<u8>> {
         if let Some(c) = &self.cipher {
             let nonce = self.generate_nonce(offset);
-            return c.encrypt_with_slice(nonce.as_slice(), plaintext);
+            c.encrypt_with_slice(nonce.as_slice(), plaintext)
         } else {
             None
         }
@@ -4,7 +4,7 @@ use parking_lot::RwLock;
 use std::{
     collections::{BTreeMap, HashMap},
     fs::{metadata, read_dir},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicUsize, atomic::Ordering, Arc},
 };
 use tokio::{select, sync::Semaphore};
@@ -35,9 +35,9 @@ lazy_static! {
 }
 
 #[inline]
-pub(crate) async fn set_lsm_size(k: &PathBuf, v: u64) {
+pub(crate) async fn set_lsm_size(k: &Path, v: u64) {
     let mut lsm_size_w = LSM_SIZE.write();
-    lsm_size_w.insert(k.clone(), v);
+    lsm_size_w.insert(k.to_path_buf(), v);
     drop(lsm_size_w)
 }
 #[inline]
@@ -47,9 +47,9 @@ pub(crate) async fn set_pending_writes(dir: PathBuf, req_len: Arc<AtomicUsize>)
     drop(pending_writes_w);
 }
 #[inline]
-pub(crate) async fn set_vlog_size(k: &PathBuf, v: u64) {
+pub(crate) async fn set_vlog_size(k: &Path, v: u64) {
     let mut vlog_size_w = VLOG_SIZE.write();
-    vlog_size_w.insert(k.clone(), v);
+    vlog_size_w.insert(k.to_path_buf(), v);
     drop(vlog_size_w)
 }
 #[inline]
@@ -76,7 +76,7 @@ pub(crate) fn sub_num_compaction_tables(val: usize) {
 }
 #[inline]
 pub(crate) fn add_num_bytes_compaction_written(level: Level,val: usize){
-    let mut written = NUM_BYTES_COMPACTION_WRITTEN.lock();;
+    let mut written = NUM_BYTES_COMPACTION_WRITTEN.lock();
     if let Some(v) = written.get_mut(&level) {
         *v+=val;
     }else {
@@ -139,7 +139,7 @@ pub(crate) fn add_num_lsm_gets(level: Level, val: usize) {
 
 #[inline]
 pub(crate) async fn calculate_size(level_dir: &PathBuf, vlog_dir: &PathBuf) {
-    let (lsm_size, mut vlog_size) = match total_size(&level_dir) {
+    let (lsm_size, mut vlog_size) = match total_size(level_dir) {
         Ok(r) => r,
         Err(e) => {
             debug!("Cannot calculate_size {:?} for {}", level_dir, e);
@@ -147,7 +147,7 @@ pub(crate) async fn calculate_size(level_dir: &PathBuf, vlog_dir: &PathBuf) {
         }
     };
     #[cfg(feature = "metrics")]
-    set_lsm_size(&level_dir, lsm_size).await;
+    set_lsm_size(level_dir, lsm_size).await;
     if vlog_dir != level_dir {
         match total_size(vlog_dir) {
             Ok((_, v)) => {
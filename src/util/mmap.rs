@@ -67,7 +67,13 @@ impl MmapFile {
             fd.set_len(max_file_size as u64)?;
             is_new_file = true;
         }
-        let mmap_raw = memmap2::MmapRaw::map_raw(&fd)?;
+        // the files of a read only db are opened without write.
+        let mmap_raw = match memmap2::MmapRaw::map_raw(&fd) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                memmap2::MmapOptions::new().map_raw_read_only(&fd)?
+            }
+            r => r?,
+        };
         let mmap_file = MmapFile {
             w_pos: 0,
             w_buf: Vec::with_capacity(DEFAULT_PAGE_SIZE.to_owned()),
@@ -147,7 +153,7 @@ impl MmapFile {
         self.flush()?;
         self.raw_sync()?;
         self.munmap()?;
-        self.fd.set_len(max_sz as u64)?;
+        self.fd.set_len(max_sz)?;
         Ok(())
     }
 
@@ -200,27 +206,17 @@ impl MmapFile {
     }
 
     #[tracing::instrument]
-    #[cfg(not(any(target_os = "linux", target_os = "android")))]
     pub(crate) fn set_len(&mut self, size: usize) -> io::Result<()> {
-        self.raw.flush_range(0, size)?;
-        self.munmap()?;
+        self.raw.flush_range(0, size.min(self.raw.len()))?;
         self.fd.set_len(size as u64)?;
+        // the old mapping is unmapped when it's dropped.
         self.raw = memmap2::MmapRaw::map_raw(&self.fd)?;
         Ok(())
     }
 
-    #[tracing::instrument]
-    #[cfg(any(target_os = "linux", target_os = "android"))]
-    pub(crate) fn set_len(&mut self, size: usize) -> io::Result<()> {
-        self.flush(0, size);
-        self.file_handle.set_len(0)?;
-        let opt = RemapOptions::new();
-        self.mmap.remap(size, opt)?;
-    }
-
     #[tracing::instrument]
     fn check_len_satisfied(&mut self, buf_len: usize) -> io::Result<()> {
-        let write_at = self.w_pos as usize;
+        let write_at = self.w_pos;
         let new_write_at = write_at + buf_len;
         if new_write_at >= self.raw.len() {
             let align = new_write_at % DEFAULT_PAGE_SIZE.to_owned();
@@ -241,9 +237,7 @@ impl MmapFile {
             self.panicked = true;
             let r = self.check_len_satisfied(buf.len());
             self.panicked = false;
-            if let Err(e) = r {
-                return Err(e);
-            }
+            r?;
             // self.as_mut()[self.w_pos as usize..].copy_from_slice(&buf);
             unsafe {
                 Self::raw_write(&self.raw, self.w_pos, buf)?;
@@ -256,8 +250,9 @@ impl MmapFile {
         Ok(buf.len())
     }
 
+    /// Copy the buffered writes into mmap, without syncing them to disk.
     #[tracing::instrument]
-    fn flush_buf(&mut self) -> io::Result<()> {
+    pub(crate) fn flush_buf(&mut self) -> io::Result<()> {
         self.panicked = true;
         let r = self.check_len_satisfied(self.w_buf.len());
         self.panicked = false;
@@ -340,7 +335,7 @@ impl Write for MmapFile {
         } else {
             return Ok(());
         };
-        self.raw.flush_range(offset as usize, len as usize)?;
+        self.raw.flush_range(offset, len)?;
         self.last_flush_pos = self.w_pos;
         Ok(())
     }
@@ -369,7 +364,7 @@ impl MmapFile {
     pub(crate) fn write_slice(&mut self, mut offset: usize, mut data: &[u8]) -> io::Result<()> {
         let buf_offset = self.w_pos + self.w_buf.len();
         if offset == buf_offset {
-            self.write(data)?;
+            self.write_all(data)?;
         } else if offset > buf_offset || data.len() >= self.w_buf.capacity() {
             self.panicked = true;
             let r = self.check_len_satisfied(data.len());
@@ -20,7 +20,6 @@ use tokio::sync::mpsc::Sender;
 
 use std::cmp::Ordering;
 use std::fmt::Debug;
-use std::fmt::Display;
 use std::future::Future;
 use std::path::Path;
 use std::{collections::HashSet, fs::read_dir, path::PathBuf, sync::Arc};
@@ -65,9 +64,9 @@ impl From<u32> for MemTableId {
         Self(value)
     }
 }
-impl Into<u32> for MemTableId {
-    fn into(self) -> u32 {
-        self.0
+impl From<MemTableId> for u32 {
+    fn from(val: MemTableId) -> Self {
+        val.0
     }
 }
 impl DBFileId for MemTableId {
@@ -80,9 +79,9 @@ impl From<u32> for SSTableId {
         Self(value)
     }
 }
-impl Into<u32> for SSTableId {
-    fn into(self) -> u32 {
-        self.0
+impl From<SSTableId> for u32 {
+    fn from(val: SSTableId) -> Self {
+        val.0
     }
 }
 impl DBFileId for SSTableId {
@@ -93,18 +92,16 @@ impl SSTableId {
         let mut id_set = HashSet::new();
         let dir = dir.as_ref();
         if let Ok(read_dir) = read_dir(dir) {
-            for ele in read_dir {
-                if let Ok(entry) = ele {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Ok(id) = Self::parse(path) {
-                            id_set.insert(id);
-                        };
-                    }
+            for entry in read_dir.flatten() {
+                let path = entry.path();
+                if path.is_file() {
+                    if let Ok(id) = Self::parse(path) {
+                        id_set.insert(id);
+                    };
                 }
             }
         };
-        return id_set;
+        id_set
     }
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -114,9 +111,9 @@ impl From<u32> for VlogId {
         Self(value)
     }
 }
-impl Into<u32> for VlogId {
-    fn into(self) -> u32 {
-        self.0
+impl From<VlogId> for u32 {
+    fn from(val: VlogId) -> Self {
+        val.0
     }
 }
 impl DBFileId for VlogId {
@@ -232,14 +229,14 @@ where
             return Ok(mid);
         }
     }
-    return Err(left);
+    Err(left)
 }
 #[test]
 fn test_search() {
     assert_eq!(search(5, |n| { n.cmp(&2) }), Ok(2));
     assert_eq!(search(10, |n| { n.cmp(&11) }), Err(10));
     assert_eq!(search(5, |n| { (n as isize).cmp(&-1) }), Err(0));
-    let v = vec![1, 3, 5];
+    let v = [1, 3, 5];
     assert_eq!(search(3, |n| { v[n].cmp(&2) }), Err(1));
     assert_eq!(search(3, |n| { v[n].cmp(&3) }), Ok(1));
     assert_eq!(search(3, |n| { v[n].cmp(&5) }), Ok(2));
@@ -1,36 +1,35 @@
 use std::{
     collections::HashMap,
     pin::Pin,
-    sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    },
+    sync::Arc,
+    task::{Context, Poll},
 };
 
 use anyhow::bail;
-use log::error;
+use futures::Stream;
+use log::{error, warn};
+use parking_lot::Mutex;
 use tokio::{
     select,
-    sync::{
-        mpsc::{self, error::SendError, Receiver, Sender},
-        Mutex,
-    },
+    sync::mpsc::{self, error::TrySendError, Receiver, Sender},
 };
 
 use crate::{
     db::DB,
+    errors::DBError,
+    kv::Meta,
     pb::badgerpb4::{Kv, Match},
+    txn::BADGER_PREFIX,
     util::closer::Closer,
     util::tire::{Trie, TrieError},
     write::WriteReq,
 };
+// the number of the update batches buffered for a subscription.
+pub(crate) const SUBSCRIPTION_CAPACITY: usize = 1000;
 #[derive(Debug)]
 struct Subscriber {
-    id: u64,
     matches: Vec<Match>,
     sender: Sender<Vec<Arc<Kv>>>,
-    closer: Closer,
-    active: AtomicU64,
 }
 #[derive(Debug, Clone)]
 pub(crate) struct Publisher(Arc<Mutex<PublisherInner>>);
@@ -55,27 +54,20 @@ impl PublisherInner {
             sender,
             subscribers,
             next_id: 0,
-            indexer: Trie::default().into(),
+            indexer: Trie::default(),
         }
     }
-    pub(crate) async fn cleanup_subscribers(&mut self) {
-        let mut subs: HashMap<u64, Arc<Subscriber>> = HashMap::new();
+    // drop the senders, so the subscriptions end after the buffered updates.
+    pub(crate) fn cleanup_subscribers(&mut self) {
         for (id, sub) in self.subscribers.drain() {
             for m in sub.matches.iter() {
                 if let Err(e) = self.indexer.delete_match(m, id) {
                     error!("{}", e);
                 };
             }
-            sub.closer.signal();
-            let r = sub.closer.wait().await;
-            if let Err(e) = r {
-                error!("{}", e);
-                subs.insert(id, sub.clone());
-            }
         }
-        self.subscribers = subs;
     }
-    pub(crate) async fn delete_subscribers(&mut self, id: u64) {
+    pub(crate) fn delete_subscribers(&mut self, id: u64) {
         if let Some(s) = self.subscribers.get(&id) {
             for m in s.matches.iter() {
                 if let Err(e) = self.indexer.delete_match(m, id) {
@@ -94,43 +86,47 @@ impl Publisher {
         s
     }
     pub(crate) async fn send_updates(&self, reqs_vec: Vec<WriteReq>) {
-        let s = self.0.lock().await;
-        let sub_len = s.subscribers.len();
+        // the listener takes the lock to publish, so don't hold it while the channel is full.
+        let (sub_len, sender) = {
+            let s = self.0.lock();
+            (s.subscribers.len(), s.sender.clone())
+        };
         if sub_len != 0 {
-            if let Err(e) = s.sender.send(reqs_vec).await {
+            if let Err(e) = sender.send(reqs_vec).await {
                 error!("{}", e);
             };
         }
-        drop(s);
     }
-    pub(crate) async fn subscribers_len(&self) -> usize {
-        let s = self.0.lock().await;
+    pub(crate) fn subscribers_len(&self) -> usize {
+        let s = self.0.lock();
         let sub_len = s.subscribers.len();
         drop(s);
         sub_len
     }
-    pub(crate) async fn publish_updates(
-        &self,
-        reqs_vec: Vec<Vec<WriteReq>>,
-    ) -> Result<(), SendError<Vec<Arc<Kv>>>> {
+    pub(crate) fn publish_updates(&self, reqs_vec: Vec<Vec<WriteReq>>) {
         let mut batch_updates = HashMap::<u64, Vec<Arc<Kv>>>::new();
-        let s = self.0.lock().await;
+        let mut s = self.0.lock();
 
-        for reqs in reqs_vec {
+        // the results of the reqs are sent when they are dropped, after the updates are sent.
+        for reqs in reqs_vec.iter() {
             for req in reqs {
                 for (dec_entry, _) in req.entries_vptrs() {
-                    let key_ts = dec_entry.key_ts().serialize();
-                    let ids = s.indexer.get(&key_ts);
-                    if ids.len() == 0 {
+                    if dec_entry.meta().contains(Meta::FIN_TXN)
+                        || dec_entry.key().starts_with(BADGER_PREFIX)
+                    {
+                        continue;
+                    }
+                    let ids = s.indexer.get(dec_entry.key());
+                    if ids.is_empty() {
                         continue;
                     }
                     let kv: Arc<Kv> = Kv {
                         key: dec_entry.key().to_vec(),
                         value: dec_entry.value().to_vec(),
-                        user_meta: vec![],
+                        user_meta: vec![dec_entry.user_meta()],
                         version: dec_entry.version().to_u64(),
                         expires_at: dec_entry.expires_at().to_u64(),
-                        meta: vec![dec_entry.user_meta()],
+                        meta: vec![dec_entry.meta().bits()],
                         stream_id: 0,
                         stream_done: false,
                     }
@@ -149,14 +145,21 @@ impl Publisher {
             }
         }
 
+        // never wait for a subscription, otherwise it stalls the commits.
+        let mut closed = Vec::new();
         for (id, kvs) in batch_updates.drain() {
-            if let Some(s) = s.subscribers.get(&id) {
-                if s.active.load(Ordering::SeqCst) == 1 {
-                    s.sender.send(kvs).await?;
+            if let Some(sub) = s.subscribers.get(&id) {
+                match sub.sender.try_send(kvs) {
+                    Ok(_) => {}
+                    Err(TrySendError::Full(_)) => {
+                        warn!("Subscription {} is lagging behind, closing it", id);
+                        closed.push(id);
+                    }
+                    Err(TrySendError::Closed(_)) => closed.push(id),
                 }
             }
         }
-        Ok(())
+        closed.into_iter().for_each(|id| s.delete_subscribers(id));
     }
     pub(crate) async fn listen_for_updates(
         self,
@@ -166,8 +169,8 @@ impl Publisher {
         loop {
             select! {
              _=closer.captured()=>{
-                let mut s = self.0.lock().await;
-                s.cleanup_subscribers().await;
+                let mut s = self.0.lock();
+                s.cleanup_subscribers();
                 drop(s);
                 closer.done();
                 return ;
@@ -177,86 +180,162 @@ impl Publisher {
                 if let Ok(s) = recv.try_recv() {
                     v.push(s);
                 }
-                if let Err(e) = self.publish_updates(v).await {
-                    error!("{}",e);
-                };
+                self.publish_updates(v);
              }
             }
         }
     }
 }
 impl Subscriber {
-    async fn new(
+    #[allow(clippy::new_ret_no_self, clippy::type_complexity)]
+    fn new(
         publisher: &Publisher,
-        closer: Closer,
         matches: Vec<Match>,
-    ) -> Result<(Arc<Subscriber>, Receiver<Vec<Arc<Kv>>>), TrieError> {
-        let (sender, receiver) = mpsc::channel::<Vec<Arc<Kv>>>(1000);
-        let mut publisher = publisher.0.lock().await;
+    ) -> Result<(u64, Receiver<Vec<Arc<Kv>>>), TrieError> {
+        let (sender, receiver) = mpsc::channel::<Vec<Arc<Kv>>>(SUBSCRIPTION_CAPACITY);
+        let mut publisher = publisher.0.lock();
         let id = publisher.next_id;
         publisher.next_id += 1;
-        let sub: Arc<Self> = Self {
-            sender,
-            active: AtomicU64::new(1),
-            id,
-            matches,
-            closer,
-        }
-        .into();
-        publisher.subscribers.insert(id, sub.clone());
+        let sub: Arc<Self> = Self { sender, matches }.into();
 
         for m in sub.matches.iter() {
-            publisher.indexer.push_match(m, id)?;
+            if let Err(e) = publisher.indexer.push_match(m, id) {
+                sub.matches.iter().for_each(|m| {
+                    let _ = publisher.indexer.delete_match(m, id);
+                });
+                return Err(e);
+            }
         }
-        Ok((sub, receiver))
+        publisher.subscribers.insert(id, sub);
+        Ok((id, receiver))
+    }
+}
+/// Stream of the updates of the keys matched by the subscription, the kvs of a batch are in the order of
+/// the writes. It ends when the db is closed, or after the buffered updates once it lags 1000 batches
+/// behind the writes, and unsubscribes when it is dropped.
+pub struct Subscription {
+    id: u64,
+    publisher: Publisher,
+    receiver: Receiver<Vec<Arc<Kv>>>,
+}
+impl Stream for Subscription {
+    type Item = Vec<Kv>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx).map(|kvs| {
+            kvs.map(|kvs| {
+                kvs.into_iter()
+                    .map(|kv| Arc::try_unwrap(kv).unwrap_or_else(|kv| kv.as_ref().clone()))
+                    .collect()
+            })
+        })
+    }
+}
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.publisher.0.lock().delete_subscribers(self.id);
     }
 }
-use std::future::Future;
 impl DB {
-    pub(crate) async fn subscribe_async<F>(&self, fun: F, matches: Vec<Match>) -> anyhow::Result<()>
-    where
-        F: Fn(Vec<Arc<Kv>>) -> Pin<Box<dyn Future<Output = anyhow::Result<()>>>>,
-    {
-        let closer = Closer::new(1);
-        let (subscriber, mut receiver) =
-            Subscriber::new(&self.publisher, closer.clone(), matches).await?;
+    /// Subscribes the updates of the keys matching any of the matches, see Match::ignore_bytes
+    /// for the bytes skipped when matching the prefix.
+    pub async fn subscribe(&self, matches: Vec<Match>) -> anyhow::Result<Subscription> {
+        if self.is_closed() {
+            bail!(DBError::DBClosed);
+        }
+        if matches.is_empty() {
+            bail!("Subscribe with no matches");
+        }
+        let (id, receiver) = Subscriber::new(&self.publisher, matches)?;
+        Ok(Subscription {
+            id,
+            publisher: self.publisher.clone(),
+            receiver,
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::StreamExt;
 
-        loop {
-            select! {
-                _=closer.captured()=>{
-                    let mut kvs=Vec::with_capacity(100);
-                    loop {
-                        if let Some(s) = receiver.try_recv().ok() {
-                            kvs.extend(s);
-                        }else{
-                            if kvs.len()>0{
-                                let res=fun(kvs).await;
-                                closer.done();
-                                return res;
-                            }else{
-                                return Ok(());
-                            }
-                        }
-                    }
-                },
-                Some(mut kvs)=receiver.recv()=>{
-                    loop {
-                        if let Some(s) = receiver.try_recv().ok() {
-                            kvs.extend(s);
-                        }else{
-                            if kvs.len()>0{
-                                if let Err(e) = fun(kvs).await {
-                                    closer.done();
-                                    subscriber.active.store(0, Ordering::SeqCst);
-                                    self.publisher.0.lock().await.delete_subscribers(subscriber.id).await;
-                                    bail!(e);
-                                };
-                            }
-                            break;
-                        }
-                    }
-                }
-            }
+    use crate::{
+        db::{tests::test_config, DB},
+        kv::Meta,
+        pb::badgerpb4::Match,
+    };
+
+    use super::SUBSCRIPTION_CAPACITY;
+
+    #[tokio::test]
+    async fn test_subscribe() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let new_match = |prefix: &str, ignore_bytes: &str| Match {
+            prefix: prefix.into(),
+            ignore_bytes: ignore_bytes.into(),
+        };
+        // the second byte is ignored.
+        let mut sub = db.subscribe(vec![new_match("a-c", "1")]).await?;
+        let other = db.subscribe(vec![new_match("b", "")]).await?;
+        assert_eq!(db.publisher.subscribers_len(), 2);
+
+        let big = Bytes::from(vec![7u8; 1 << 12]);
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("a1c"), big.clone()).await?;
+        txn.set(Bytes::from("abd"), Bytes::from("v")).await?;
+        txn.commit().await?;
+        txn.discard().await?;
+        drop(other);
+        assert_eq!(db.publisher.subscribers_len(), 1);
+        let mut txn = db.get_update_txn().await?;
+        txn.delete("a2c").await?;
+        txn.commit().await?;
+        txn.discard().await?;
+
+        let kvs = sub.next().await.unwrap();
+        assert_eq!(kvs.len(), 1);
+        assert_eq!(kvs[0].key, b"a1c");
+        assert_eq!(kvs[0].value, big.to_vec());
+        let kvs = sub.next().await.unwrap();
+        assert_eq!(kvs.len(), 1);
+        assert_eq!(kvs[0].key, b"a2c");
+        assert!(Meta::from_bits_retain(kvs[0].meta[0]).contains(Meta::DELETE));
+
+        db.close().await?;
+        assert!(sub.next().await.is_none());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_subscribe_lagging() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = DB::open(test_config(dir.path())).await?;
+        let mut sub = db
+            .subscribe(vec![Match {
+                prefix: "a".into(),
+                ignore_bytes: "".into(),
+            }])
+            .await?;
+
+        // the commits don't wait for the subscription which is not polled,
+        // the listener publishes at most two commits in a batch.
+        for i in 0..2 * SUBSCRIPTION_CAPACITY + 10 {
+            let mut txn = db.get_update_txn().await?;
+            txn.set(Bytes::from(format!("a{i}")), Bytes::from("v"))
+                .await?;
+            txn.commit().await?;
+            txn.discard().await?;
         }
+        // the results of the commits are sent after they are published.
+        assert_eq!(db.publisher.subscribers_len(), 0);
+        db.close().await?;
+
+        let mut batches = 0;
+        while let Some(kvs) = sub.next().await {
+            assert!(!kvs.is_empty());
+            batches += 1;
+        }
+        assert_eq!(batches, SUBSCRIPTION_CAPACITY);
+        Ok(())
     }
 }
@@ -52,10 +52,20 @@ where
 
     AsyncRayonHandle { rx }
 }
+lazy_static! {
+    static ref GLOBAL_RAYON_POOL_INITED: parking_lot::Mutex<bool> = parking_lot::Mutex::new(false);
+}
+/// the global pool can only be built once, so reopening the db (or opening another db) must skip it.
 #[deny(unused)]
 pub fn init_global_rayon_pool() -> Result<(), rayon::ThreadPoolBuildError> {
+    let mut inited = GLOBAL_RAYON_POOL_INITED.lock();
+    if *inited {
+        return Ok(());
+    }
     let cpus = num_cpus::get();
     rayon::ThreadPoolBuilder::new()
         .num_threads(cpus * 2)
-        .build_global()
+        .build_global()?;
+    *inited = true;
+    Ok(())
 }
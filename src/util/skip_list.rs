@@ -2,8 +2,8 @@ use rand::Rng;
 
 use crate::{
     iter::{
-        DoubleEndedSinkIter, DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekIter,
-        KvSinkIter, SinkIter, SinkIterator,
+        DoubleEndedSinkIter, DoubleEndedSinkIterator, KvDoubleEndedSinkIter, KvSeekBackIter,
+        KvSeekIter, KvSinkIter, SinkIter, SinkIterator,
     },
     kv::{KeyTsBorrow, ValueMeta},
     util::arena::Arena,
@@ -148,7 +148,7 @@ impl SkipListInner {
         let mut next = [std::ptr::null::<Node>(); SKL_MAX_HEIGHT + 1];
         prev[height] = self.head.as_ptr();
         for h in (0..height).rev() {
-            let (p, n) = self.find_splice_for_level(key.into(), prev[h + 1], h);
+            let (p, n) = self.find_splice_for_level(key, prev[h + 1], h);
             prev[h] = p;
             next[h] = n;
             if prev[h] == next[h] {
@@ -181,7 +181,7 @@ impl SkipListInner {
                     Some(prev_node) => prev_node,
                     None => {
                         assert!(h > 1);
-                        let (p, n) = self.find_splice_for_level(key.into(), self.head.as_ptr(), h);
+                        let (p, n) = self.find_splice_for_level(key, self.head.as_ptr(), h);
                         prev[h] = p;
                         next[h] = n;
                         assert_ne!(prev[h], next[h]);
@@ -207,7 +207,7 @@ impl SkipListInner {
                                     break;
                                 }
                                 Err(_) => {
-                                    let (p, n) = self.find_splice_for_level(key.into(), prev[0], 0);
+                                    let (p, n) = self.find_splice_for_level(key, prev[0], 0);
                                     if p == n {
                                         self.try_set_value(prev[0], value);
                                         return;
@@ -233,7 +233,7 @@ impl SkipListInner {
                         break;
                     }
                     Err(_) => {
-                        let (p, n) = self.find_splice_for_level(key.into(), prev_node as _, h);
+                        let (p, n) = self.find_splice_for_level(key, prev_node as _, h);
                         prev[h] = p;
                         next[h] = n;
                         if prev[h] == next[h] {
@@ -246,7 +246,7 @@ impl SkipListInner {
             }
         }
     }
-    fn find_splice_for_level<'a>(
+    fn find_splice_for_level(
         &self,
         key: &[u8],
         mut before_ptr: *const Node,
@@ -283,7 +283,7 @@ impl SkipListInner {
         {
             h += 1;
         }
-        return h;
+        h
     }
     fn try_set_value(&self, ptr: *const Node, value: &[u8]) {
         if let Some(node) = unsafe { ptr.as_ref() } {
@@ -375,24 +375,18 @@ impl SkipListInner {
         let head_ptr = node as *const _;
         let mut level = self.height() - 1;
         loop {
-            match node.next(&self.arena, level) {
-                Some(next) => {
-                    let next_key = next.get_key(&self.arena).unwrap();
-                    match (self.cmp)(key, next_key) {
-                        std::cmp::Ordering::Greater => {
-                            //node.key <next.key < key
-                            node = next;
-                            continue;
-                        }
-                        _ => {}
-                    }
+            if let Some(next) = node.next(&self.arena, level) {
+                let next_key = next.get_key(&self.arena).unwrap();
+                if (self.cmp)(key, next_key) == std::cmp::Ordering::Greater {
+                    //node.key <next.key < key
+                    node = next;
+                    continue;
                 }
-                None => {}
             }
             if level > 0 {
                 level -= 1;
             } else {
-                if head_ptr == node as *const _ {
+                if std::ptr::eq(head_ptr, node) {
                     return None;
                 } else {
                     return node.into();
@@ -402,6 +396,7 @@ impl SkipListInner {
     }
     fn find_last(&self) -> Option<&Node> {
         let mut node = unsafe { self.head.as_ref() };
+        let head_ptr = node as *const _;
         let mut level = self.height() - 1;
         loop {
             match node.next(&self.arena, level) {
@@ -411,6 +406,8 @@ impl SkipListInner {
                 None => {
                     if level > 0 {
                         level -= 1;
+                    } else if std::ptr::eq(head_ptr, node) {
+                        return None;
                     } else {
                         return node.into();
                     }
@@ -444,7 +441,16 @@ impl SkipList {
     }
     #[inline]
     pub(crate) fn iter(&self) -> SkipListIter<'_> {
-        SkipListIter::new(&self)
+        SkipListIter::new(self)
+    }
+    pub(crate) fn owned_iter(&self) -> SkipListOwnedIter {
+        let skip_list = self.clone();
+        // the SkipListInner is kept alive by skip_list, and iter will be dropped before skip_list.
+        let inner: &'static SkipListInner = unsafe { &*Arc::as_ptr(&skip_list.skip_list) };
+        SkipListOwnedIter {
+            iter: SkipListIter::new(inner),
+            _skip_list: skip_list,
+        }
     }
     #[inline]
     pub(crate) fn get(&self, key: &[u8], allow_near: bool) -> Option<&[u8]> {
@@ -488,7 +494,7 @@ impl<'a> SinkIter for SkipListIter<'a> {
 
     fn item(&self) -> Option<&Self::Item> {
         if let Some(node) = self.node {
-            if node as *const _ == self.inner.head.as_ptr() {
+            if std::ptr::eq(node, self.inner.head.as_ptr()) {
                 return None;
             };
         }
@@ -505,7 +511,7 @@ impl<'a> SinkIterator for SkipListIter<'a> {
         if let Some(now) = self.node {
             if let Some(new) = now.next(&self.inner.arena, 0) {
                 if let Some(back) = self.node_back {
-                    if new as *const _ == back as *const _ {
+                    if std::ptr::eq(new, back) {
                         return Ok(false);
                     }
                 }
@@ -521,7 +527,7 @@ impl<'a> DoubleEndedSinkIterator for SkipListIter<'a> {
         if let Some(now) = self.node_back {
             if let Some(new) = now.prev(&self.inner.arena) {
                 if let Some(node) = self.node {
-                    if new as *const _ == node as *const _ {
+                    if std::ptr::eq(new, node) {
                         return Ok(false);
                     }
                 }
@@ -530,7 +536,7 @@ impl<'a> DoubleEndedSinkIterator for SkipListIter<'a> {
             }
         } else {
             if let Some(last) = self.inner.find_last() {
-                if last as *const _ != self.inner.head.as_ptr() {
+                if !std::ptr::eq(last, self.inner.head.as_ptr()) {
                     self.node_back = last.into();
                     return Ok(true);
                 }
@@ -542,7 +548,7 @@ impl<'a> DoubleEndedSinkIterator for SkipListIter<'a> {
 impl<'a> KvSinkIter<ValueMeta> for SkipListIter<'a> {
     fn key(&self) -> Option<KeyTsBorrow<'_>> {
         if let Some(item) = self.item() {
-            item.get_key(&self.inner.arena).and_then(|x| Some(x.into()))
+            item.get_key(&self.inner.arena).map(|x| x.into())
         } else {
             None
         }
@@ -551,7 +557,7 @@ impl<'a> KvSinkIter<ValueMeta> for SkipListIter<'a> {
     fn value(&self) -> Option<ValueMeta> {
         if let Some(item) = self.item() {
             if let Some(data) = item.get_value(&self.inner.arena) {
-                return ValueMeta::deserialize(data).into();
+                return ValueMeta::deserialize(data);
             }
         }
         None
@@ -560,7 +566,7 @@ impl<'a> KvSinkIter<ValueMeta> for SkipListIter<'a> {
 impl<'a> KvDoubleEndedSinkIter<ValueMeta> for SkipListIter<'a> {
     fn key_back(&self) -> Option<KeyTsBorrow<'_>> {
         if let Some(item) = self.item_back() {
-            item.get_key(&self.inner.arena).and_then(|x| Some(x.into()))
+            item.get_key(&self.inner.arena).map(|x| x.into())
         } else {
             None
         }
@@ -569,7 +575,7 @@ impl<'a> KvDoubleEndedSinkIter<ValueMeta> for SkipListIter<'a> {
     fn value_back(&self) -> Option<ValueMeta> {
         if let Some(item) = self.item_back() {
             if let Some(data) = item.get_value(&self.inner.arena) {
-                return ValueMeta::deserialize(data).into();
+                return ValueMeta::deserialize(data);
             }
         }
         None
@@ -578,12 +584,69 @@ impl<'a> KvDoubleEndedSinkIter<ValueMeta> for SkipListIter<'a> {
 impl<'a> KvSeekIter for SkipListIter<'a> {
     fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
         let node = self.inner.find_or_near(k.as_ref(), true);
-        return Ok(if node.is_some() {
+        Ok(if node.is_some() {
             self.node = node;
             true
         } else {
             false
-        });
+        })
+    }
+}
+impl<'a> KvSeekBackIter for SkipListIter<'a> {
+    fn seek_back(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        let node = self
+            .inner
+            .find_or_near(k.as_ref(), false)
+            .or_else(|| self.inner.find_prev(k.as_ref()));
+        Ok(if node.is_some() {
+            self.node_back = node;
+            true
+        } else {
+            false
+        })
+    }
+}
+/// same as SkipListIter, but holds the skip_list, so it can outlive the memtable.
+pub(crate) struct SkipListOwnedIter {
+    iter: SkipListIter<'static>,
+    _skip_list: SkipList,
+}
+impl SinkIterator for SkipListOwnedIter {
+    fn next(&mut self) -> Result<bool, anyhow::Error> {
+        self.iter.next()
+    }
+}
+impl DoubleEndedSinkIterator for SkipListOwnedIter {
+    fn next_back(&mut self) -> Result<bool, anyhow::Error> {
+        self.iter.next_back()
+    }
+}
+impl KvSinkIter<ValueMeta> for SkipListOwnedIter {
+    fn key(&self) -> Option<KeyTsBorrow<'_>> {
+        self.iter.key()
+    }
+
+    fn value(&self) -> Option<ValueMeta> {
+        self.iter.value()
+    }
+}
+impl KvDoubleEndedSinkIter<ValueMeta> for SkipListOwnedIter {
+    fn key_back(&self) -> Option<KeyTsBorrow<'_>> {
+        self.iter.key_back()
+    }
+
+    fn value_back(&self) -> Option<ValueMeta> {
+        self.iter.value_back()
+    }
+}
+impl KvSeekIter for SkipListOwnedIter {
+    fn seek(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        self.iter.seek(k)
+    }
+}
+impl KvSeekBackIter for SkipListOwnedIter {
+    fn seek_back(&mut self, k: KeyTsBorrow<'_>) -> anyhow::Result<bool> {
+        self.iter.seek_back(k)
     }
 }
 #[cfg(test)]
@@ -616,7 +679,7 @@ mod tests {
         }
         let two_decimal = |a: u32, b: u32| {
             let ratio = (a as f32) / (b as f32);
-            (ratio * 100 as f32) as u32
+            (ratio * 100_f32) as u32
         };
 
         assert_eq!(
@@ -737,7 +800,7 @@ mod tests {
     impl Iterator for SkipListLevelIter {
         type Item = *const Node;
 
-        fn next<'a>(&'a mut self) -> Option<Self::Item> {
+        fn next(&mut self) -> Option<Self::Item> {
             if let Some(node) = unsafe { self.cursor.as_ref() } {
                 match node.tower[self.height].get_node(&self.skip_list.arena) {
                     Some(next) => {
@@ -1,6 +1,7 @@
 use std::{
     ffi::CString,
-    fs::File,
+    fs::{hard_link, remove_file, File, OpenOptions},
+    io::{copy, Read},
     os::fd::{AsRawFd, FromRawFd},
     path::PathBuf,
 };
@@ -9,15 +10,12 @@ use anyhow::anyhow;
 use anyhow::bail;
 pub(crate) fn open_with_libc(dir: &PathBuf, oflag: i32) -> anyhow::Result<File> {
     unsafe {
-        match CString::new(dir.to_string_lossy().as_bytes()) {
-            Ok(path) => {
-                let fd = libc::open(path.as_ptr(), oflag);
-                drop(path);
-                if fd != -1 {
-                    return Ok(File::from_raw_fd(fd));
-                }
+        if let Ok(path) = CString::new(dir.to_string_lossy().as_bytes()) {
+            let fd = libc::open(path.as_ptr(), oflag);
+            drop(path);
+            if fd != -1 {
+                return Ok(File::from_raw_fd(fd));
             }
-            Err(_) => {}
         };
     }
 
@@ -38,3 +36,31 @@ pub(crate) fn sync_dir(dir: &PathBuf) -> anyhow::Result<()> {
         .map_err(|e| anyhow!("cannot sync dir {:?} : {}", dir, e))?;
     Ok(())
 }
+// copy the first len bytes of src to the new file dst.
+pub(crate) fn copy_prefix(src: &PathBuf, dst: &PathBuf, len: usize) -> anyhow::Result<()> {
+    let mut reader = File::open(src)?.take(len as u64);
+    let mut writer = OpenOptions::new().write(true).create_new(true).open(dst)?;
+    copy(&mut reader, &mut writer)?;
+    writer.sync_all()?;
+    Ok(())
+}
+// a file hard linked next to dst, so that it's kept until the first len bytes are copied to dst.
+pub(crate) struct PinnedPrefix {
+    pinned: PathBuf,
+    dst: PathBuf,
+    len: usize,
+}
+impl PinnedPrefix {
+    pub(crate) fn new(src: &PathBuf, dst: PathBuf, len: usize) -> anyhow::Result<Self> {
+        let mut pinned = dst.clone().into_os_string();
+        pinned.push(".pinned");
+        let pinned = PathBuf::from(pinned);
+        hard_link(src, &pinned).map_err(|e| anyhow!("cannot link {:?} : {}", src, e))?;
+        Ok(Self { pinned, dst, len })
+    }
+    pub(crate) fn copy(self) -> anyhow::Result<()> {
+        copy_prefix(&self.pinned, &self.dst, self.len)?;
+        remove_file(&self.pinned)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,5 @@
 use std::{
     collections::{HashMap, HashSet},
-    mem::replace,
     num::ParseIntError,
 };
 
@@ -35,9 +34,11 @@ pub enum TrieError {
 }
 impl Trie {
     pub(crate) fn push(&mut self, prefix: &[u8], id: u64) -> Result<(), TrieError> {
-        let mut m = Match::default();
-        m.prefix = prefix.to_vec();
-        self.push_match(&mut m, id)
+        let m = Match {
+            prefix: prefix.to_vec(),
+            ..Default::default()
+        };
+        self.push_match(&m, id)
     }
     pub(crate) fn get(&self, key: &[u8]) -> HashSet<u64> {
         self.get_iter(&self.root, key)
@@ -51,13 +52,13 @@ impl Trie {
         self.fix(m, id, Operation::Set)
     }
     fn get_iter(&self, cur_node: &TrieNode, key: &[u8]) -> HashSet<u64> {
-        let mut out = cur_node.ids.iter().map(|x| *x).collect::<HashSet<u64>>();
-        if key.len() == 0 {
+        let mut out = cur_node.ids.iter().copied().collect::<HashSet<u64>>();
+        if key.is_empty() {
             return out;
         }
 
         if let Some(ignore) = cur_node.ignore.as_ref() {
-            out.extend(self.get_iter(&ignore, key));
+            out.extend(self.get_iter(ignore, &key[1..]));
         }
 
         if let Some(child) = cur_node.children.get(&key[0]) {
@@ -87,8 +88,8 @@ impl Trie {
         while ignore.len() < m.prefix.len() {
             ignore.push(false);
         }
-        for i in 0..m.prefix.len() {
-            if ignore[i] {
+        for (i, &ignored) in ignore.iter().enumerate().take(m.prefix.len()) {
+            if ignored {
                 if cur_node.ignore.is_none() {
                     match op {
                         Operation::Del => return Ok(()),
@@ -100,15 +101,13 @@ impl Trie {
                 cur_node = cur_node.ignore.as_mut().unwrap().as_mut();
             } else {
                 let byte = m.prefix[i];
-                if cur_node.children.get(&byte).is_none() {
-                    match op {
-                        Operation::Del => return Ok(()),
-                        Operation::Set => {
-                            cur_node.children.insert(byte, TrieNode::default());
-                        }
-                    }
-                }
-                cur_node = cur_node.children.get_mut(&byte).unwrap();
+                cur_node = match op {
+                    Operation::Del => match cur_node.children.get_mut(&byte) {
+                        Some(node) => node,
+                        None => return Ok(()),
+                    },
+                    Operation::Set => cur_node.children.entry(byte).or_default(),
+                };
             }
         }
         match op {
@@ -128,26 +127,26 @@ impl Trie {
     }
     fn parse_ignore_bytes(ignore: &str) -> Result<Vec<bool>, TrieError> {
         let mut out: Vec<bool> = Vec::new();
-        if ignore == "" {
+        if ignore.is_empty() {
             return Ok(out);
         }
         for each in ignore.trim().split(",") {
             let r = each.trim().split("-").map(|x| x.trim()).collect::<Vec<_>>();
-            if r.len() == 0 || r.len() > 2 {
+            if r.is_empty() || r.len() > 2 {
                 return Err(TrieError::InvalidRange(each.to_string()));
             }
             let start = r[0].parse::<usize>().map_err(TrieError::from)?;
             while out.len() <= start {
                 out.push(false);
             }
-            out[start as usize] = true;
+            out[start] = true;
             if r.len() == 2 {
                 let end = r[1].parse::<usize>().map_err(TrieError::from)?;
                 while out.len() <= end {
                     out.push(false);
                 }
-                for i in start..=end {
-                    out[i as usize] = true;
+                for ignored in &mut out[start..=end] {
+                    *ignored = true;
                 }
             }
         }
@@ -160,6 +159,7 @@ mod tests {
     use std::collections::HashSet;
 
     use super::{Trie, TrieError};
+    use crate::pb::badgerpb4::Match;
 
     #[test]
     fn test_parse_ignore_bytes() -> Result<(), TrieError> {
@@ -197,6 +197,21 @@ mod tests {
         Ok(())
     }
     #[test]
+    fn test_get_ignore() -> Result<(), TrieError> {
+        let mut trie = Trie::default();
+        let m = Match {
+            prefix: b"a-c".to_vec(),
+            ignore_bytes: "1".into(),
+        };
+        trie.push_match(&m, 1)?;
+
+        assert_eq!(trie.get(b"abcd"), HashSet::from([1]));
+        assert_eq!(trie.get(b"a-c"), HashSet::from([1]));
+        assert!(trie.get(b"abd").is_empty());
+        assert!(trie.get(b"ac").is_empty());
+        Ok(())
+    }
+    #[test]
     fn test_hash() {
         let mut a = HashSet::new();
         let mut b = HashSet::new();
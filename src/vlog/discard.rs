@@ -1,7 +1,7 @@
 use crate::util::{mmap::MmapFile, search};
 use bytes::Buf;
 use log::info;
-use std::{fs::OpenOptions, path::PathBuf, sync::Arc};
+use std::{fs::OpenOptions, path::Path, sync::Arc};
 use tokio::sync::Mutex;
 const DISCARD_FILE_NAME: &str = "DISCARD";
 const DISCARD_FILE_SIZE: usize = 1 << 20; //1MB
@@ -14,7 +14,7 @@ struct DiscardStatsInner {
     next_empty_slot: usize,
 }
 impl DiscardStats {
-    pub(crate) fn new(vlog_dir: &PathBuf) -> anyhow::Result<Self> {
+    pub(crate) fn new(vlog_dir: &Path) -> anyhow::Result<Self> {
         Ok(Self(Arc::new(Mutex::new(DiscardStatsInner::new(
             vlog_dir,
         )?))))
@@ -45,6 +45,7 @@ impl DiscardStats {
                         let index = inner.next_empty_slot;
                         inner.set(index * 16, fid)?;
                         inner.set(index * 16 + 8, discard as u64)?;
+                        inner.next_empty_slot += 1;
 
                         while inner.next_empty_slot >= inner.mmap_f.len() / 16 {
                             let len = inner.mmap_f.len();
@@ -59,7 +60,7 @@ impl DiscardStats {
     }
 }
 impl DiscardStatsInner {
-    fn new(vlog_dir: &PathBuf) -> anyhow::Result<Self> {
+    fn new(vlog_dir: &Path) -> anyhow::Result<Self> {
         let file_path = vlog_dir.join(DISCARD_FILE_NAME);
         let mut fp_open_opt = OpenOptions::new();
         fp_open_opt.read(true).write(true).create(true);
@@ -90,8 +91,8 @@ impl DiscardStatsInner {
     }
     #[inline(always)]
     pub(crate) fn set(&mut self, offset: usize, val: u64) -> anyhow::Result<()> {
-        let big_endian = val.to_be_bytes();
-        self.mmap_f.write_slice(offset, big_endian.as_slice())?;
+        // written into mmap directly, so it's seen by get.
+        self.mmap_f.as_mut()[offset..offset + 8].copy_from_slice(&val.to_be_bytes());
         Ok(())
     }
     #[inline(always)]
@@ -104,7 +105,7 @@ impl DiscardStatsInner {
     pub(crate) fn sort(&mut self) {
         let slice = &mut self.mmap_f.as_mut()[..self.next_empty_slot * 8 * 2];
         let chunks = unsafe { slice.as_chunks_unchecked_mut::<16>() };
-        chunks.sort_unstable_by(|a, b| a.as_ref().get_u64().cmp(&b.as_ref().get_u64()));
+        chunks.sort_unstable_by_key(|a| a.as_ref().get_u64());
     }
     #[inline]
     pub(crate) fn max_slot(&self) -> usize {
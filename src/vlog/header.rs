@@ -61,11 +61,10 @@ impl VlogEntryHeader {
         (e, index)
     }
     pub(super) fn decode_from<R: Read>(reader: &mut R) -> std::io::Result<Self> {
-        let meta: u8 = 0;
-        reader.read_exact(&mut [meta])?;
-        let meta = Meta::from_bits_retain(meta);
-        let user_meta: u8 = 0;
-        reader.read_exact(&mut [user_meta])?;
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)?;
+        let meta = Meta::from_bits_retain(buf[0]);
+        let user_meta = buf[1];
 
         let key_len = reader.read_varint::<u32>()?;
         let value_len = reader.read_varint::<u32>()?;
@@ -100,3 +99,26 @@ impl VlogEntryHeader {
         self.expires_at
     }
 }
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::kv::{Entry, Meta};
+
+    use super::VlogEntryHeader;
+
+    #[test]
+    fn test_decode_from() -> std::io::Result<()> {
+        let mut e = Entry::new(Bytes::from("key"), Bytes::from("value"));
+        e.set_expires_at(123456789);
+        e.set_user_meta(7);
+        e.set_meta(Meta::TXN);
+        let buf = VlogEntryHeader::new(&e).encode();
+        let header = VlogEntryHeader::decode_from(&mut buf.as_slice())?;
+        assert_eq!(header.meta(), Meta::TXN);
+        assert_eq!(header.user_meta(), 7);
+        assert_eq!(header.expires_at(), 123456789.into());
+        assert_eq!(header.value_len(), 5);
+        Ok(())
+    }
+}
@@ -63,7 +63,7 @@ impl Debug for Histogram {
         for p in &PS {
             let res = self.percentile(*p).round();
             let line = format!("({} -> {}) ", p, res);
-            f.write_str(&*line)?;
+            f.write_str(&line)?;
         }
 
         f.write_str("]")
@@ -99,7 +99,7 @@ impl Histogram {
             let count = self.count.load(Ordering::Acquire);
 
             if count == 0 {
-                return std::f64::NAN;
+                return f64::NAN;
             }
 
             let mut target = count as f64 * (p / 100.);
@@ -119,7 +119,7 @@ impl Histogram {
             }
         }
 
-        std::f64::NAN
+        f64::NAN
     }
 
     /// Dump out some common percentiles.
@@ -154,7 +154,7 @@ fn compress<T: Into<f64>>(value: T) -> u16 {
     let boosted = 1. + abs;
     let ln = boosted.ln();
     let compressed = PRECISION * ln + 0.5;
-    assert!(compressed <= std::u16::MAX as f64);
+    assert!(compressed <= u16::MAX as f64);
     compressed as u16
 }
 
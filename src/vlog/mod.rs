@@ -1,6 +1,7 @@
 use std::{
     collections::{BTreeMap, HashSet},
-    fs::{read_dir, OpenOptions},
+    fs::{hard_link, read_dir, remove_file, OpenOptions},
+    mem::take,
     path::PathBuf,
     sync::{
         atomic::{AtomicI32, AtomicU32, AtomicUsize, Ordering},
@@ -18,7 +19,7 @@ use crate::{
     default::DEFAULT_VALUE_DIR,
     errors::{err_file, DBError},
     key_registry::KeyRegistry,
-    util::{log_file::LogFile, DBFileId, VlogId},
+    util::{log_file::LogFile, sys::PinnedPrefix, DBFileId, VlogId},
     vlog::read::LogFileIter,
 };
 
@@ -64,7 +65,7 @@ impl Default for ValueLogConfig {
         Self {
             read_only: false,
             value_dir: PathBuf::from(DEFAULT_VALUE_DIR),
-            vlog_file_size: 1 << 30 - 1,
+            vlog_file_size: 1 << (30 - 1),
             sync_writes: false,
             vlog_max_entries: 1_000_000,
         }
@@ -153,7 +154,7 @@ impl ValueLog {
         let mut last_logfile_w = last_logfile.write().await;
         let mut last_log_file_iter = LogFileIter::new(&last_logfile_w, VLOG_HEADER_SIZE);
         loop {
-            if let Some(_) = last_log_file_iter.next()? {
+            if last_log_file_iter.next()?.is_some() {
                 continue;
             };
             break;
@@ -193,11 +194,12 @@ impl ValueLog {
                 .map_err(|e| anyhow!("Open existing file: {:?} for {}", path, e))?;
 
                 // delete empty fid
-                if log_file.get_size() == VLOG_HEADER_SIZE {
+                if log_file.get_size() == VLOG_HEADER_SIZE && !read_only {
                     info!("Deleting empty file: {:?}", path);
                     log_file.delete().map_err(|e| {
                         anyhow!("While trying to delete empty file: {:?} for {}", &path, e)
                     })?;
+                    continue;
                 }
                 fid_logfile_w.insert(fid, Arc::new(RwLock::new(log_file)));
                 self.max_fid.fetch_max(fid.into(), Ordering::SeqCst);
@@ -231,6 +233,113 @@ impl ValueLog {
         Ok(new_logfile)
     }
 
+    /// Delete all the vlog files and start a new one, the writes must be blocked by the caller.
+    pub(crate) async fn drop_all(&self) -> anyhow::Result<usize> {
+        let mut fid_logfile_w = self.fid_logfile.write().await;
+        let log_files = take(&mut *fid_logfile_w);
+        drop(fid_logfile_w);
+        for log_file in log_files.values() {
+            let log_file_r = log_file.read().await;
+            remove_file(log_file_r.path())
+                .map_err(|e| err_file(e, log_file_r.path(), "Unable to delete vlog file"))?;
+        }
+        self.create_vlog_file().await?;
+        Ok(log_files.len())
+    }
+
+    /// Delete the vlog files whose values are all discarded except the latest one,
+    /// the writes must be blocked by the caller.
+    pub(crate) async fn delete_discarded(&self) -> anyhow::Result<usize> {
+        let max_fid: VlogId = self.max_fid.load(Ordering::SeqCst).into();
+        let fid_logfile_r = self.fid_logfile.read().await;
+        let mut deleted = Vec::new();
+        for (fid, log_file) in fid_logfile_r.iter().filter(|(fid, _)| **fid != max_fid) {
+            let fid: u32 = (*fid).into();
+            let discard = self.discard_stats.update(fid as u64, 0).await?;
+            let size = log_file.read().await.get_size();
+            if discard > 0 && discard as usize >= size.saturating_sub(VLOG_HEADER_SIZE) {
+                deleted.push(fid);
+            }
+        }
+        drop(fid_logfile_r);
+
+        let mut fid_logfile_w = self.fid_logfile.write().await;
+        let log_files = deleted
+            .into_iter()
+            .filter_map(|fid| fid_logfile_w.remove(&fid.into()).map(|f| (fid, f)))
+            .collect::<Vec<_>>();
+        drop(fid_logfile_w);
+        for (fid, log_file) in log_files.iter() {
+            let log_file_r = log_file.read().await;
+            remove_file(log_file_r.path())
+                .map_err(|e| err_file(e, log_file_r.path(), "Unable to delete vlog file"))?;
+            // reset the discard stats of it.
+            self.discard_stats.update(*fid as u64, -1).await?;
+        }
+        Ok(log_files.len())
+    }
+
+    /// Rotate to a new vlog file unless the latest one is empty, the writes must be blocked by the caller.
+    pub(crate) async fn rotate(&self) -> anyhow::Result<()> {
+        let latest = self.get_latest_logfile().await?;
+        let mut latest_w = latest.write().await;
+        let w_offset = self.writable_log_offset();
+        if w_offset == VLOG_HEADER_SIZE {
+            return Ok(());
+        }
+        latest_w.raw_sync()?;
+        latest_w.truncate(w_offset)?;
+        self.create_vlog_file().await?;
+        drop(latest_w);
+        Ok(())
+    }
+
+    /// Hard link the vlog files into dir, the latest one is pinned to be copied up to the written offset,
+    /// the writes must be blocked by the caller.
+    pub(crate) async fn checkpoint(&self, dir: &PathBuf) -> anyhow::Result<Option<PinnedPrefix>> {
+        let max_fid: VlogId = self.max_fid.load(Ordering::SeqCst).into();
+        let fid_logfile_r = self.fid_logfile.read().await;
+        let mut latest = None;
+        for (fid, log_file) in fid_logfile_r.iter() {
+            let log_file_r = log_file.read().await;
+            let target = fid.join_dir(dir);
+            if *fid == max_fid {
+                latest = Some(PinnedPrefix::new(
+                    log_file_r.path(),
+                    target,
+                    self.writable_log_offset(),
+                )?);
+            } else {
+                hard_link(log_file_r.path(), &target)
+                    .map_err(|e| err_file(e, log_file_r.path(), "Unable to link vlog file"))?;
+            }
+        }
+        Ok(latest)
+    }
+
+    // sync the latest vlog file, the older ones are synced when they are rotated.
+    pub(crate) async fn sync(&self) -> anyhow::Result<()> {
+        if self.config.read_only {
+            return Ok(());
+        }
+        let latest = self.get_latest_logfile().await?;
+        let latest_r = latest.read().await;
+        latest_r.raw_sync()?;
+        Ok(())
+    }
+
+    // sync the latest vlog file and truncate it to the written size, the writes must be stopped by the caller.
+    pub(crate) async fn close(&self) -> anyhow::Result<()> {
+        if self.config.read_only {
+            return Ok(());
+        }
+        let latest = self.get_latest_logfile().await?;
+        let mut latest_w = latest.write().await;
+        latest_w.raw_sync()?;
+        latest_w.truncate(self.writable_log_offset())?;
+        Ok(())
+    }
+
     #[inline]
     pub(crate) async fn get_latest_logfile(&self) -> anyhow::Result<Arc<RwLock<LogFile<VlogId>>>> {
         let p = self.fid_logfile.read().await;
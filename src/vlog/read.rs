@@ -1,16 +1,17 @@
 use std::{
     hash::Hasher,
     io::{self, BufRead, BufReader, Read},
+    mem,
 };
 
 use crate::{
     kv::{Entry, Meta, TxnTs, ValuePointer},
-    util::{log_file::LogFile, DBFileId},
+    util::{log_file::LogFile, DBFileId, VlogId},
 };
 
-use super::header::VlogEntryHeader;
+use super::{header::VlogEntryHeader, ValueLog};
 use anyhow::bail;
-use bytes::Buf;
+use bytes::{Buf, Bytes};
 #[derive(Debug)]
 pub(crate) struct LogFileIter<'a, F: DBFileId> {
     log_file: &'a LogFile<F>,
@@ -40,7 +41,7 @@ impl<'a, F: DBFileId> LogFileIter<'a, F> {
 
         let entry_header = VlogEntryHeader::decode_from(&mut hash_reader)?;
         let header_len = hash_reader.len;
-        if entry_header.key_len() > 1 << 16 as u32 {
+        if entry_header.key_len() > 1 << 16_u32 {
             return Err(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
                 "key length must be below u16",
@@ -53,7 +54,7 @@ impl<'a, F: DBFileId> LogFileIter<'a, F> {
         let mut kv_buf = vec![0; key_len + value_len];
         hash_reader.read_exact(&mut kv_buf)?;
 
-        if kv_buf.len() == 0 {
+        if kv_buf.is_empty() {
             return Err(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
                 "kv len can't be zero",
@@ -71,7 +72,7 @@ impl<'a, F: DBFileId> LogFileIter<'a, F> {
             header_len,
         );
         let hash = hash_reader.hasher.finalize();
-        let mut crc_buf = (0 as u32).to_be_bytes();
+        let mut crc_buf = 0_u32.to_be_bytes();
         hash_reader.reader.read_exact(&mut crc_buf)?;
 
         let crc = crc_buf.as_slice().get_u32();
@@ -116,6 +117,7 @@ impl<'a, F: DBFileId> LogFileIter<'a, F> {
                         if last_commit != TxnTs::default() {
                             break;
                         }
+                        self.entries_vptrs.push((entry, v_ptr));
                         self.valid_end_offset = self.record_offset;
                         return Ok(Some(&self.entries_vptrs));
                     }
@@ -138,6 +140,63 @@ impl<'a, F: DBFileId> LogFileIter<'a, F> {
     }
 }
 
+impl ValueLog {
+    /// read the value of entry which is pointed by vptr
+    pub(crate) async fn read_value(&self, vptr: &ValuePointer) -> anyhow::Result<Bytes> {
+        let fid: VlogId = vptr.fid().into();
+        let fid_logfile_r = self.fid_logfile.read().await;
+        let log_file = match fid_logfile_r.get(&fid) {
+            Some(s) => s.clone(),
+            None => bail!("Failed to get vlog file {:?} for {:?}", fid, vptr),
+        };
+        drop(fid_logfile_r);
+
+        let log_file_r = log_file.read().await;
+        let offset = vptr.offset() as usize;
+        let buf = log_file_r
+            .read_slice_ref(offset, vptr.len() as usize)
+            .map_err(|e| anyhow::anyhow!("Failed to read {:?} from vlog for {}", vptr, e))?;
+        log_file_r.decode_value(buf, offset)
+    }
+}
+impl<F: DBFileId> LogFile<F> {
+    // +--------+-----+-------+-------+
+    // | header | key | value | crc32 |
+    // +--------+-----+-------+-------+
+    pub(crate) fn decode_value(&self, buf: &[u8], offset: usize) -> anyhow::Result<Bytes> {
+        let crc_len = mem::size_of::<u32>();
+        if buf.len() <= crc_len {
+            bail!("Invalid length of vlog entry: {}", buf.len());
+        }
+        let (data, mut crc_buf) = buf.split_at(buf.len() - crc_len);
+        if crc32fast::hash(data) != crc_buf.get_u32() {
+            bail!(
+                "Failed to checksum crc32 for vlog entry at offset {}",
+                offset
+            );
+        }
+
+        let (header, header_len) = VlogEntryHeader::decode(data);
+        let kv_buf = &data[header_len..];
+        let kv_buf = match self.try_decrypt(kv_buf, offset) {
+            Some(s) => Bytes::from(s),
+            None => Bytes::copy_from_slice(kv_buf),
+        };
+
+        let key_len = header.key_len() as usize;
+        let value_len = header.value_len() as usize;
+        if kv_buf.len() < key_len + value_len {
+            bail!(
+                "Invalid vlog entry at offset {}, expected kv len {} but got {}",
+                offset,
+                key_len + value_len,
+                kv_buf.len()
+            );
+        }
+        Ok(kv_buf.slice(key_len..key_len + value_len))
+    }
+}
+
 pub(crate) struct HashReader<'a, B: BufRead, T: Hasher> {
     reader: &'a mut BufReader<B>,
     hasher: T,
@@ -152,3 +211,43 @@ impl<B: BufRead, T: Hasher> Read for HashReader<'_, B, T> {
         Ok(size)
     }
 }
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bytes::Bytes;
+
+    use crate::{
+        key_registry::KeyRegistryConfig, kv::Entry, memtable::MemTableConfig,
+        vlog::VLOG_HEADER_SIZE,
+    };
+
+    use super::LogFileIter;
+
+    #[tokio::test]
+    async fn test_iter_entries_without_txn() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut key_registry_config = KeyRegistryConfig::default();
+        key_registry_config.set_dir(dir.path().to_path_buf());
+        let key_registry = key_registry_config.open().await?;
+        let mut memtable_config = MemTableConfig::default();
+        memtable_config.set_dir(dir.path().to_path_buf());
+        let mut memtable = memtable_config.new(&key_registry).await?;
+        for key in ["a", "b"] {
+            let mut e = Entry::new(Bytes::from(key), Bytes::from("v"));
+            e.set_version(1.into());
+            memtable.push(&e)?;
+        }
+        memtable.wal_mut().flush()?;
+
+        // an entry without txn meta is returned by itself.
+        let mut iter = LogFileIter::new(memtable.wal(), VLOG_HEADER_SIZE);
+        for key in ["a", "b"] {
+            let entries = iter.next()?.unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(&entries[0].0.key()[..], key.as_bytes());
+        }
+        assert!(iter.next()?.is_none());
+        Ok(())
+    }
+}
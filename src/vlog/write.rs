@@ -33,6 +33,7 @@ impl<T: Hasher> Write for HashWriter<'_, T> {
 impl ValueLog {
     pub(crate) async fn write(&self, reqs: &mut Vec<WriteReq>) -> anyhow::Result<()> {
         self.validate_write(reqs)?;
+        let sync = self.config.sync_writes || reqs.iter().any(|req| req.sync());
 
         let mut buf = Vec::with_capacity(DEFAULT_PAGE_SIZE.to_owned());
         #[cfg(feature = "metrics")]
@@ -61,12 +62,12 @@ impl ValueLog {
                 let tmp_meta = dec_entry.meta();
                 dec_entry.meta_mut().remove(Meta::TXN);
                 dec_entry.meta_mut().remove(Meta::FIN_TXN);
-                let len = cur_logfile_w.encode_entry(&mut buf, &dec_entry, offset);
+                let len = cur_logfile_w.encode_entry(&mut buf, dec_entry, offset);
 
                 dec_entry.set_meta(tmp_meta);
                 *vptr = ValuePointer::new(fid.into(), len, offset);
 
-                if buf.len() != 0 {
+                if !buf.is_empty() {
                     let buf_len = buf.len();
                     let start_offset = self.writable_log_offset_fetch_add(buf_len);
                     let end_offset = start_offset + buf_len;
@@ -91,14 +92,15 @@ impl ValueLog {
 
             self.num_entries_written
                 .fetch_add(written, Ordering::SeqCst);
+            // read_value reads from mmap, so the buffered writes must be visible there.
+            cur_logfile_w.flush_buf()?;
 
             let w_offset = self.writable_log_offset();
             if w_offset > self.config.vlog_file_size
                 || self.num_entries_written.load(Ordering::SeqCst) > self.config.vlog_max_entries
             {
-                if self.config.sync_writes {
-                    cur_logfile_w.raw_sync()?;
-                }
+                // always sync the rotated file, DB::sync only syncs the latest one.
+                cur_logfile_w.raw_sync()?;
                 cur_logfile_w.truncate(w_offset)?;
                 let new = self.create_vlog_file().await?; //new logfile will be latest logfile
                 drop(cur_logfile_w);
@@ -111,11 +113,11 @@ impl ValueLog {
         if w_offset > self.config.vlog_file_size
             || self.num_entries_written.load(Ordering::SeqCst) > self.config.vlog_max_entries
         {
-            if self.config.sync_writes {
-                cur_logfile_w.raw_sync()?;
-            }
+            cur_logfile_w.raw_sync()?;
             cur_logfile_w.truncate(w_offset)?;
             let _ = self.create_vlog_file().await?; //new logfile will be latest logfile
+        } else if sync {
+            cur_logfile_w.raw_sync()?;
         };
         Ok(())
     }
@@ -159,7 +161,7 @@ impl ValueLog {
 impl<F: DBFileId> LogFile<F> {
     pub(crate) fn encode_entry(&self, buf: &mut Vec<u8>, entry: &Entry, offset: usize) -> usize {
         buf.clear();
-        let header = VlogEntryHeader::new(&entry);
+        let header = VlogEntryHeader::new(entry);
         let mut hash_writer = HashWriter {
             writer: buf,
             hasher: crc32fast::Hasher::new(),
@@ -10,10 +10,11 @@ use std::{
 #[cfg(feature = "metrics")]
 use crate::util::metrics::{add_num_bytes_written_user, add_num_puts, set_pending_writes};
 use crate::{
-    db::DB,
+    db::{WeakDB, DB},
     default::KV_WRITES_ENTRIES_CHANNEL_CAPACITY,
     errors::DBError,
-    kv::{Entry, Meta, ValuePointer},
+    kv::{Entry, Meta, TxnTs, ValuePointer},
+    txn::WriteOptions,
     util::closer::Closer,
 };
 use anyhow::anyhow;
@@ -32,6 +33,10 @@ pub(crate) struct WriteReq {
     entries_vptrs: Vec<(Entry, ValuePointer)>,
     result: anyhow::Result<()>,
     send_result: Option<oneshot::Sender<anyhow::Result<()>>>,
+    // sync the wal and vlog before sending the result.
+    sync: bool,
+    // the commit ts of the txn, marked done by the writer before sending the result.
+    commit_ts: Option<TxnTs>,
 }
 
 impl WriteReq {
@@ -47,9 +52,26 @@ impl WriteReq {
             entries_vptrs: p,
             send_result: send_result.into(),
             result: Ok(()),
+            sync: false,
+            commit_ts: None,
         }
     }
 
+    #[inline]
+    pub(crate) fn sync(&self) -> bool {
+        self.sync
+    }
+
+    #[inline]
+    pub(crate) fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+
+    #[inline]
+    pub(crate) fn set_commit_ts(&mut self, commit_ts: TxnTs) {
+        self.commit_ts = Some(commit_ts);
+    }
+
     #[inline]
     pub(crate) fn entries_vptrs_mut(&mut self) -> &mut Vec<(Entry, ValuePointer)> {
         &mut self.entries_vptrs
@@ -77,6 +99,8 @@ impl DB {
         &self,
         entries: Vec<Entry>,
         entries_size: usize,
+        write_options: WriteOptions,
+        commit_ts: Option<TxnTs>,
     ) -> anyhow::Result<oneshot::Receiver<anyhow::Result<()>>> {
         if self.block_writes.load(Ordering::SeqCst) {
             bail!(DBError::BlockedWrites)
@@ -85,14 +109,22 @@ impl DB {
         #[cfg(feature = "metrics")]
         add_num_bytes_written_user(entries_size);
         let (send_result, receiver) = oneshot::channel::<anyhow::Result<()>>();
-        let w_req = WriteReq::new(entries, send_result);
+        let mut w_req = WriteReq::new(entries, send_result);
+        w_req.set_sync(write_options.sync());
+        if let Some(commit_ts) = commit_ts {
+            w_req.set_commit_ts(commit_ts);
+        }
         self.send_write_req.send(w_req).await?;
         #[cfg(feature = "metrics")]
         add_num_puts(entires_len);
         Ok(receiver)
     }
     #[inline]
-    pub(crate) async fn do_writes(self, mut recv_write_req: Receiver<WriteReq>, closer: Closer) {
+    pub(crate) async fn do_writes(
+        db: WeakDB,
+        mut recv_write_req: Receiver<WriteReq>,
+        closer: Closer,
+    ) {
         defer!(
           closer.done();
         );
@@ -100,15 +132,30 @@ impl DB {
         let notify_recv = notify_send.clone();
         notify_send.notify_one();
         let mut write_reqs = Vec::with_capacity(10);
-        async fn write_requests(db: DB, write_reqs: Vec<WriteReq>, notify_send: Arc<Notify>) {
-            if let Err(e) = db.write_requests(write_reqs).await {
-                error!("write Requests: {}", e);
+        async fn write_requests(
+            db: WeakDB,
+            mut write_reqs: Vec<WriteReq>,
+            notify_send: Arc<Notify>,
+        ) {
+            match db.upgrade() {
+                Some(db) => {
+                    if let Err(e) = db.write_requests(write_reqs).await {
+                        error!("write Requests: {}", e);
+                    }
+                }
+                None => write_reqs
+                    .iter_mut()
+                    .for_each(|r| r.set_result(Err(DBError::DBClosed.into()))),
             }
             notify_send.notify_one();
         }
         let req_len = Arc::new(AtomicUsize::new(0));
         #[cfg(feature = "metrics")]
-        set_pending_writes(self.opt.memtable.dir().clone(), req_len.clone()).await;
+        {
+            if let Some(db) = db.upgrade() {
+                set_pending_writes(db.opt.memtable.dir().clone(), req_len.clone()).await;
+            }
+        }
         loop {
             select! {
                 Some(write_req)=recv_write_req.recv()=>{
@@ -116,22 +163,19 @@ impl DB {
                     req_len.store(write_reqs.len(), Ordering::Relaxed);
                 },
                 _= closer.captured()=>{
-                    while let Some(w) = recv_write_req.recv().await {
+                    // the writes are blocked, so drain the requests already sent.
+                    while let Ok(w) = recv_write_req.try_recv() {
                         write_reqs.push(w);
                     }
                     notify_recv.notified().await;
-                    write_requests(self.clone(), write_reqs, notify_send.clone()).await;
+                    write_requests(db.clone(), write_reqs, notify_send.clone()).await;
                     return ;
                 }
             }
             'a: loop {
                 if write_reqs.len() >= 3 * KV_WRITES_ENTRIES_CHANNEL_CAPACITY {
                     notify_recv.notified().await;
-                    tokio::spawn(write_requests(
-                        self.clone(),
-                        write_reqs,
-                        notify_send.clone(),
-                    ));
+                    tokio::spawn(write_requests(db.clone(), write_reqs, notify_send.clone()));
                     write_reqs = Vec::with_capacity(10);
                     req_len.store(0, Ordering::Relaxed);
                     break 'a;
@@ -142,17 +186,17 @@ impl DB {
                         req_len.store(write_reqs.len(), Ordering::Relaxed);
                     },
                     _=notify_recv.notified()=>{
-                        tokio::spawn(write_requests(self.clone(), write_reqs, notify_send.clone()));
+                        tokio::spawn(write_requests(db.clone(), write_reqs, notify_send.clone()));
                         write_reqs=Vec::with_capacity(10);
                         req_len.store(0, Ordering::Relaxed);
                         break 'a;
                     }
                     _= closer.captured()=>{
-                        while let Some(w) = recv_write_req.recv().await {
+                        while let Ok(w) = recv_write_req.try_recv() {
                             write_reqs.push(w);
                         }
                         notify_recv.notified().await;
-                        write_requests(self.clone(), write_reqs, notify_send.clone()).await;
+                        write_requests(db.clone(), write_reqs, notify_send.clone()).await;
                         return ;
                     }
                 }
@@ -160,9 +204,29 @@ impl DB {
         }
     }
     async fn write_requests(&self, mut reqs: Vec<WriteReq>) -> anyhow::Result<()> {
-        if reqs.len() == 0 {
+        if reqs.is_empty() {
             return Ok(());
         }
+        let result = self.write_entries(&mut reqs).await;
+        // the commits are done whether written or not, before the results are sent.
+        let mut done_result = Ok(());
+        for req in reqs.iter_mut() {
+            if let Some(commit_ts) = req.commit_ts.take() {
+                if let Err(e) = self.oracle.done_commit(commit_ts).await {
+                    // the later commits are still done, only the first error is kept.
+                    if done_result.is_ok() {
+                        done_result = Err(e);
+                    }
+                }
+            }
+        }
+        if result.is_ok() {
+            debug!("Sending updates to subscribers");
+            self.publisher.send_updates(reqs).await;
+        }
+        result.and(done_result)
+    }
+    async fn write_entries(&self, reqs: &mut Vec<WriteReq>) -> anyhow::Result<()> {
         debug!("write_requests called. Writing to value log");
         let handle_err = |e: anyhow::Error, reqs: &mut Vec<WriteReq>| {
             let e = Arc::new(e);
@@ -170,15 +234,15 @@ impl DB {
                 .for_each(|x| x.set_result(Err(anyhow!(e.clone()))));
             e
         };
-        if let Err(e) = self.vlog.write(&mut reqs).await {
-            bail!(handle_err(e, &mut reqs));
+        if let Err(e) = self.vlog.write(reqs).await {
+            bail!(handle_err(e, reqs));
         };
 
         debug!("Writing to memtable");
         let mut count = 0;
         let mut err = None;
         for req in reqs.iter_mut() {
-            if req.entries_vptrs().len() == 0 {
+            if req.entries_vptrs().is_empty() {
                 continue;
             }
             count += req.entries_vptrs().len();
@@ -192,10 +256,8 @@ impl DB {
             };
         }
         if let Some(e) = err {
-            bail!(handle_err(e, &mut reqs));
+            bail!(handle_err(e, reqs));
         }
-        debug!("Sending updates to subscribers");
-        self.publisher.send_updates(reqs).await;
         debug!("{} entries written", count);
         Ok(())
     }
@@ -213,8 +275,10 @@ impl DB {
         let new_memtable = self.opt.memtable.new(&self.key_registry).await?;
 
         let mut memtable_w = memtable.write().await;
-        let old_memtable = replace(&mut *memtable_w, new_memtable);
+        let mut old_memtable = replace(&mut *memtable_w, new_memtable);
         drop(memtable_w);
+        // DB::sync only flushes the wal of the mutable memtable.
+        old_memtable.wal_mut().flush()?;
 
         let old_memtable = Arc::new(old_memtable);
         self.flush_memtable.send(old_memtable.clone()).await?;
@@ -230,17 +294,62 @@ impl DB {
         for (entry, vptr) in req.entries_vptrs_mut() {
             if vptr.is_empty() {
                 entry.meta_mut().remove(Meta::VALUE_POINTER);
+                memtable_w.push(entry)?;
             } else {
+                // keep the value in the entry for the subscribers.
+                let mut entry = entry.clone();
                 entry.meta_mut().insert(Meta::VALUE_POINTER);
                 entry.set_value(vptr.serialize());
+                memtable_w.push(&entry)?;
             }
-            memtable_w.push(&entry)?;
         }
 
-        if self.opt.sync_writes() {
+        if self.opt.sync_writes() || req.sync() {
             memtable_w.wal_mut().flush()?;
         }
         drop(memtable_w);
         Ok(())
     }
 }
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::db::{tests::test_config, DB};
+
+    #[tokio::test]
+    async fn test_commit_async() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = test_config(dir.path());
+        let db = DB::open(config).await?;
+
+        let mut handles = Vec::new();
+        for i in 0..10 {
+            let mut txn = db.get_update_txn().await?;
+            txn.set(Bytes::from(format!("key{i}")), Bytes::from(format!("{i}")))
+                .await?;
+            handles.push(txn.commit_async().await?);
+            txn.discard().await?;
+        }
+        let commit_ts = handles.iter().map(|h| h.commit_ts()).collect::<Vec<_>>();
+        assert!(commit_ts.windows(2).all(|w| w[0] < w[1]));
+        for handle in handles {
+            handle.wait().await?;
+        }
+
+        // the handle is dropped without waiting, the commit should still be visible.
+        let mut txn = db.get_update_txn().await?;
+        txn.set(Bytes::from("dropped"), Bytes::from("v")).await?;
+        drop(txn.commit_async().await?);
+        txn.discard().await?;
+
+        let txn = db.get_update_txn().await?;
+        for i in 0..10 {
+            let item = txn.get(format!("key{i}")).await?;
+            assert_eq!(item.value().await?, &Bytes::from(format!("{i}")));
+        }
+        assert_eq!(txn.get("dropped").await?.value().await?, &Bytes::from("v"));
+        txn.discard().await?;
+        Ok(())
+    }
+}